@@ -0,0 +1,10 @@
+-- Bank attributes needed on transfer orders
+ALTER TABLE banque
+    ADD COLUMN IF NOT EXISTS code_banque CHAR(3),
+    ADD COLUMN IF NOT EXISTS swift_bic VARCHAR(11),
+    ADD COLUMN IF NOT EXISTS agence VARCHAR(255);
+
+ALTER TABLE banque
+    ADD CONSTRAINT banque_code_banque_format CHECK (code_banque ~ '^[0-9]{3}$');
+
+CREATE UNIQUE INDEX IF NOT EXISTS banque_code_banque_key ON banque (code_banque);
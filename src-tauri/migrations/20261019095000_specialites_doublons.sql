@@ -0,0 +1,15 @@
+-- Legacy databases may hold the same specialty twice under names differing only by
+-- case, accents or spaces, which would make the unique index of the next migration
+-- fail. Every copy but the oldest is renamed "nom (id)" so residents keep their
+-- specialty and its duration; the copies can then be merged with merge_specialites.
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+-- Same normalization as normalize_nom(), which the next migration creates
+UPDATE specialites AS doublon
+SET nom = btrim(doublon.nom) || ' (' || doublon.id_specialite || ')'
+WHERE EXISTS (
+    SELECT 1 FROM specialites AS premiere
+    WHERE premiere.id_specialite < doublon.id_specialite
+      AND lower(public.unaccent(regexp_replace(btrim(premiere.nom), '\s+', ' ', 'g')))
+        = lower(public.unaccent(regexp_replace(btrim(doublon.nom), '\s+', ' ', 'g')))
+);
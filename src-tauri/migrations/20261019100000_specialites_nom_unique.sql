@@ -0,0 +1,13 @@
+-- Specialty names are unique regardless of case, accents and surrounding spaces
+CREATE EXTENSION IF NOT EXISTS unaccent;
+
+-- unaccent() is only STABLE; pinning the dictionary makes the wrapper usable in an index
+CREATE OR REPLACE FUNCTION normalize_nom(nom TEXT) RETURNS TEXT AS $$
+    SELECT lower(public.unaccent('public.unaccent'::regdictionary, regexp_replace(btrim(nom), '\s+', ' ', 'g')))
+$$ LANGUAGE sql IMMUTABLE PARALLEL SAFE STRICT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS specialites_nom_normalized_key ON specialites (normalize_nom(nom));
+
+-- NOT VALID keeps legacy rows loadable while enforcing the range on every insert/update
+ALTER TABLE specialites
+    ADD CONSTRAINT specialites_nombre_annees_range CHECK (nombre_annees BETWEEN 1 AND 7) NOT VALID;
@@ -0,0 +1,16 @@
+-- Salary grid: base salary and indemnities per residency year, optionally per specialty
+CREATE TABLE IF NOT EXISTS bareme (
+    id_bareme SERIAL PRIMARY KEY,
+    annee_residanat INT NOT NULL CHECK (annee_residanat BETWEEN 1 AND 7),
+    id_specialite INT REFERENCES specialites (id_specialite) ON DELETE CASCADE,
+    salaire_base NUMERIC(12, 2) NOT NULL CHECK (salaire_base >= 0),
+    indemnite_fonction NUMERIC(12, 2) NOT NULL DEFAULT 0 CHECK (indemnite_fonction >= 0),
+    indemnite_logement NUMERIC(12, 2) NOT NULL DEFAULT 0 CHECK (indemnite_logement >= 0),
+    indemnite_transport NUMERIC(12, 2) NOT NULL DEFAULT 0 CHECK (indemnite_transport >= 0),
+    -- Always the first day of a month: a grid row applies to whole payment months
+    date_effet DATE NOT NULL CHECK (EXTRACT(DAY FROM date_effet) = 1)
+);
+
+-- One row per year, specialty (or the generic grid) and effective date
+CREATE UNIQUE INDEX IF NOT EXISTS bareme_annee_specialite_date_key
+    ON bareme (annee_residanat, COALESCE(id_specialite, 0), date_effet);
@@ -0,0 +1,8 @@
+-- Breakdown of each monthly payment, computed by the Rust payroll engine.
+-- Legacy rows generated by generate_monthly_payments() keep these columns NULL.
+ALTER TABLE paiement_mensuel
+    ADD COLUMN IF NOT EXISTS id_bareme INT REFERENCES bareme (id_bareme) ON DELETE SET NULL,
+    ADD COLUMN IF NOT EXISTS salaire_base NUMERIC(12, 2),
+    ADD COLUMN IF NOT EXISTS indemnite_fonction NUMERIC(12, 2),
+    ADD COLUMN IF NOT EXISTS indemnite_logement NUMERIC(12, 2),
+    ADD COLUMN IF NOT EXISTS indemnite_transport NUMERIC(12, 2);
@@ -0,0 +1,40 @@
+-- One payroll run per month, holding the payments generated for it
+CREATE TABLE IF NOT EXISTS payroll_run (
+    id_run SERIAL PRIMARY KEY,
+    annee INT NOT NULL,
+    mois INT NOT NULL CHECK (mois BETWEEN 1 AND 12),
+    statut VARCHAR(20) NOT NULL DEFAULT 'brouillon',
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    nombre_paiements INT NOT NULL DEFAULT 0,
+    total_montant NUMERIC(14, 2) NOT NULL DEFAULT 0,
+    total_allocations NUMERIC(14, 2) NOT NULL DEFAULT 0,
+    CONSTRAINT payroll_run_periode_key UNIQUE (annee, mois)
+);
+
+-- Every creation or regeneration of a run, with who did it and why
+CREATE TABLE IF NOT EXISTS payroll_run_audit (
+    id_audit SERIAL PRIMARY KEY,
+    id_run INT NOT NULL REFERENCES payroll_run (id_run) ON DELETE CASCADE,
+    action VARCHAR(30) NOT NULL,
+    auteur VARCHAR(255) NOT NULL,
+    motif TEXT,
+    details TEXT,
+    date_action TIMESTAMP NOT NULL DEFAULT now()
+);
+
+ALTER TABLE paiement_mensuel
+    ADD COLUMN IF NOT EXISTS id_run INT REFERENCES payroll_run (id_run) ON DELETE CASCADE,
+    ADD COLUMN IF NOT EXISTS periode DATE;
+
+UPDATE paiement_mensuel
+SET periode = date_trunc('month', date_paiement)::date
+WHERE periode IS NULL;
+
+ALTER TABLE paiement_mensuel ALTER COLUMN periode SET NOT NULL;
+
+-- Legacy rows from generate_monthly_payments() may already contain duplicates and are left as is;
+-- every payment belonging to a run is unique per resident and month
+CREATE UNIQUE INDEX IF NOT EXISTS paiement_mensuel_resident_periode_key
+    ON paiement_mensuel (id_resident, periode)
+    WHERE id_run IS NOT NULL;
@@ -0,0 +1,83 @@
+-- Roles of the application users; existing accounts keep full access
+ALTER TABLE admin
+    ADD COLUMN IF NOT EXISTS role VARCHAR(20) NOT NULL DEFAULT 'admin'
+    CHECK (role IN ('admin', 'gestionnaire', 'validateur', 'tresorier'));
+
+ALTER TABLE admin ALTER COLUMN role SET DEFAULT 'gestionnaire';
+
+-- Run lifecycle: brouillon -> valide -> envoye -> paye
+ALTER TABLE payroll_run
+    ADD CONSTRAINT payroll_run_statut_check CHECK (statut IN ('brouillon', 'valide', 'envoye', 'paye'));
+
+-- Audit entries outlive cancelled runs
+ALTER TABLE payroll_run_audit
+    ADD COLUMN IF NOT EXISTS annee INT,
+    ADD COLUMN IF NOT EXISTS mois INT;
+
+UPDATE payroll_run_audit
+SET annee = payroll_run.annee, mois = payroll_run.mois
+FROM payroll_run
+WHERE payroll_run_audit.id_run = payroll_run.id_run;
+
+ALTER TABLE payroll_run_audit
+    ALTER COLUMN annee SET NOT NULL,
+    ALTER COLUMN mois SET NOT NULL,
+    ALTER COLUMN id_run DROP NOT NULL,
+    DROP CONSTRAINT IF EXISTS payroll_run_audit_id_run_fkey,
+    ADD CONSTRAINT payroll_run_audit_id_run_fkey
+        FOREIGN KEY (id_run) REFERENCES payroll_run (id_run) ON DELETE SET NULL;
+
+-- Only draft runs can be deleted (cancelled)
+CREATE OR REPLACE FUNCTION payroll_run_verrou() RETURNS trigger AS $$
+BEGIN
+    IF OLD.statut <> 'brouillon' THEN
+        RAISE EXCEPTION 'Le run de paie %/% est % et ne peut pas être supprimé', OLD.mois, OLD.annee, OLD.statut;
+    END IF;
+    RETURN OLD;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS payroll_run_verrou ON payroll_run;
+CREATE TRIGGER payroll_run_verrou
+    BEFORE DELETE ON payroll_run
+    FOR EACH ROW EXECUTE FUNCTION payroll_run_verrou();
+
+-- Payments of a run that left the draft state are frozen: no insert, no delete,
+-- and no update of the fields that define what is paid to whom
+CREATE OR REPLACE FUNCTION paiement_mensuel_verrou() RETURNS trigger AS $$
+DECLARE
+    statut_run VARCHAR(20);
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        SELECT statut INTO statut_run FROM payroll_run WHERE id_run = NEW.id_run;
+        IF statut_run IS NOT NULL AND statut_run <> 'brouillon' THEN
+            RAISE EXCEPTION 'Le run de paie est % : aucun paiement ne peut y être ajouté', statut_run;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    SELECT statut INTO statut_run FROM payroll_run WHERE id_run = OLD.id_run;
+    IF statut_run IS NULL OR statut_run = 'brouillon' THEN
+        RETURN COALESCE(NEW, OLD);
+    END IF;
+
+    IF TG_OP = 'DELETE' OR
+        (OLD.id_run, OLD.id_resident, OLD.periode, OLD.date_paiement, OLD.jours_travail, OLD.montant,
+         OLD.allocations_familiales, OLD.salaire_base, OLD.indemnite_fonction, OLD.indemnite_logement,
+         OLD.indemnite_transport)
+        IS DISTINCT FROM
+        (NEW.id_run, NEW.id_resident, NEW.periode, NEW.date_paiement, NEW.jours_travail, NEW.montant,
+         NEW.allocations_familiales, NEW.salaire_base, NEW.indemnite_fonction, NEW.indemnite_logement,
+         NEW.indemnite_transport)
+    THEN
+        RAISE EXCEPTION 'Le paiement % est verrouillé : le run de paie est %', OLD.id_paiement, statut_run;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS paiement_mensuel_verrou ON paiement_mensuel;
+CREATE TRIGGER paiement_mensuel_verrou
+    BEFORE INSERT OR UPDATE OR DELETE ON paiement_mensuel
+    FOR EACH ROW EXECUTE FUNCTION paiement_mensuel_verrou();
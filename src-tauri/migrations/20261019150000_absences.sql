@@ -0,0 +1,14 @@
+-- Absences, unpaid leave and suspensions: days not paid by the payroll run
+CREATE TABLE IF NOT EXISTS absences (
+    id_absence SERIAL PRIMARY KEY,
+    id_resident INT NOT NULL REFERENCES residents (id_resident) ON DELETE CASCADE,
+    type_absence VARCHAR(30) NOT NULL CHECK (type_absence IN ('absence', 'conge_sans_solde', 'suspension')),
+    date_debut DATE NOT NULL,
+    date_fin DATE NOT NULL,
+    motif TEXT,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT now(),
+    CHECK (date_fin >= date_debut)
+);
+
+CREATE INDEX IF NOT EXISTS absences_resident_dates_idx ON absences (id_resident, date_debut, date_fin);
@@ -0,0 +1,111 @@
+-- Contribution rates (CMR or CNSS, AMO, mutuelle), versioned by effective date.
+-- Rates are percentages; `assiette` is the base they apply to.
+CREATE TABLE IF NOT EXISTS taux_cotisation (
+    id_taux SERIAL PRIMARY KEY,
+    code VARCHAR(20) NOT NULL,
+    libelle VARCHAR(255) NOT NULL,
+    taux NUMERIC(6, 3) NOT NULL CHECK (taux >= 0 AND taux <= 100),
+    assiette VARCHAR(20) NOT NULL CHECK (assiette IN ('salaire_base', 'brut')),
+    plafond NUMERIC(12, 2) CHECK (plafond > 0),
+    date_effet DATE NOT NULL,
+    CONSTRAINT taux_cotisation_code_date_key UNIQUE (code, date_effet)
+);
+
+-- Annual income tax brackets; all brackets sharing a date_effet form one scale
+CREATE TABLE IF NOT EXISTS tranche_ir (
+    id_tranche SERIAL PRIMARY KEY,
+    revenu_min NUMERIC(12, 2) NOT NULL CHECK (revenu_min >= 0),
+    revenu_max NUMERIC(12, 2),
+    taux NUMERIC(6, 3) NOT NULL CHECK (taux >= 0 AND taux <= 100),
+    somme_a_deduire NUMERIC(12, 2) NOT NULL DEFAULT 0,
+    date_effet DATE NOT NULL,
+    CHECK (revenu_max IS NULL OR revenu_max > revenu_min),
+    CONSTRAINT tranche_ir_min_date_key UNIQUE (revenu_min, date_effet)
+);
+
+-- Family deductions and professional expenses allowance used by the IR computation
+CREATE TABLE IF NOT EXISTS parametre_ir (
+    id_parametre SERIAL PRIMARY KEY,
+    deduction_par_charge NUMERIC(12, 2) NOT NULL CHECK (deduction_par_charge >= 0),
+    max_charges INT NOT NULL CHECK (max_charges >= 0),
+    taux_frais_pro NUMERIC(6, 3) NOT NULL CHECK (taux_frais_pro >= 0 AND taux_frais_pro <= 100),
+    plafond_frais_pro NUMERIC(12, 2) NOT NULL CHECK (plafond_frais_pro >= 0),
+    date_effet DATE NOT NULL UNIQUE
+);
+
+-- Every earning and deduction of a payment, as printed on the payslip
+CREATE TABLE IF NOT EXISTS lignes_paiement (
+    id_ligne SERIAL PRIMARY KEY,
+    id_paiement INT NOT NULL REFERENCES paiement_mensuel (id_paiement) ON DELETE CASCADE,
+    ordre INT NOT NULL,
+    code VARCHAR(20) NOT NULL,
+    libelle VARCHAR(255) NOT NULL,
+    categorie VARCHAR(10) NOT NULL CHECK (categorie IN ('gain', 'retenue')),
+    base NUMERIC(12, 2),
+    taux NUMERIC(6, 3),
+    montant NUMERIC(12, 2) NOT NULL
+);
+
+CREATE INDEX IF NOT EXISTS lignes_paiement_paiement_idx ON lignes_paiement (id_paiement);
+
+ALTER TABLE paiement_mensuel
+    ADD COLUMN IF NOT EXISTS salaire_brut NUMERIC(12, 2),
+    ADD COLUMN IF NOT EXISTS total_retenues NUMERIC(12, 2);
+
+-- Extend the payment lock to the new amount columns
+CREATE OR REPLACE FUNCTION paiement_mensuel_verrou() RETURNS trigger AS $$
+DECLARE
+    statut_run VARCHAR(20);
+BEGIN
+    IF TG_OP = 'INSERT' THEN
+        SELECT statut INTO statut_run FROM payroll_run WHERE id_run = NEW.id_run;
+        IF statut_run IS NOT NULL AND statut_run <> 'brouillon' THEN
+            RAISE EXCEPTION 'Le run de paie est % : aucun paiement ne peut y être ajouté', statut_run;
+        END IF;
+        RETURN NEW;
+    END IF;
+
+    SELECT statut INTO statut_run FROM payroll_run WHERE id_run = OLD.id_run;
+    IF statut_run IS NULL OR statut_run = 'brouillon' THEN
+        RETURN COALESCE(NEW, OLD);
+    END IF;
+
+    IF TG_OP = 'DELETE' OR
+        (OLD.id_run, OLD.id_resident, OLD.periode, OLD.date_paiement, OLD.jours_travail, OLD.montant,
+         OLD.allocations_familiales, OLD.salaire_base, OLD.indemnite_fonction, OLD.indemnite_logement,
+         OLD.indemnite_transport, OLD.salaire_brut, OLD.total_retenues)
+        IS DISTINCT FROM
+        (NEW.id_run, NEW.id_resident, NEW.periode, NEW.date_paiement, NEW.jours_travail, NEW.montant,
+         NEW.allocations_familiales, NEW.salaire_base, NEW.indemnite_fonction, NEW.indemnite_logement,
+         NEW.indemnite_transport, NEW.salaire_brut, NEW.total_retenues)
+    THEN
+        RAISE EXCEPTION 'Le paiement % est verrouillé : le run de paie est %', OLD.id_paiement, statut_run;
+    END IF;
+
+    RETURN NEW;
+END;
+$$ LANGUAGE plpgsql;
+
+-- Lines follow the lock of their payment
+CREATE OR REPLACE FUNCTION lignes_paiement_verrou() RETURNS trigger AS $$
+DECLARE
+    statut_run VARCHAR(20);
+BEGIN
+    SELECT payroll_run.statut INTO statut_run
+    FROM paiement_mensuel
+    JOIN payroll_run ON paiement_mensuel.id_run = payroll_run.id_run
+    WHERE paiement_mensuel.id_paiement = COALESCE(NEW.id_paiement, OLD.id_paiement);
+
+    IF statut_run IS NOT NULL AND statut_run <> 'brouillon' THEN
+        RAISE EXCEPTION 'Les lignes du paiement % sont verrouillées : le run de paie est %',
+            COALESCE(NEW.id_paiement, OLD.id_paiement), statut_run;
+    END IF;
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS lignes_paiement_verrou ON lignes_paiement;
+CREATE TRIGGER lignes_paiement_verrou
+    BEFORE INSERT OR UPDATE OR DELETE ON lignes_paiement
+    FOR EACH ROW EXECUTE FUNCTION lignes_paiement_verrou();
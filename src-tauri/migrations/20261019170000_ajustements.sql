@@ -0,0 +1,41 @@
+-- One-off bonuses, on-call allowances and withholdings, applied by the payroll run of their month
+CREATE TABLE IF NOT EXISTS ajustements (
+    id_ajustement SERIAL PRIMARY KEY,
+    id_resident INT NOT NULL REFERENCES residents (id_resident) ON DELETE CASCADE,
+    periode DATE NOT NULL CHECK (EXTRACT(DAY FROM periode) = 1),
+    type_ajustement VARCHAR(20) NOT NULL CHECK (type_ajustement IN ('prime', 'garde', 'retenue')),
+    montant NUMERIC(12, 2) NOT NULL CHECK (montant > 0),
+    motif TEXT NOT NULL,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    id_paiement INT REFERENCES paiement_mensuel (id_paiement) ON DELETE SET NULL
+);
+
+CREATE INDEX IF NOT EXISTS ajustements_periode_idx ON ajustements (periode, id_resident);
+
+-- An adjustment paid by a locked run can no longer change
+CREATE OR REPLACE FUNCTION ajustements_verrou() RETURNS trigger AS $$
+DECLARE
+    statut_run VARCHAR(20);
+BEGIN
+    IF TG_OP = 'INSERT' OR OLD.id_paiement IS NULL THEN
+        RETURN COALESCE(NEW, OLD);
+    END IF;
+
+    SELECT payroll_run.statut INTO statut_run
+    FROM paiement_mensuel
+    JOIN payroll_run ON paiement_mensuel.id_run = payroll_run.id_run
+    WHERE paiement_mensuel.id_paiement = OLD.id_paiement;
+
+    IF statut_run IS NOT NULL AND statut_run <> 'brouillon' THEN
+        RAISE EXCEPTION 'L''ajustement % est verrouillé : le run de paie est %', OLD.id_ajustement, statut_run;
+    END IF;
+
+    RETURN COALESCE(NEW, OLD);
+END;
+$$ LANGUAGE plpgsql;
+
+DROP TRIGGER IF EXISTS ajustements_verrou ON ajustements;
+CREATE TRIGGER ajustements_verrou
+    BEFORE UPDATE OR DELETE ON ajustements
+    FOR EACH ROW EXECUTE FUNCTION ajustements_verrou();
@@ -0,0 +1,35 @@
+-- Recovery of overpaid salary, spread over installments deducted from future
+-- payrolls (through a withholding adjustment) or from an annual rappel
+CREATE TABLE IF NOT EXISTS recouvrements (
+    id_recouvrement SERIAL PRIMARY KEY,
+    id_resident INT NOT NULL REFERENCES residents (id_resident) ON DELETE CASCADE,
+    montant_total NUMERIC(12, 2) NOT NULL CHECK (montant_total > 0),
+    imputation VARCHAR(10) NOT NULL CHECK (imputation IN ('paie', 'rappel')),
+    motif TEXT NOT NULL,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW()
+);
+
+-- Overpaid payments covered by a recovery; a payment is recovered at most once
+CREATE TABLE IF NOT EXISTS recouvrement_paiements (
+    id_recouvrement INT NOT NULL REFERENCES recouvrements (id_recouvrement) ON DELETE CASCADE,
+    id_paiement INT NOT NULL UNIQUE REFERENCES paiement_mensuel (id_paiement) ON DELETE CASCADE,
+    trop_percu NUMERIC(12, 2) NOT NULL,
+    PRIMARY KEY (id_recouvrement, id_paiement)
+);
+
+CREATE TABLE IF NOT EXISTS echeances_recouvrement (
+    id_echeance SERIAL PRIMARY KEY,
+    id_recouvrement INT NOT NULL REFERENCES recouvrements (id_recouvrement) ON DELETE CASCADE,
+    rang INT NOT NULL,
+    montant NUMERIC(12, 2) NOT NULL CHECK (montant > 0),
+    periode DATE,
+    exercice INT,
+    id_ajustement INT REFERENCES ajustements (id_ajustement) ON DELETE RESTRICT,
+    id_rappel INT,
+    CHECK ((periode IS NULL) <> (exercice IS NULL)),
+    UNIQUE (id_recouvrement, rang)
+);
+
+ALTER TABLE rappels_annuels
+    ADD COLUMN IF NOT EXISTS retenue_recouvrement NUMERIC(12, 2) NOT NULL DEFAULT 0;
@@ -0,0 +1,6 @@
+-- Layout of the fixed-width bulk-transfer file each bank accepts
+ALTER TABLE banque
+    ADD COLUMN IF NOT EXISTS format_virement VARCHAR(20) NOT NULL DEFAULT 'fixe160';
+
+ALTER TABLE banque
+    ADD CONSTRAINT banque_format_virement_check CHECK (format_virement IN ('fixe160', 'fixe120'));
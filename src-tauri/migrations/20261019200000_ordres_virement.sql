@@ -0,0 +1,15 @@
+-- Transfer orders exported per bank, numbered in sequence within each year.
+-- Exporting the same month again for a bank keeps its number.
+CREATE TABLE IF NOT EXISTS ordres_virement (
+    id_ordre SERIAL PRIMARY KEY,
+    exercice INT NOT NULL,
+    numero INT NOT NULL,
+    periode DATE NOT NULL,
+    id_banque INT NOT NULL REFERENCES banque (id_banque),
+    nombre_virements INT NOT NULL,
+    total NUMERIC(14, 2) NOT NULL,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (exercice, numero),
+    UNIQUE (periode, id_banque)
+);
@@ -0,0 +1,28 @@
+-- Outcome of each transfer as reported by the bank's return files
+ALTER TABLE paiement_mensuel
+    ADD COLUMN IF NOT EXISTS statut_virement VARCHAR(20) NOT NULL DEFAULT 'en_attente',
+    ADD COLUMN IF NOT EXISTS motif_rejet TEXT,
+    ADD COLUMN IF NOT EXISTS date_retour DATE;
+
+ALTER TABLE paiement_mensuel
+    ADD CONSTRAINT paiement_mensuel_statut_virement_check CHECK (statut_virement IN ('en_attente', 'paye', 'rejete'));
+
+ALTER TABLE rappels_annuels
+    ADD COLUMN IF NOT EXISTS statut_virement VARCHAR(20) NOT NULL DEFAULT 'en_attente',
+    ADD COLUMN IF NOT EXISTS motif_rejet TEXT,
+    ADD COLUMN IF NOT EXISTS date_retour DATE;
+
+ALTER TABLE rappels_annuels
+    ADD CONSTRAINT rappels_annuels_statut_virement_check CHECK (statut_virement IN ('en_attente', 'paye', 'rejete'));
+
+-- One row per imported return file
+CREATE TABLE IF NOT EXISTS retours_banque (
+    id_retour SERIAL PRIMARY KEY,
+    fichier VARCHAR(255) NOT NULL,
+    nombre_lignes INT NOT NULL,
+    nombre_payes INT NOT NULL,
+    nombre_rejetes INT NOT NULL,
+    nombre_non_rapproches INT NOT NULL,
+    importe_par VARCHAR(255) NOT NULL,
+    importe_le TIMESTAMP NOT NULL DEFAULT NOW()
+);
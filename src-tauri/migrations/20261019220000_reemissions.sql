@@ -0,0 +1,44 @@
+-- Rejected transfers can be reissued: the original row keeps its rejection
+-- and moves to 'reemis', the new transfer is recorded in reemissions
+ALTER TABLE paiement_mensuel DROP CONSTRAINT IF EXISTS paiement_mensuel_statut_virement_check;
+ALTER TABLE paiement_mensuel
+    ADD CONSTRAINT paiement_mensuel_statut_virement_check CHECK (statut_virement IN ('en_attente', 'paye', 'rejete', 'reemis'));
+
+ALTER TABLE rappels_annuels DROP CONSTRAINT IF EXISTS rappels_annuels_statut_virement_check;
+ALTER TABLE rappels_annuels
+    ADD CONSTRAINT rappels_annuels_statut_virement_check CHECK (statut_virement IN ('en_attente', 'paye', 'rejete', 'reemis'));
+
+-- RIB the bank rejected, as read from the return file
+ALTER TABLE paiement_mensuel ADD COLUMN IF NOT EXISTS rib_rejete VARCHAR(24);
+ALTER TABLE rappels_annuels ADD COLUMN IF NOT EXISTS rib_rejete VARCHAR(24);
+
+-- Reissue orders share the yearly numbering but cover several banks and months
+ALTER TABLE ordres_virement
+    ADD COLUMN IF NOT EXISTS reemission BOOLEAN NOT NULL DEFAULT FALSE,
+    ALTER COLUMN id_banque DROP NOT NULL;
+
+ALTER TABLE ordres_virement DROP CONSTRAINT IF EXISTS ordres_virement_periode_id_banque_key;
+CREATE UNIQUE INDEX IF NOT EXISTS ordres_virement_periode_id_banque_key
+    ON ordres_virement (periode, id_banque) WHERE NOT reemission;
+
+CREATE TABLE IF NOT EXISTS reemissions (
+    id_reemission SERIAL PRIMARY KEY,
+    id_ordre INT NOT NULL REFERENCES ordres_virement (id_ordre),
+    id_paiement INT REFERENCES paiement_mensuel (id_paiement),
+    id_rappel INT REFERENCES rappels_annuels (id_rappel),
+    -- RIB the rejected transfer was sent to, and the one used for the reissue
+    rib_rejete VARCHAR(24) NOT NULL,
+    rib VARCHAR(24) NOT NULL,
+    motif_rejet_initial TEXT,
+    montant NUMERIC(14, 2) NOT NULL,
+    statut_virement VARCHAR(20) NOT NULL DEFAULT 'en_attente'
+        CHECK (statut_virement IN ('en_attente', 'paye', 'rejete')),
+    motif_rejet TEXT,
+    date_retour DATE,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    CHECK ((id_paiement IS NULL) <> (id_rappel IS NULL))
+);
+
+CREATE INDEX IF NOT EXISTS reemissions_id_paiement_idx ON reemissions (id_paiement);
+CREATE INDEX IF NOT EXISTS reemissions_id_rappel_idx ON reemissions (id_rappel);
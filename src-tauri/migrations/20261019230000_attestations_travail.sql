@@ -0,0 +1,36 @@
+-- Templates of work certificates, `{champ}` placeholders being filled from the resident
+CREATE TABLE IF NOT EXISTS modeles_attestation (
+    id_modele SERIAL PRIMARY KEY,
+    nom VARCHAR(100) NOT NULL UNIQUE,
+    titre VARCHAR(255) NOT NULL DEFAULT 'Attestation de travail',
+    corps TEXT NOT NULL
+);
+
+INSERT INTO modeles_attestation (nom, corps) VALUES
+    ('Résident en fonction',
+     'Le Directeur du {employeur} soussigné atteste que le Docteur {nom_prenom} exerce en qualité de médecin résident en {specialite} au sein de l''établissement {periode}.
+
+La présente attestation est délivrée à l''intéressé(e), sur sa demande, pour servir et valoir ce que de droit.'),
+    ('Ancien résident',
+     'Le Directeur du {employeur} soussigné atteste que le Docteur {nom_prenom} a exercé en qualité de médecin résident en {specialite} au sein de l''établissement du {date_debut} au {date_fin}.
+
+La présente attestation est délivrée à l''intéressé(e), sur sa demande, pour servir et valoir ce que de droit.')
+ON CONFLICT (nom) DO NOTHING;
+
+-- Register of issued certificates, numbered from 1 each year. The name and the
+-- text are kept as issued, whatever happens later to the resident or the template.
+CREATE TABLE IF NOT EXISTS attestations_travail (
+    id_attestation SERIAL PRIMARY KEY,
+    exercice INT NOT NULL,
+    numero INT NOT NULL,
+    id_resident INT REFERENCES residents (id_resident) ON DELETE SET NULL,
+    nom_prenom VARCHAR(255) NOT NULL,
+    id_modele INT REFERENCES modeles_attestation (id_modele) ON DELETE SET NULL,
+    en_fonction BOOLEAN NOT NULL,
+    texte TEXT NOT NULL,
+    created_by VARCHAR(255) NOT NULL,
+    created_at TIMESTAMP NOT NULL DEFAULT NOW(),
+    UNIQUE (exercice, numero)
+);
+
+CREATE INDEX IF NOT EXISTS attestations_travail_id_resident_idx ON attestations_travail (id_resident);
@@ -0,0 +1,6 @@
+-- Identity of the resident as required on the annual salary declaration
+ALTER TABLE residents
+    ADD COLUMN IF NOT EXISTS cin VARCHAR(20),
+    ADD COLUMN IF NOT EXISTS adresse TEXT;
+
+CREATE UNIQUE INDEX IF NOT EXISTS residents_cin_key ON residents (cin) WHERE cin IS NOT NULL;
@@ -0,0 +1,17 @@
+-- RIB each transfer was sent to, recorded when its order or bank file is
+-- exported, so that the bank's returns are matched against what was sent
+ALTER TABLE paiement_mensuel ADD COLUMN IF NOT EXISTS rib_envoye VARCHAR(24);
+ALTER TABLE rappels_annuels ADD COLUMN IF NOT EXISTS rib_envoye VARCHAR(24);
+
+-- Transfers already sent and still awaiting their return keep the RIB they
+-- were matched on until now
+UPDATE paiement_mensuel
+SET rib_envoye = residents.rib
+FROM residents, payroll_run
+WHERE paiement_mensuel.id_resident = residents.id_resident
+  AND paiement_mensuel.id_run = payroll_run.id_run
+  AND payroll_run.statut = 'envoye'
+  AND paiement_mensuel.statut_virement = 'en_attente';
+
+-- Rappel orders were never recorded: which pending rappels were actually sent is
+-- unknown, so they are left without a RIB until their order is generated again
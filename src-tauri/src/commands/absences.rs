@@ -0,0 +1,242 @@
+use crate::commands::auth::session_courante;
+use crate::models::{AppState, Absence, NewAbsence};
+use crate::payroll::TYPES_ABSENCE;
+use chrono::NaiveDate;
+use tauri::State;
+use thiserror::Error;
+
+
+//managing absences and unpaid leave
+
+#[derive(Debug, Error)]
+pub enum AbsenceValidationError {
+    #[error("Type d'absence inconnu : {0}")]
+    UnknownType(String),
+    #[error("La date de fin doit être postérieure ou égale à la date de début")]
+    InvalidDates,
+    #[error("L'absence couvre une période dont la paie est verrouillée")]
+    LockedPeriod,
+}
+
+pub(crate) async fn fetch_absences(pool: &sqlx::PgPool, debut: NaiveDate, fin: NaiveDate) -> Result<Vec<Absence>, sqlx::Error> {
+    sqlx::query_as::<_, Absence>(
+        r#"
+        SELECT
+            absences.id_absence,
+            absences.id_resident,
+            residents.nom_prenom as nom_resident,
+            absences.type_absence,
+            absences.date_debut,
+            absences.date_fin,
+            absences.motif
+        FROM absences
+        LEFT JOIN residents ON absences.id_resident = residents.id_resident
+        WHERE absences.date_debut <= $2 AND absences.date_fin >= $1
+        ORDER BY absences.date_debut
+        "#,
+    )
+    .bind(debut)
+    .bind(fin)
+    .fetch_all(pool)
+    .await
+}
+
+fn validate_absence(type_absence: &str, date_debut: NaiveDate, date_fin: NaiveDate) -> Result<(), String> {
+    if !TYPES_ABSENCE.contains(&type_absence) {
+        return Err(AbsenceValidationError::UnknownType(type_absence.to_string()).to_string());
+    }
+
+    if date_fin < date_debut {
+        return Err(AbsenceValidationError::InvalidDates.to_string());
+    }
+
+    Ok(())
+}
+
+// Whether a locked payroll run already paid the resident for a month overlapping [debut, fin]
+pub(crate) async fn periode_verrouillee<'e>(
+    executor: impl sqlx::postgres::PgExecutor<'e>,
+    id_resident: i32,
+    debut: NaiveDate,
+    fin: NaiveDate,
+) -> Result<bool, String> {
+    let record = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM paiement_mensuel
+        LEFT JOIN payroll_run ON paiement_mensuel.id_run = payroll_run.id_run
+        WHERE paiement_mensuel.id_resident = $1
+          AND paiement_mensuel.periode BETWEEN date_trunc('month', $2::date)::date AND $3
+          AND payroll_run.statut IS DISTINCT FROM 'brouillon'
+        "#,
+        id_resident,
+        debut,
+        fin
+    )
+    .fetch_one(executor)
+    .await
+    .map_err(|e| format!("Failed to query database: {}", e))?;
+
+    Ok(record.count > 0)
+}
+
+// Share-locks the runs of the months overlapping [debut, fin] until the transaction
+// ends, so none of them can be validated between a check and the write that follows
+pub(crate) async fn verrouiller_runs(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    debut: NaiveDate,
+    fin: NaiveDate,
+) -> Result<(), String> {
+    sqlx::query!(
+        "SELECT id_run FROM payroll_run
+         WHERE make_date(annee, mois, 1) BETWEEN date_trunc('month', $1::date)::date AND $2
+         FOR SHARE",
+        debut,
+        fin
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to lock payroll runs: {}", e))?;
+
+    Ok(())
+}
+
+// Absences cannot be recorded, moved or removed in months already paid by a locked run
+async fn ensure_period_open(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    id_resident: i32,
+    date_debut: NaiveDate,
+    date_fin: NaiveDate,
+) -> Result<(), String> {
+    verrouiller_runs(tx, date_debut, date_fin).await?;
+    if periode_verrouillee(&mut *tx, id_resident, date_debut, date_fin).await? {
+        return Err(AbsenceValidationError::LockedPeriod.to_string());
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_absences(state: State<'_, AppState>, date_debut: NaiveDate, date_fin: NaiveDate) -> Result<Vec<Absence>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    fetch_absences(pool, date_debut, date_fin)
+        .await
+        .map_err(|e| format!("Failed to fetch absences: {}", e))
+}
+
+#[tauri::command]
+pub async fn add_absence(state: State<'_, AppState>, absence: NewAbsence) -> Result<(), String> {
+    let session = session_courante(&state).await?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_absence(&absence.type_absence, absence.date_debut, absence.date_fin)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    ensure_period_open(&mut tx, absence.id_resident, absence.date_debut, absence.date_fin).await?;
+
+    sqlx::query!(
+        "INSERT INTO absences (id_resident, type_absence, date_debut, date_fin, motif, created_by) VALUES ($1, $2, $3, $4, $5, $6)",
+        absence.id_resident,
+        absence.type_absence,
+        absence.date_debut,
+        absence.date_fin,
+        absence.motif,
+        session.username
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to add absence: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to add absence: {}", e))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn modify_absence(state: State<'_, AppState>, absence: Absence) -> Result<(), String> {
+    session_courante(&state).await?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_absence(&absence.type_absence, absence.date_debut, absence.date_fin)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let current = sqlx::query!(
+        "SELECT id_resident, date_debut, date_fin FROM absences WHERE id_absence = $1 FOR UPDATE",
+        absence.id_absence
+    )
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to fetch absence: {}", e))?
+    .ok_or("Absence introuvable")?;
+
+    // Both the old and the new dates must lie in open periods
+    ensure_period_open(&mut tx, current.id_resident, current.date_debut, current.date_fin).await?;
+    ensure_period_open(&mut tx, absence.id_resident, absence.date_debut, absence.date_fin).await?;
+
+    sqlx::query!(
+        "UPDATE absences SET id_resident = $1, type_absence = $2, date_debut = $3, date_fin = $4, motif = $5 WHERE id_absence = $6",
+        absence.id_resident,
+        absence.type_absence,
+        absence.date_debut,
+        absence.date_fin,
+        absence.motif,
+        absence.id_absence
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to modify absence: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to modify absence: {}", e))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_absence(state: State<'_, AppState>, id_absence: i32) -> Result<(), String> {
+    session_courante(&state).await?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let current = sqlx::query!(
+        "SELECT id_resident, date_debut, date_fin FROM absences WHERE id_absence = $1 FOR UPDATE",
+        id_absence
+    )
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to fetch absence: {}", e))?
+    .ok_or("Absence introuvable")?;
+
+    ensure_period_open(&mut tx, current.id_resident, current.date_debut, current.date_fin).await?;
+
+    sqlx::query!("DELETE FROM absences WHERE id_absence = $1", id_absence)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to delete absence: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to delete absence: {}", e))?;
+
+    Ok(())
+}
@@ -0,0 +1,216 @@
+use crate::commands::absences::{periode_verrouillee, verrouiller_runs};
+use crate::commands::auth::session_courante;
+use crate::models::{AppState, Ajustement, NewAjustement};
+use crate::payroll::{peut_preparer, Periode, TYPES_AJUSTEMENT};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Datelike, NaiveDate};
+use tauri::State;
+use thiserror::Error;
+
+
+//managing manual adjustments (bonuses, on-call allowances, withholdings)
+
+#[derive(Debug, Error)]
+pub enum AjustementValidationError {
+    #[error("Type d'ajustement inconnu : {0}")]
+    UnknownType(String),
+    #[error("Le montant de l'ajustement doit être positif")]
+    InvalidAmount,
+    #[error("Le motif de l'ajustement est obligatoire")]
+    EmptyReason,
+    #[error("La période doit être le premier jour d'un mois")]
+    InvalidPeriod,
+    #[error("La paie de cette période est verrouillée")]
+    LockedPeriod,
+    #[error("Ajustement introuvable")]
+    NotFound,
+}
+
+pub(crate) async fn fetch_ajustements(pool: &sqlx::PgPool, periode: Option<NaiveDate>) -> Result<Vec<Ajustement>, sqlx::Error> {
+    sqlx::query_as::<_, Ajustement>(
+        r#"
+        SELECT
+            ajustements.id_ajustement,
+            ajustements.id_resident,
+            residents.nom_prenom as nom_resident,
+            ajustements.periode,
+            ajustements.type_ajustement,
+            ajustements.montant,
+            ajustements.motif,
+            ajustements.created_by,
+            ajustements.created_at,
+            ajustements.id_paiement
+        FROM ajustements
+        LEFT JOIN residents ON ajustements.id_resident = residents.id_resident
+        WHERE $1::date IS NULL OR ajustements.periode = $1
+        ORDER BY ajustements.periode DESC, residents.nom_prenom
+        "#,
+    )
+    .bind(periode)
+    .fetch_all(pool)
+    .await
+}
+
+fn validate_ajustement(type_ajustement: &str, montant: &BigDecimal, motif: &str, periode: NaiveDate) -> Result<(), String> {
+    if !TYPES_AJUSTEMENT.contains(&type_ajustement) {
+        return Err(AjustementValidationError::UnknownType(type_ajustement.to_string()).to_string());
+    }
+
+    if montant <= &BigDecimal::zero() {
+        return Err(AjustementValidationError::InvalidAmount.to_string());
+    }
+
+    if motif.trim().is_empty() {
+        return Err(AjustementValidationError::EmptyReason.to_string());
+    }
+
+    if periode.day() != 1 {
+        return Err(AjustementValidationError::InvalidPeriod.to_string());
+    }
+
+    Ok(())
+}
+
+// The run of the month stays share-locked until the transaction ends
+async fn ensure_period_open(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    id_resident: i32,
+    periode: NaiveDate,
+) -> Result<(), String> {
+    let fin = Periode::contenant(periode).fin();
+    verrouiller_runs(tx, periode, fin).await?;
+    if periode_verrouillee(&mut *tx, id_resident, periode, fin).await? {
+        return Err(AjustementValidationError::LockedPeriod.to_string());
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_ajustements(state: State<'_, AppState>, periode: Option<NaiveDate>) -> Result<Vec<Ajustement>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    fetch_ajustements(pool, periode)
+        .await
+        .map_err(|e| format!("Failed to fetch adjustments: {}", e))
+}
+
+// The adjustment is picked up by the payroll run of its month; a draft run
+// has to be regenerated to include it
+#[tauri::command]
+pub async fn add_ajustement(state: State<'_, AppState>, ajustement: NewAjustement) -> Result<(), String> {
+    let session = session_courante(&state).await?;
+    peut_preparer(session.role).map_err(|e| e.to_string())?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_ajustement(&ajustement.type_ajustement, &ajustement.montant, &ajustement.motif, ajustement.periode)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    ensure_period_open(&mut tx, ajustement.id_resident, ajustement.periode).await?;
+
+    sqlx::query!(
+        "INSERT INTO ajustements (id_resident, periode, type_ajustement, montant, motif, created_by) VALUES ($1, $2, $3, $4, $5, $6)",
+        ajustement.id_resident,
+        ajustement.periode,
+        ajustement.type_ajustement,
+        ajustement.montant,
+        ajustement.motif.trim(),
+        session.username
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to add adjustment: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to add adjustment: {}", e))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn modify_ajustement(state: State<'_, AppState>, ajustement: Ajustement) -> Result<(), String> {
+    let session = session_courante(&state).await?;
+    peut_preparer(session.role).map_err(|e| e.to_string())?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_ajustement(&ajustement.type_ajustement, &ajustement.montant, &ajustement.motif, ajustement.periode)?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let current = sqlx::query!(
+        "SELECT id_resident, periode FROM ajustements WHERE id_ajustement = $1 FOR UPDATE",
+        ajustement.id_ajustement
+    )
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to fetch adjustment: {}", e))?
+    .ok_or_else(|| AjustementValidationError::NotFound.to_string())?;
+
+    ensure_period_open(&mut tx, current.id_resident, current.periode).await?;
+    ensure_period_open(&mut tx, ajustement.id_resident, ajustement.periode).await?;
+
+    sqlx::query!(
+        "UPDATE ajustements SET id_resident = $1, periode = $2, type_ajustement = $3, montant = $4, motif = $5 WHERE id_ajustement = $6",
+        ajustement.id_resident,
+        ajustement.periode,
+        ajustement.type_ajustement,
+        ajustement.montant,
+        ajustement.motif.trim(),
+        ajustement.id_ajustement
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to modify adjustment: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to modify adjustment: {}", e))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_ajustement(state: State<'_, AppState>, id_ajustement: i32) -> Result<(), String> {
+    let session = session_courante(&state).await?;
+    peut_preparer(session.role).map_err(|e| e.to_string())?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let current = sqlx::query!(
+        "SELECT id_resident, periode FROM ajustements WHERE id_ajustement = $1 FOR UPDATE",
+        id_ajustement
+    )
+    .fetch_optional(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to fetch adjustment: {}", e))?
+    .ok_or_else(|| AjustementValidationError::NotFound.to_string())?;
+
+    ensure_period_open(&mut tx, current.id_resident, current.periode).await?;
+
+    sqlx::query!("DELETE FROM ajustements WHERE id_ajustement = $1", id_ajustement)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to delete adjustment: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to delete adjustment: {}", e))?;
+
+    Ok(())
+}
@@ -0,0 +1,389 @@
+use crate::commands::auth::session_courante;
+use crate::documents::{
+    rendre_attestation, rendre_attestation_travail, verifier_modele, AttestationSalaire, AttestationTravail, Employeur,
+    LigneAttestation, NumeroOrdre, MOIS,
+};
+use crate::models::{AppState, AttestationTravailEmise, ModeleAttestation, NewModeleAttestation};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Datelike, Local, NaiveDate};
+use std::path::Path;
+use tauri::State;
+use thiserror::Error;
+
+
+//generating attestations
+
+// Payments of the months of `annee` and rappels paid during it, by payment date.
+// Only validated runs count, along with the payments made before runs existed,
+// and transfers the bank rejected were never received.
+async fn charger_attestation(pool: &sqlx::PgPool, id_resident: i32, annee: i32) -> Result<AttestationSalaire, String> {
+    let resident = sqlx::query!(
+        r#"
+        SELECT residents.nom_prenom, residents.date_debut, specialites.nom as "nom_specialite?"
+        FROM residents
+        LEFT JOIN specialites ON residents.id_specialite = specialites.id_specialite
+        WHERE residents.id_resident = $1
+        "#,
+        id_resident
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch resident: {}", e))?
+    .ok_or("Résident introuvable")?;
+
+    let debut = NaiveDate::from_ymd_opt(annee, 1, 1).ok_or_else(|| format!("Année invalide : {}", annee))?;
+    let fin = NaiveDate::from_ymd_opt(annee, 12, 31).ok_or_else(|| format!("Année invalide : {}", annee))?;
+
+    let paiements = sqlx::query!(
+        r#"
+        SELECT
+            paiement_mensuel.periode,
+            paiement_mensuel.date_paiement,
+            paiement_mensuel.montant,
+            paiement_mensuel.allocations_familiales,
+            paiement_mensuel.salaire_brut,
+            paiement_mensuel.total_retenues
+        FROM paiement_mensuel
+        LEFT JOIN payroll_run ON paiement_mensuel.id_run = payroll_run.id_run
+        WHERE paiement_mensuel.id_resident = $1 AND paiement_mensuel.periode BETWEEN $2 AND $3
+          AND (paiement_mensuel.id_run IS NULL OR payroll_run.statut IN ('valide', 'envoye', 'paye'))
+          AND paiement_mensuel.statut_virement <> 'rejete'
+        "#,
+        id_resident,
+        debut,
+        fin
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch payments: {}", e))?;
+
+    let rappels = sqlx::query!(
+        r#"
+        SELECT exercice, montant, retenue_recouvrement, date_generation as "date_generation!"
+        FROM rappels_annuels
+        WHERE id_resident = $1 AND date_generation BETWEEN $2 AND $3 AND montant > 0
+          AND statut_virement <> 'rejete'
+        "#,
+        id_resident,
+        debut,
+        fin
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch rappels: {}", e))?;
+
+    let mut lignes: Vec<LigneAttestation> = paiements
+        .into_iter()
+        .map(|record| {
+            let allocations = record.allocations_familiales.unwrap_or_else(BigDecimal::zero);
+            let retenues = record.total_retenues.unwrap_or_else(BigDecimal::zero);
+            // Payments made before the gross-to-net breakdown, as on their payslips
+            let brut = record
+                .salaire_brut
+                .unwrap_or_else(|| &record.montant + &retenues - &allocations);
+
+            LigneAttestation {
+                libelle: format!("{} {}", MOIS[record.periode.month0() as usize], record.periode.year()),
+                date_paiement: record.date_paiement,
+                brut,
+                allocations,
+                retenues,
+                net: record.montant,
+            }
+        })
+        .collect();
+
+    // Rappels are stored net of the overpayments recovered on them
+    lignes.extend(rappels.into_iter().map(|record| LigneAttestation {
+        libelle: format!("Rappel exercice {}", record.exercice),
+        date_paiement: record.date_generation,
+        brut: &record.montant + &record.retenue_recouvrement,
+        allocations: BigDecimal::zero(),
+        retenues: record.retenue_recouvrement,
+        net: record.montant,
+    }));
+    lignes.sort_by_key(|ligne| ligne.date_paiement);
+
+    if lignes.is_empty() {
+        return Err(format!("Aucun paiement pour {} en {}", resident.nom_prenom, annee));
+    }
+
+    Ok(AttestationSalaire {
+        nom_prenom: resident.nom_prenom,
+        nom_specialite: resident.nom_specialite,
+        date_debut: resident.date_debut,
+        annee,
+        lignes,
+        date_edition: Local::now().naive_local().date(),
+    })
+}
+
+// Writes the income attestation of a resident for the calendar year `annee`
+// to `chemin`: monthly detail, rappels, totals and net amount in words
+#[tauri::command]
+pub async fn generate_salary_attestation(
+    state: State<'_, AppState>,
+    id_resident: i32,
+    annee: i32,
+    chemin: String,
+) -> Result<String, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    if annee > Local::now().naive_local().date().year() {
+        return Err(format!("L'année {} n'est pas encore commencée", annee));
+    }
+
+    let attestation = charger_attestation(pool, id_resident, annee).await?;
+    let pdf = rendre_attestation(&Employeur::depuis_env(), &attestation).map_err(|e| e.to_string())?;
+
+    let chemin = Path::new(&chemin);
+    std::fs::write(chemin, pdf).map_err(|e| format!("Failed to write {}: {}", chemin.display(), e))?;
+    Ok(chemin.display().to_string())
+}
+
+
+//managing work certificate templates
+
+#[derive(Debug, Error)]
+pub enum ModeleValidationError {
+    #[error("Le nom du modèle est obligatoire")]
+    EmptyName,
+    #[error("Le texte du modèle est obligatoire")]
+    EmptyBody,
+    #[error("Un modèle porte déjà ce nom")]
+    Duplicate,
+    #[error("Modèle d'attestation introuvable")]
+    NotFound,
+}
+
+fn validate_modele(nom: &str, corps: &str) -> Result<(), String> {
+    if nom.trim().is_empty() {
+        return Err(ModeleValidationError::EmptyName.to_string());
+    }
+    if corps.trim().is_empty() {
+        return Err(ModeleValidationError::EmptyBody.to_string());
+    }
+
+    verifier_modele(corps).map_err(|e| e.to_string())
+}
+
+fn map_modele_error(error: sqlx::Error, action: &str) -> String {
+    match &error {
+        sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("23505") => {
+            ModeleValidationError::Duplicate.to_string()
+        }
+        _ => format!("Failed to {} certificate template: {}", action, error),
+    }
+}
+
+#[tauri::command]
+pub async fn get_modeles_attestation(state: State<'_, AppState>) -> Result<Vec<ModeleAttestation>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    sqlx::query_as::<_, ModeleAttestation>("SELECT * FROM modeles_attestation ORDER BY nom")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch certificate templates: {}", e))
+}
+
+#[tauri::command]
+pub async fn add_modele_attestation(state: State<'_, AppState>, modele: NewModeleAttestation) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_modele(&modele.nom, &modele.corps)?;
+
+    sqlx::query!(
+        "INSERT INTO modeles_attestation (nom, titre, corps) VALUES ($1, $2, $3)",
+        modele.nom.trim(),
+        modele.titre.trim(),
+        modele.corps
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| map_modele_error(e, "add"))?;
+
+    Ok(())
+}
+
+// Certificates already issued keep the text they were issued with
+#[tauri::command]
+pub async fn modify_modele_attestation(state: State<'_, AppState>, modele: ModeleAttestation) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_modele(&modele.nom, &modele.corps)?;
+
+    let updated = sqlx::query!(
+        "UPDATE modeles_attestation SET nom = $1, titre = $2, corps = $3 WHERE id_modele = $4",
+        modele.nom.trim(),
+        modele.titre.trim(),
+        modele.corps,
+        modele.id_modele
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| map_modele_error(e, "modify"))?;
+
+    if updated.rows_affected() == 0 {
+        return Err(ModeleValidationError::NotFound.to_string());
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_modele_attestation(state: State<'_, AppState>, id_modele: i32) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let deleted = sqlx::query!("DELETE FROM modeles_attestation WHERE id_modele = $1", id_modele)
+        .execute(pool)
+        .await
+        .map_err(|e| map_modele_error(e, "delete"))?;
+
+    if deleted.rows_affected() == 0 {
+        return Err(ModeleValidationError::NotFound.to_string());
+    }
+
+    Ok(())
+}
+
+
+//issuing work certificates
+
+// Fills `id_modele` for a resident, writes the PDF to `chemin` and records it in
+// the register under the next number of the current year
+#[tauri::command]
+pub async fn generate_work_certificate(
+    state: State<'_, AppState>,
+    id_resident: i32,
+    id_modele: i32,
+    chemin: String,
+) -> Result<AttestationTravailEmise, String> {
+    let session = session_courante(&state).await?;
+
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let modele = sqlx::query_as::<_, ModeleAttestation>("SELECT * FROM modeles_attestation WHERE id_modele = $1")
+        .bind(id_modele)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch certificate template: {}", e))?
+        .ok_or_else(|| ModeleValidationError::NotFound.to_string())?;
+
+    let resident = sqlx::query!(
+        r#"
+        SELECT residents.nom_prenom, residents.date_debut, residents.date_fin, specialites.nom as "nom_specialite?"
+        FROM residents
+        LEFT JOIN specialites ON residents.id_specialite = specialites.id_specialite
+        WHERE residents.id_resident = $1
+        "#,
+        id_resident
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch resident: {}", e))?
+    .ok_or("Résident introuvable")?;
+
+    let aujourd_hui = Local::now().naive_local().date();
+    let exercice = aujourd_hui.year();
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // The register is locked until the certificate is recorded, so that two
+    // certificates issued at once cannot get the same number
+    sqlx::query!("LOCK TABLE attestations_travail IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to number certificate: {}", e))?;
+
+    let numero = sqlx::query!(
+        r#"SELECT COALESCE(MAX(numero), 0) + 1 as "numero!" FROM attestations_travail WHERE exercice = $1"#,
+        exercice
+    )
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to number certificate: {}", e))?
+    .numero;
+
+    let attestation = AttestationTravail {
+        numero: NumeroOrdre { numero, exercice },
+        nom_prenom: resident.nom_prenom,
+        nom_specialite: resident.nom_specialite,
+        date_debut: resident.date_debut,
+        date_fin: resident.date_fin,
+        date_edition: aujourd_hui,
+        titre: modele.titre,
+        corps: modele.corps,
+    };
+    let employeur = Employeur::depuis_env();
+    let texte = attestation.texte(&employeur).map_err(|e| e.to_string())?;
+    let pdf = rendre_attestation_travail(&employeur, &attestation).map_err(|e| e.to_string())?;
+
+    let emise = sqlx::query!(
+        "INSERT INTO attestations_travail (exercice, numero, id_resident, nom_prenom, id_modele, en_fonction, texte, created_by)
+         VALUES ($1, $2, $3, $4, $5, $6, $7, $8)
+         RETURNING id_attestation, created_at",
+        exercice,
+        numero,
+        id_resident,
+        attestation.nom_prenom,
+        id_modele,
+        attestation.en_fonction(),
+        texte,
+        session.username
+    )
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to record certificate: {}", e))?;
+
+    // The number is only kept once the file is written
+    let chemin = Path::new(&chemin);
+    std::fs::write(chemin, pdf).map_err(|e| format!("Failed to write {}: {}", chemin.display(), e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit certificate: {}", e))?;
+
+    Ok(AttestationTravailEmise {
+        id_attestation: emise.id_attestation,
+        exercice,
+        numero,
+        id_resident: Some(id_resident),
+        nom_prenom: attestation.nom_prenom,
+        id_modele: Some(id_modele),
+        nom_modele: Some(modele.nom),
+        en_fonction: attestation.en_fonction(),
+        created_by: session.username,
+        created_at: emise.created_at,
+    })
+}
+
+// Register of issued work certificates, latest first, for one year or all of them
+#[tauri::command]
+pub async fn get_attestations_travail(state: State<'_, AppState>, annee: Option<i32>) -> Result<Vec<AttestationTravailEmise>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    sqlx::query_as::<_, AttestationTravailEmise>(
+        "SELECT attestations_travail.id_attestation, attestations_travail.exercice, attestations_travail.numero,
+                attestations_travail.id_resident, attestations_travail.nom_prenom, attestations_travail.id_modele,
+                modeles_attestation.nom as nom_modele, attestations_travail.en_fonction,
+                attestations_travail.created_by, attestations_travail.created_at
+         FROM attestations_travail
+         LEFT JOIN modeles_attestation ON attestations_travail.id_modele = modeles_attestation.id_modele
+         WHERE ($1::int IS NULL OR attestations_travail.exercice = $1)
+         ORDER BY attestations_travail.exercice DESC, attestations_travail.numero DESC",
+    )
+    .bind(annee)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch issued certificates: {}", e))
+}
@@ -1,13 +1,13 @@
 use tauri::State;
 use sqlx::Row;
-use crate::models::{AppState, LoginPayload};
+use crate::models::{AppState, LoginPayload, Role, Session};
 
 #[tauri::command]
 pub async fn login(payload: LoginPayload, state: State<'_, AppState>) -> Result<bool, String> {
     let pool = state.pool.lock().await;
     let pool = pool.as_ref().ok_or("Database not connected")?;
     
-    match sqlx::query("SELECT password FROM admin WHERE username = $1")
+    match sqlx::query("SELECT password, role FROM admin WHERE username = $1")
         .bind(&payload.username)
         .fetch_one(pool)
         .await
@@ -15,6 +15,12 @@ pub async fn login(payload: LoginPayload, state: State<'_, AppState>) -> Result<
         Ok(row) => {
             let stored_password: String = row.try_get("password").map_err(|e| e.to_string())?;
             if stored_password == payload.password {
+                let role: String = row.try_get("role").map_err(|e| e.to_string())?;
+                let role = Role::parse(&role).ok_or_else(|| format!("Unknown role: {}", role))?;
+                *state.session.lock().await = Some(Session {
+                    username: payload.username.clone(),
+                    role,
+                });
                 Ok(true)
             } else {
                 Err("Invalid credentials".to_string())
@@ -30,3 +36,18 @@ pub async fn login(payload: LoginPayload, state: State<'_, AppState>) -> Result<
         }
     }
 }
+
+#[tauri::command]
+pub async fn get_session(state: State<'_, AppState>) -> Result<Option<Session>, String> {
+    Ok(state.session.lock().await.clone())
+}
+
+// Logged-in user, recorded on payroll actions and checked against workflow roles
+pub(crate) async fn session_courante(state: &State<'_, AppState>) -> Result<Session, String> {
+    state
+        .session
+        .lock()
+        .await
+        .clone()
+        .ok_or_else(|| "Veuillez vous connecter".to_string())
+}
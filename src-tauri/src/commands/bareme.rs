@@ -0,0 +1,200 @@
+use crate::models::{AppState, Bareme, NewBareme};
+use crate::payroll::{annee_residanat, bareme_applicable};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Datelike, NaiveDate};
+use tauri::State;
+use thiserror::Error;
+
+
+//managing the salary grid
+
+#[derive(Debug, Error)]
+pub enum BaremeValidationError {
+    #[error("L'année de résidanat doit être comprise entre 1 et 7")]
+    InvalidYear,
+    #[error("Les montants du barème ne peuvent pas être négatifs")]
+    NegativeAmount,
+    #[error("La date d'effet doit être le premier jour d'un mois")]
+    InvalidEffectiveDate,
+    #[error("Une ligne de barème existe déjà pour cette année, cette spécialité et cette date d'effet")]
+    Duplicate,
+    #[error("Ligne de barème introuvable")]
+    NotFound,
+}
+
+fn validate_bareme(
+    annee_residanat: i32,
+    montants: [&BigDecimal; 4],
+    date_effet: NaiveDate,
+) -> Result<(), String> {
+    if !(1..=7).contains(&annee_residanat) {
+        return Err(BaremeValidationError::InvalidYear.to_string());
+    }
+
+    if montants.iter().any(|montant| *montant < &BigDecimal::zero()) {
+        return Err(BaremeValidationError::NegativeAmount.to_string());
+    }
+
+    if date_effet.day() != 1 {
+        return Err(BaremeValidationError::InvalidEffectiveDate.to_string());
+    }
+
+    Ok(())
+}
+
+fn map_bareme_error(error: sqlx::Error, action: &str) -> String {
+    match &error {
+        sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("23505") => {
+            BaremeValidationError::Duplicate.to_string()
+        }
+        _ => format!("Failed to {} salary grid row: {}", action, error),
+    }
+}
+
+pub(crate) async fn fetch_grille(pool: &sqlx::PgPool) -> Result<Vec<Bareme>, sqlx::Error> {
+    sqlx::query_as::<_, Bareme>(
+        r#"
+        SELECT
+            bareme.id_bareme,
+            bareme.annee_residanat,
+            bareme.id_specialite,
+            specialites.nom as nom_specialite,
+            bareme.salaire_base,
+            bareme.indemnite_fonction,
+            bareme.indemnite_logement,
+            bareme.indemnite_transport,
+            bareme.date_effet
+        FROM bareme
+        LEFT JOIN specialites ON bareme.id_specialite = specialites.id_specialite
+        ORDER BY bareme.date_effet DESC, bareme.annee_residanat, specialites.nom NULLS FIRST
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+}
+
+#[tauri::command]
+pub async fn get_baremes(state: State<'_, AppState>) -> Result<Vec<Bareme>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    fetch_grille(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch salary grid: {}", e))
+}
+
+#[tauri::command]
+pub async fn add_bareme(state: State<'_, AppState>, bareme: NewBareme) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_bareme(
+        bareme.annee_residanat,
+        [&bareme.salaire_base, &bareme.indemnite_fonction, &bareme.indemnite_logement, &bareme.indemnite_transport],
+        bareme.date_effet,
+    )?;
+
+    sqlx::query!(
+        "INSERT INTO bareme (annee_residanat, id_specialite, salaire_base, indemnite_fonction, indemnite_logement, indemnite_transport, date_effet)
+         VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        bareme.annee_residanat,
+        bareme.id_specialite,
+        bareme.salaire_base,
+        bareme.indemnite_fonction,
+        bareme.indemnite_logement,
+        bareme.indemnite_transport,
+        bareme.date_effet
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| map_bareme_error(e, "add"))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn modify_bareme(state: State<'_, AppState>, bareme: Bareme) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_bareme(
+        bareme.annee_residanat,
+        [&bareme.salaire_base, &bareme.indemnite_fonction, &bareme.indemnite_logement, &bareme.indemnite_transport],
+        bareme.date_effet,
+    )?;
+
+    let updated = sqlx::query!(
+        "UPDATE bareme SET
+            annee_residanat = $1,
+            id_specialite = $2,
+            salaire_base = $3,
+            indemnite_fonction = $4,
+            indemnite_logement = $5,
+            indemnite_transport = $6,
+            date_effet = $7
+            WHERE id_bareme = $8",
+        bareme.annee_residanat,
+        bareme.id_specialite,
+        bareme.salaire_base,
+        bareme.indemnite_fonction,
+        bareme.indemnite_logement,
+        bareme.indemnite_transport,
+        bareme.date_effet,
+        bareme.id_bareme
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| map_bareme_error(e, "modify"))?;
+
+    if updated.rows_affected() == 0 {
+        return Err(BaremeValidationError::NotFound.to_string());
+    }
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_bareme(state: State<'_, AppState>, id_bareme: i32) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let deleted = sqlx::query!("DELETE FROM bareme WHERE id_bareme = $1", id_bareme)
+        .execute(pool)
+        .await
+        .map_err(|e| map_bareme_error(e, "delete"))?;
+
+    if deleted.rows_affected() == 0 {
+        return Err(BaremeValidationError::NotFound.to_string());
+    }
+
+    Ok(())
+}
+
+// Grid row the payroll uses for a resident in the month of `periode`
+#[tauri::command]
+pub async fn get_bareme_applicable(state: State<'_, AppState>, id_resident: i32, periode: NaiveDate) -> Result<Option<Bareme>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let resident = sqlx::query!(
+        r#"
+        SELECT residents.date_debut, residents.id_specialite, specialites.nombre_annees as "nombre_annees?"
+        FROM residents
+        LEFT JOIN specialites ON residents.id_specialite = specialites.id_specialite
+        WHERE residents.id_resident = $1
+        "#,
+        id_resident
+    )
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch resident: {}", e))?
+    .ok_or("Résident introuvable")?;
+
+    let grille = fetch_grille(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch salary grid: {}", e))?;
+
+    let annee = annee_residanat(resident.date_debut, periode, resident.nombre_annees.unwrap_or(1));
+
+    Ok(bareme_applicable(&grille, annee, resident.id_specialite, periode).cloned())
+}
@@ -0,0 +1,204 @@
+use crate::documents::{nom_fichier, rendre_bulletins, BulletinPaie, Employeur};
+use crate::models::{AppState, LignePaiement};
+use crate::payroll::Periode;
+use bigdecimal::{BigDecimal, Zero};
+use std::path::Path;
+use tauri::State;
+
+
+//generating payslips
+
+fn gain(code: &str, libelle: &str, montant: BigDecimal) -> LignePaiement {
+    LignePaiement {
+        code: code.to_string(),
+        libelle: libelle.to_string(),
+        categorie: "gain".to_string(),
+        base: None,
+        taux: None,
+        montant,
+    }
+}
+
+// Payments made before the gross-to-net breakdown only have their stored components
+fn lignes_historiques(montant: &BigDecimal, allocations: &Option<BigDecimal>, composantes: [(&str, &str, &Option<BigDecimal>); 4]) -> Vec<LignePaiement> {
+    let mut lignes: Vec<LignePaiement> = composantes
+        .iter()
+        .filter_map(|(code, libelle, montant)| {
+            montant
+                .as_ref()
+                .filter(|m| !m.is_zero())
+                .map(|m| gain(code, libelle, m.clone()))
+        })
+        .collect();
+
+    let allocations = allocations.clone().unwrap_or_else(BigDecimal::zero);
+    if lignes.is_empty() {
+        lignes.push(gain("INDEMNITES", "Indemnités", montant - &allocations));
+    }
+    if !allocations.is_zero() {
+        lignes.push(gain("ALLOC_FAM", "Allocations familiales", allocations));
+    }
+
+    lignes
+}
+
+// Loads the payslips of the given payments, or of every payment of `periode`.
+// Draft runs may still be regenerated and get no payslip.
+async fn charger_bulletins(pool: &sqlx::PgPool, id_paiement: Option<i32>, periode: Option<Periode>) -> Result<Vec<BulletinPaie>, String> {
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            paiement_mensuel.id_paiement,
+            paiement_mensuel.periode,
+            paiement_mensuel.date_paiement,
+            paiement_mensuel.jours_travail,
+            paiement_mensuel.montant,
+            paiement_mensuel.allocations_familiales,
+            paiement_mensuel.salaire_base,
+            paiement_mensuel.indemnite_fonction,
+            paiement_mensuel.indemnite_logement,
+            paiement_mensuel.indemnite_transport,
+            paiement_mensuel.salaire_brut,
+            paiement_mensuel.total_retenues,
+            residents.nom_prenom as "nom_prenom?",
+            residents.rib as "rib?",
+            specialites.nom as "nom_specialite?",
+            banque.nom as "nom_banque?"
+        FROM paiement_mensuel
+        LEFT JOIN residents ON paiement_mensuel.id_resident = residents.id_resident
+        LEFT JOIN specialites ON residents.id_specialite = specialites.id_specialite
+        LEFT JOIN banque ON residents.id_banque = banque.id_banque
+        LEFT JOIN payroll_run ON paiement_mensuel.id_run = payroll_run.id_run
+        WHERE ($1::int IS NULL OR paiement_mensuel.id_paiement = $1)
+          AND ($2::date IS NULL OR paiement_mensuel.periode = $2)
+          AND (paiement_mensuel.id_run IS NULL OR payroll_run.statut IN ('valide', 'envoye', 'paye'))
+        ORDER BY residents.nom_prenom
+        "#,
+        id_paiement,
+        periode.map(|p| p.debut())
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch payments: {}", e))?;
+
+    if records.is_empty() {
+        return Err("Aucun bulletin à éditer : aucun paiement d'un run de paie validé".to_string());
+    }
+
+    let ids: Vec<i32> = records.iter().map(|record| record.id_paiement).collect();
+    let lignes = sqlx::query!(
+        r#"
+        SELECT id_paiement, code, libelle, categorie, base, taux, montant
+        FROM lignes_paiement
+        WHERE id_paiement = ANY($1)
+        ORDER BY id_paiement, ordre
+        "#,
+        &ids
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch payment lines: {}", e))?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| {
+            let mut detail: Vec<LignePaiement> = lignes
+                .iter()
+                .filter(|ligne| ligne.id_paiement == record.id_paiement)
+                .map(|ligne| LignePaiement {
+                    code: ligne.code.clone(),
+                    libelle: ligne.libelle.clone(),
+                    categorie: ligne.categorie.clone(),
+                    base: ligne.base.clone(),
+                    taux: ligne.taux.clone(),
+                    montant: ligne.montant.clone(),
+                })
+                .collect();
+
+            if detail.is_empty() {
+                detail = lignes_historiques(
+                    &record.montant,
+                    &record.allocations_familiales,
+                    [
+                        ("SALAIRE_BASE", "Salaire de base", &record.salaire_base),
+                        ("IND_FONCTION", "Indemnité de fonction", &record.indemnite_fonction),
+                        ("IND_LOGEMENT", "Indemnité de logement", &record.indemnite_logement),
+                        ("IND_TRANSPORT", "Indemnité de transport", &record.indemnite_transport),
+                    ],
+                );
+            }
+
+            let total_retenues = record.total_retenues.unwrap_or_else(BigDecimal::zero);
+            let salaire_brut = record.salaire_brut.unwrap_or_else(|| {
+                &record.montant + &total_retenues - record.allocations_familiales.clone().unwrap_or_else(BigDecimal::zero)
+            });
+
+            BulletinPaie {
+                nom_prenom: record.nom_prenom.unwrap_or_default(),
+                nom_specialite: record.nom_specialite,
+                nom_banque: record.nom_banque,
+                rib: record.rib.unwrap_or_default(),
+                periode: Periode::contenant(record.periode),
+                date_paiement: record.date_paiement,
+                jours_travail: record.jours_travail,
+                lignes: detail,
+                salaire_brut,
+                total_retenues,
+                montant: record.montant,
+            }
+        })
+        .collect())
+}
+
+fn ecrire(chemin: &Path, contenu: &[u8]) -> Result<String, String> {
+    std::fs::write(chemin, contenu).map_err(|e| format!("Failed to write {}: {}", chemin.display(), e))?;
+    Ok(chemin.display().to_string())
+}
+
+// Writes the payslip of one payment to `chemin`, chosen with the save dialog
+#[tauri::command]
+pub async fn generate_payslip(state: State<'_, AppState>, id_paiement: i32, chemin: String) -> Result<String, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let bulletins = charger_bulletins(pool, Some(id_paiement), None).await?;
+    let pdf = rendre_bulletins(&Employeur::depuis_env(), &bulletins).map_err(|e| e.to_string())?;
+
+    ecrire(Path::new(&chemin), &pdf)
+}
+
+// Writes the payslips of a whole month into `dossier`: one file per resident,
+// or a single file with one page per resident when `combine` is set.
+// Returns the paths written.
+#[tauri::command]
+pub async fn generate_payslips(
+    state: State<'_, AppState>,
+    annee: i32,
+    mois: u32,
+    dossier: String,
+    combine: bool,
+) -> Result<Vec<String>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let periode = Periode::new(annee, mois).map_err(|e| e.to_string())?;
+    let bulletins = charger_bulletins(pool, None, Some(periode)).await?;
+    let employeur = Employeur::depuis_env();
+    let dossier = Path::new(&dossier);
+    let suffixe = format!("{:02}-{}", periode.mois, periode.annee);
+
+    if combine {
+        let pdf = rendre_bulletins(&employeur, &bulletins).map_err(|e| e.to_string())?;
+        let chemin = dossier.join(format!("Bulletins de paie {}.pdf", suffixe));
+        return Ok(vec![ecrire(&chemin, &pdf)?]);
+    }
+
+    let mut chemins = Vec::new();
+    for bulletin in &bulletins {
+        let pdf = rendre_bulletins(&employeur, std::slice::from_ref(bulletin)).map_err(|e| e.to_string())?;
+        let chemin = dossier.join(format!("Bulletin {} - {}.pdf", suffixe, nom_fichier(&bulletin.nom_prenom)));
+        chemins.push(ecrire(&chemin, &pdf)?);
+    }
+
+    Ok(chemins)
+}
@@ -1,5 +1,7 @@
-use crate::models::{AppState, Specialite, Resident, MyError, NewSpecialite, NewResident, Banque, PaiementMensuel, RappelAnnuel};
+use crate::models::{AppState, Specialite, Resident, MyError, NewSpecialite, NewResident, Banque, NewBanque, PaiementMensuel, RappelAnnuel};
 use thiserror::Error;
+use serde::{Serialize, Serializer};
+use serde::ser::SerializeStruct;
 use bigdecimal::BigDecimal;
 use std::str::FromStr;
 use chrono::{Local, NaiveDate};
@@ -7,6 +9,9 @@ use tauri::{State};
 use dotenv::dotenv;
 use std::env;
 use sqlx::postgres::PgPoolOptions;
+use crate::payroll::{cin_valide, valider_exercice};
+use crate::commands::recouvrements::imputer_echeances_rappel;
+use crate::documents::virements::{format_fixe, FORMATS_FIXES};
 
 
 #[tauri::command]
@@ -25,6 +30,13 @@ pub async fn connect_db() -> Result<sqlx::Pool<sqlx::Postgres>, sqlx::Error> {
 
     println!("Successfully connected to the database");
 
+    // The base tables and the generate_yearly_payments function predate this
+    // repository and are not versioned here. src-tauri/migrations only holds the
+    // changes made since, each written against that existing schema. They are
+    // applied on connection so an installed database catches up with the code
+    // that queries it.
+    sqlx::migrate!().run(&pool).await?;
+
     Ok(pool)
 }
 
@@ -37,7 +49,7 @@ pub async fn get_banques(state: State<'_, AppState>) -> Result<Vec<Banque>, MyEr
         message: "Database not connected".to_string(),
     })?;
     
-    let banks = sqlx::query_as::<_, Banque>("SELECT id_banque, nom FROM banque")
+    let banks = sqlx::query_as::<_, Banque>("SELECT id_banque, nom, code_banque, swift_bic, agence, format_virement FROM banque")
         .fetch_all(pool)
         .await
         .map_err(MyError::from)?;
@@ -45,8 +57,224 @@ pub async fn get_banques(state: State<'_, AppState>) -> Result<Vec<Banque>, MyEr
     Ok(banks)
 }
 
+#[derive(Debug, Error)]
+pub enum BankValidationError {
+    #[error("Le nom de la banque est obligatoire")]
+    EmptyName,
+    #[error("Le code banque doit contenir 3 chiffres")]
+    InvalidCode,
+    #[error("Le code SWIFT/BIC doit contenir 8 ou 11 caractères")]
+    InvalidSwift,
+    #[error("Format de fichier de virement inconnu (formats acceptés : {0})")]
+    InvalidTransferFormat(String),
+    #[error("Le code banque {0} est déjà attribué à une autre banque")]
+    DuplicateCode(String),
+}
+
+fn validate_banque(nom: &str, code_banque: Option<&str>, swift_bic: Option<&str>, format_virement: Option<&str>) -> Result<(), String> {
+    if nom.trim().is_empty() {
+        return Err(BankValidationError::EmptyName.to_string());
+    }
+
+    if let Some(code) = code_banque {
+        if code.len() != 3 || !code.chars().all(|c| c.is_ascii_digit()) {
+            return Err(BankValidationError::InvalidCode.to_string());
+        }
+    }
+
+    // 4 letters for the bank, 2 for the country, 2 for the location, optional 3 for the branch
+    if let Some(swift) = swift_bic {
+        let valid = (swift.len() == 8 || swift.len() == 11)
+            && swift.chars().take(6).all(|c| c.is_ascii_uppercase())
+            && swift.chars().skip(6).all(|c| c.is_ascii_uppercase() || c.is_ascii_digit());
+        if !valid {
+            return Err(BankValidationError::InvalidSwift.to_string());
+        }
+    }
+
+    if let Some(format) = format_virement {
+        if format_fixe(format).is_err() {
+            let formats: Vec<&str> = FORMATS_FIXES.iter().map(|format| format.code).collect();
+            return Err(BankValidationError::InvalidTransferFormat(formats.join(", ")).to_string());
+        }
+    }
+
+    Ok(())
+}
+
+// Uniqueness of the code is enforced by the banque_code_banque_key index
+fn map_banque_error(error: sqlx::Error, code_banque: Option<&str>, action: &str) -> String {
+    match (&error, code_banque) {
+        (sqlx::Error::Database(db_error), Some(code)) if db_error.constraint() == Some("banque_code_banque_key") => {
+            BankValidationError::DuplicateCode(code.to_string()).to_string()
+        }
+        _ => format!("Failed to {} bank: {}", action, error),
+    }
+}
+
+#[tauri::command]
+pub async fn add_banque(state: State<'_, AppState>, banque: NewBanque) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_banque(
+        &banque.nom,
+        banque.code_banque.as_deref(),
+        banque.swift_bic.as_deref(),
+        banque.format_virement.as_deref(),
+    )?;
+
+    sqlx::query!(
+        "INSERT INTO banque (nom, code_banque, swift_bic, agence, format_virement) VALUES ($1, $2, $3, $4, COALESCE($5, 'fixe160'))",
+        banque.nom.trim(),
+        banque.code_banque,
+        banque.swift_bic,
+        banque.agence,
+        banque.format_virement
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| map_banque_error(e, banque.code_banque.as_deref(), "add"))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn modify_banque(state: State<'_, AppState>, banque: Banque) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_banque(
+        &banque.nom,
+        banque.code_banque.as_deref(),
+        banque.swift_bic.as_deref(),
+        banque.format_virement.as_deref(),
+    )?;
+
+    sqlx::query!(
+        "UPDATE banque SET nom = $1, code_banque = $2, swift_bic = $3, agence = $4, format_virement = COALESCE($5, format_virement)
+         WHERE id_banque = $6",
+        banque.nom.trim(),
+        banque.code_banque,
+        banque.swift_bic,
+        banque.agence,
+        banque.format_virement,
+        banque.id_banque
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| map_banque_error(e, banque.code_banque.as_deref(), "modify"))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_banque(state: State<'_, AppState>, id_banque: i32) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let references = sqlx::query!(
+        "SELECT COUNT(*) FROM residents WHERE id_banque = $1",
+        id_banque
+    )
+    .fetch_one(pool)
+    .await
+    .map_err(|e| format!("Failed to query database: {}", e))?;
+
+    if let Some(count) = references.count {
+        if count > 0 {
+            return Err(format!("Impossible de supprimer la banque : {} résident(s) y sont rattachés", count));
+        }
+    }
+
+    sqlx::query!("DELETE FROM banque WHERE id_banque = $1", id_banque)
+        .execute(pool)
+        .await
+        .map_err(|e| format!("Failed to delete bank: {}", e))?;
+
+    Ok(())
+}
+
 //managing specialties
 
+#[derive(Debug, Error)]
+pub enum SpecialiteError {
+    #[error("Database not connected")]
+    NotConnected,
+    #[error("Spécialité introuvable")]
+    NotFound,
+    #[error("La spécialité est utilisée par {actifs} résident(s) actif(s) et {archives} résident(s) archivé(s)")]
+    InUse { actifs: i64, archives: i64 },
+    #[error("Impossible de fusionner une spécialité avec elle-même")]
+    SameSpecialty,
+    #[error("Fusion impossible : {0} paiement(s) verrouillé(s) dépendent de la spécialité des résidents concernés")]
+    LockedPayments(i64),
+    #[error("Fusion impossible : la spécialité a ses propres lignes de barème ({0}), supprimez-les d'abord")]
+    HasBareme(i64),
+    #[error("La spécialité « {0} » existe déjà")]
+    DuplicateName(String),
+    #[error("Le nom de la spécialité est obligatoire")]
+    EmptyName,
+    #[error("Le nombre d'années doit être compris entre {min} et {max}")]
+    InvalidDuration { min: i32, max: i32 },
+    #[error("Database error: {0}")]
+    Database(#[from] sqlx::Error),
+}
+
+impl SpecialiteError {
+    fn kind(&self) -> &'static str {
+        match self {
+            SpecialiteError::NotConnected | SpecialiteError::Database(_) => "database",
+            SpecialiteError::NotFound => "not_found",
+            SpecialiteError::InUse { .. }
+            | SpecialiteError::DuplicateName(_)
+            | SpecialiteError::LockedPayments(_)
+            | SpecialiteError::HasBareme(_) => "conflict",
+            SpecialiteError::SameSpecialty
+            | SpecialiteError::EmptyName
+            | SpecialiteError::InvalidDuration { .. } => "invalid",
+        }
+    }
+}
+
+const MIN_NOMBRE_ANNEES: i32 = 1;
+const MAX_NOMBRE_ANNEES: i32 = 7;
+
+fn validate_specialite(nom: &str, nombre_annees: i32) -> Result<(), SpecialiteError> {
+    if nom.is_empty() {
+        return Err(SpecialiteError::EmptyName);
+    }
+
+    if !(MIN_NOMBRE_ANNEES..=MAX_NOMBRE_ANNEES).contains(&nombre_annees) {
+        return Err(SpecialiteError::InvalidDuration {
+            min: MIN_NOMBRE_ANNEES,
+            max: MAX_NOMBRE_ANNEES,
+        });
+    }
+
+    Ok(())
+}
+
+// Uniqueness is enforced by the specialites_nom_normalized_key index, not by a prior SELECT
+fn map_unique_violation(error: sqlx::Error, nom: &str) -> SpecialiteError {
+    match &error {
+        sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("23505") => {
+            SpecialiteError::DuplicateName(nom.to_string())
+        }
+        _ => SpecialiteError::Database(error),
+    }
+}
+
+// Serialized as { kind, message } so the frontend can branch on the kind and display the message
+impl Serialize for SpecialiteError {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let mut state = serializer.serialize_struct("SpecialiteError", 2)?;
+        state.serialize_field("kind", self.kind())?;
+        state.serialize_field("message", &self.to_string())?;
+        state.end()
+    }
+}
+
 #[tauri::command]
 pub async fn get_specialites(state: State<'_, AppState>) -> Result<Vec<Specialite>, MyError> {
     let pool = state.pool.lock().await;
@@ -62,80 +290,160 @@ pub async fn get_specialites(state: State<'_, AppState>) -> Result<Vec<Specialit
     Ok(specialties)
 }
 #[tauri::command]
-pub async fn add_specialite(pool: State<'_, AppState>, specialite: NewSpecialite) -> Result<(), String> {
+pub async fn add_specialite(pool: State<'_, AppState>, specialite: NewSpecialite) -> Result<(), SpecialiteError> {
     let pool = pool.pool.lock().await;
-    let pool = pool.as_ref().ok_or_else(|| MyError {
-        message: "Database not connected".to_string(),
-    }).map_err(|e| e.message)?; // Convert MyError to String here
+    let pool = pool.as_ref().ok_or(SpecialiteError::NotConnected)?;
 
-    let existing_specialty = sqlx::query!(
-        "SELECT COUNT(*) FROM specialites WHERE nom = $1",
-        specialite.nom
-    )
-    .fetch_one(pool)
-    .await
-    .map_err(|e| format!("Failed to query database: {}", e))?;
-
-    if let Some(count) = existing_specialty.count {
-        if count > 0 {
-            return Err("Specialty name already exists".to_string());
-        }
-    }
+    let nom = specialite.nom.trim();
+    validate_specialite(nom, specialite.nombre_annees)?;
 
     sqlx::query!(
         "INSERT INTO specialites (nom, nombre_annees) VALUES ($1, $2)",
-        specialite.nom,
+        nom,
         specialite.nombre_annees
     )
     .execute(pool)
     .await
-    .map_err(|e| format!("Failed to add specialty: {}", e))?;
+    .map_err(|e| map_unique_violation(e, nom))?;
 
     Ok(())
 }
 
 #[tauri::command]
-pub async fn delete_specialite(pool: State<'_, AppState>, id_specialite: i32) -> Result<(), String> {
+pub async fn delete_specialite(pool: State<'_, AppState>, id_specialite: i32) -> Result<(), SpecialiteError> {
     let pool = pool.pool.lock().await;
-    let pool = pool.as_ref().ok_or("Database not connected")?;
+    let pool = pool.as_ref().ok_or(SpecialiteError::NotConnected)?;
+
+    let mut tx = pool.begin().await?;
+
+    // Locking the specialty row blocks concurrent inserts of residents referencing it
+    sqlx::query!(
+        "SELECT id_specialite FROM specialites WHERE id_specialite = $1 FOR UPDATE",
+        id_specialite
+    )
+    .fetch_optional(&mut tx)
+    .await?
+    .ok_or(SpecialiteError::NotFound)?;
+
+    let references = sqlx::query!(
+        r#"
+        SELECT
+            COUNT(*) FILTER (WHERE date_fin IS NULL OR date_fin > CURRENT_DATE) as "actifs!",
+            COUNT(*) FILTER (WHERE date_fin <= CURRENT_DATE) as "archives!"
+        FROM residents
+        WHERE id_specialite = $1
+        "#,
+        id_specialite
+    )
+    .fetch_one(&mut tx)
+    .await?;
+
+    if references.actifs > 0 || references.archives > 0 {
+        return Err(SpecialiteError::InUse {
+            actifs: references.actifs,
+            archives: references.archives,
+        });
+    }
 
     sqlx::query!("DELETE FROM specialites WHERE id_specialite = $1", id_specialite)
-        .execute(pool)
-        .await
-        .map_err(|e| format!("Failed to delete specialty: {}", e))?;
+        .execute(&mut tx)
+        .await?;
+
+    tx.commit().await?;
 
     Ok(())
 }
 
+// Moves every resident of `from` to `to`, then deletes `from`; returns the number of residents reassigned
 #[tauri::command]
-pub async fn modify_specialite(pool: State<'_, AppState>, specialite: Specialite) -> Result<(), String> {
+pub async fn merge_specialites(pool: State<'_, AppState>, from: i32, to: i32) -> Result<u64, SpecialiteError> {
     let pool = pool.pool.lock().await;
-    let pool = pool.as_ref().ok_or("Database not connected")?;
+    let pool = pool.as_ref().ok_or(SpecialiteError::NotConnected)?;
 
-    let existing_specialty = sqlx::query!(
-        "SELECT COUNT(*) FROM specialites WHERE nom = $1 AND id_specialite != $2",
-        specialite.nom,
-        specialite.id_specialite
+    if from == to {
+        return Err(SpecialiteError::SameSpecialty);
+    }
+
+    let mut tx = pool.begin().await?;
+
+    let locked = sqlx::query!(
+        "SELECT id_specialite FROM specialites WHERE id_specialite = ANY($1) ORDER BY id_specialite FOR UPDATE",
+        &[from, to][..]
     )
-    .fetch_one(pool)
-    .await
-    .map_err(|e| format!("Failed to query database: {}", e))?;
+    .fetch_all(&mut tx)
+    .await?;
 
-    if let Some(count) = existing_specialty.count {
-        if count > 0 {
-            return Err("Specialty name already exists".to_string());
-        }
+    if locked.len() != 2 {
+        return Err(SpecialiteError::NotFound);
     }
 
-    sqlx::query!(
+    // Deleting the specialty would cascade to its grid rows
+    let bareme = sqlx::query!(
+        r#"SELECT COUNT(*) as "count!" FROM bareme WHERE id_specialite = $1"#,
+        from
+    )
+    .fetch_one(&mut tx)
+    .await?
+    .count;
+    if bareme > 0 {
+        return Err(SpecialiteError::HasBareme(bareme));
+    }
+
+    // The specialty of a paid resident is frozen, as when modifying the resident
+    let residents: Vec<i32> = sqlx::query!(
+        "SELECT id_resident FROM residents WHERE id_specialite = $1 ORDER BY id_resident FOR UPDATE",
+        from
+    )
+    .fetch_all(&mut tx)
+    .await?
+    .into_iter()
+    .map(|record| record.id_resident)
+    .collect();
+
+    let payes = count_locked_payments(&mut tx, &residents).await?;
+    if payes > 0 {
+        return Err(SpecialiteError::LockedPayments(payes));
+    }
+
+    let reassigned = sqlx::query!(
+        "UPDATE residents SET id_specialite = $1 WHERE id_specialite = $2",
+        to,
+        from
+    )
+    .execute(&mut tx)
+    .await?
+    .rows_affected();
+
+    sqlx::query!("DELETE FROM specialites WHERE id_specialite = $1", from)
+        .execute(&mut tx)
+        .await?;
+
+    tx.commit().await?;
+
+    Ok(reassigned)
+}
+
+#[tauri::command]
+pub async fn modify_specialite(pool: State<'_, AppState>, specialite: Specialite) -> Result<(), SpecialiteError> {
+    let pool = pool.pool.lock().await;
+    let pool = pool.as_ref().ok_or(SpecialiteError::NotConnected)?;
+
+    let nom = specialite.nom.trim();
+    validate_specialite(nom, specialite.nombre_annees)?;
+
+    let updated = sqlx::query!(
         "UPDATE specialites SET nom = $1, nombre_annees = $2 WHERE id_specialite = $3",
-        specialite.nom,
+        nom,
         specialite.nombre_annees,
         specialite.id_specialite
     )
     .execute(pool)
     .await
-    .map_err(|e| format!("Failed to modify specialty: {}", e))?;
+    .map_err(|e| map_unique_violation(e, nom))?;
+
+    if updated.rows_affected() == 0 {
+        return Err(SpecialiteError::NotFound);
+    }
 
     Ok(())
 }
@@ -151,14 +459,27 @@ pub enum ValidationError {
     EmptySpecialtyID,
     #[error("Veuillez sélectionner une banque")]
     EmptyBankID,
+    #[error("Le RIB doit contenir 24 chiffres avec une clé valide")]
+    InvalidRib,
+    #[error("La date de début et la spécialité ne peuvent plus être modifiées : le résident a des paiements verrouillés")]
+    LockedPayFields,
+    #[error("Impossible de supprimer un résident ayant des paiements verrouillés")]
+    LockedResident,
+    #[error("Résident introuvable")]
+    NotFound,
+    #[error("La CIN doit comporter une ou deux lettres suivies de six chiffres au plus")]
+    InvalidCin,
+    #[error("Cette CIN est déjà attribuée à un autre résident")]
+    DuplicateCin,
 }
 
 
-trait ValidatableResident {
+pub(crate) trait ValidatableResident {
     fn rib(&self) -> &str;
     fn nombre_enfants(&self) -> i32;
     fn id_specialite(&self) -> i32;
     fn id_banque(&self) -> i32;
+    fn cin(&self) -> Option<&str>;
 }
 
 impl ValidatableResident for Resident {
@@ -178,6 +499,10 @@ impl ValidatableResident for Resident {
     fn id_banque(&self) -> i32 {
         self.id_banque.unwrap_or(0)
     }
+
+    fn cin(&self) -> Option<&str> {
+        self.cin.as_deref()
+    }
 }
 
 impl ValidatableResident for NewResident {
@@ -197,10 +522,14 @@ impl ValidatableResident for NewResident {
     fn id_banque(&self) -> i32 {
         self.id_banque.unwrap_or(0)
     }
+
+    fn cin(&self) -> Option<&str> {
+        self.cin.as_deref()
+    }
 }
 
 
-fn validate_resident<R: ValidatableResident>(resident: &R) -> Result<(), String> {
+pub(crate) fn validate_resident<R: ValidatableResident>(resident: &R) -> Result<(), String> {
 
 
     if resident.nombre_enfants() < 0 {
@@ -215,6 +544,50 @@ fn validate_resident<R: ValidatableResident>(resident: &R) -> Result<(), String>
         return Err(ValidationError::EmptyBankID.to_string());
     }
 
+    if let Some(cin) = normalize_cin(resident.cin()) {
+        if !cin_valide(&cin) {
+            return Err(ValidationError::InvalidCin.to_string());
+        }
+    }
+
+    Ok(())
+}
+
+// CIN are stored in capitals without spaces; blank means not provided
+pub(crate) fn normalize_cin(cin: Option<&str>) -> Option<String> {
+    cin.map(|cin| cin.split_whitespace().collect::<String>().to_uppercase())
+        .filter(|cin| !cin.is_empty())
+}
+
+pub(crate) fn normalize_adresse(adresse: Option<&str>) -> Option<String> {
+    adresse.map(str::trim).filter(|adresse| !adresse.is_empty()).map(str::to_string)
+}
+
+pub(crate) fn map_resident_error(error: sqlx::Error, action: &str) -> String {
+    match &error {
+        sqlx::Error::Database(db_error) if db_error.constraint() == Some("residents_cin_key") => {
+            ValidationError::DuplicateCin.to_string()
+        }
+        _ => format!("Failed to {} resident: {}", action, error),
+    }
+}
+
+// A RIB is 24 digits (bank, city, account, key) and the whole number is divisible by 97
+pub(crate) fn validate_rib(rib: &str) -> Result<(), String> {
+    let digits: String = rib.chars().filter(|c| !c.is_whitespace()).collect();
+
+    if digits.len() != 24 || !digits.chars().all(|c| c.is_ascii_digit()) {
+        return Err(ValidationError::InvalidRib.to_string());
+    }
+
+    let remainder = digits
+        .bytes()
+        .fold(0u32, |acc, b| (acc * 10 + (b - b'0') as u32) % 97);
+
+    if remainder != 0 {
+        return Err(ValidationError::InvalidRib.to_string());
+    }
+
     Ok(())
 }
 
@@ -224,6 +597,11 @@ pub async fn get_residents(pool: State<'_, AppState>) -> Result<Vec<Resident>, S
     let pool = pool.pool.lock().await;
     let pool = pool.as_ref().ok_or("Database not connected")?;
 
+    fetch_residents(pool).await
+}
+
+// Residents still in post, as listed on the residents page and exported from it
+pub(crate) async fn fetch_residents(pool: &sqlx::PgPool) -> Result<Vec<Resident>, String> {
     // Fetch records with the correct type annotations
     let records = sqlx::query!(
         r#"
@@ -237,7 +615,9 @@ pub async fn get_residents(pool: State<'_, AppState>) -> Result<Vec<Resident>, S
             residents.nombre_enfants as "nombre_enfants",
             residents.id_banque as "id_banque",
             specialites.nom as "nom_specialite",
-            banque.nom as "nom_banque"
+            banque.nom as "nom_banque",
+            residents.cin,
+            residents.adresse
         FROM residents
         LEFT JOIN specialites ON residents.id_specialite = specialites.id_specialite
         LEFT JOIN banque ON residents.id_banque = banque.id_banque
@@ -261,6 +641,8 @@ pub async fn get_residents(pool: State<'_, AppState>) -> Result<Vec<Resident>, S
             id_banque: Some(record.id_banque.expect("id_bank is None")),
             nom_specialite: record.nom_specialite,
             nom_banque: record.nom_banque,
+            cin: record.cin,
+            adresse: record.adresse,
         })
         .collect();
 
@@ -301,33 +683,98 @@ pub async fn add_resident(pool: State<'_, AppState>, resident: NewResident) -> R
     validate_resident(&resident).map_err(|e| e.to_string())?;
   
     sqlx::query!(
-      "INSERT INTO residents (nom_prenom, date_debut, id_specialite, rib, nombre_enfants, id_banque) VALUES ($1, $2, $3, $4, $5, $6)",
+      "INSERT INTO residents (nom_prenom, date_debut, id_specialite, rib, nombre_enfants, id_banque, cin, adresse) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
       resident.nom_prenom,
       resident.date_debut,
       resident.id_specialite,
       resident.rib,
       resident.nombre_enfants.unwrap_or(0),
-      resident.id_banque
+      resident.id_banque,
+      normalize_cin(resident.cin.as_deref()),
+      normalize_adresse(resident.adresse.as_deref())
     )
     .execute(pool)
     .await
-    .map_err(|e| format!("Failed to add resident: {}", e))?;
+    .map_err(|e| map_resident_error(e, "add"))?;
   
     Ok(())
 }
 
+// Payments from validated runs, or generated before runs existed, can no longer change.
+// The runs paying these residents are locked until the end of the transaction, so
+// none of them can be validated between the check and the write that follows it.
+async fn count_locked_payments(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    residents: &[i32],
+) -> Result<i64, sqlx::Error> {
+    sqlx::query!(
+        "SELECT id_run FROM payroll_run
+         WHERE id_run IN (SELECT id_run FROM paiement_mensuel WHERE id_resident = ANY($1))
+         FOR SHARE",
+        residents
+    )
+    .fetch_all(&mut *tx)
+    .await?;
+
+    let record = sqlx::query!(
+        r#"
+        SELECT COUNT(*) as "count!"
+        FROM paiement_mensuel
+        LEFT JOIN payroll_run ON paiement_mensuel.id_run = payroll_run.id_run
+        WHERE paiement_mensuel.id_resident = ANY($1)
+          AND payroll_run.statut IS DISTINCT FROM 'brouillon'
+        "#,
+        residents
+    )
+    .fetch_one(&mut *tx)
+    .await?;
+
+    Ok(record.count)
+}
+
+// Locks the resident's row for the rest of the transaction
+async fn verrouiller_resident(tx: &mut sqlx::Transaction<'_, sqlx::Postgres>, id_resident: i32) -> Result<(NaiveDate, i32), String> {
+    let record = sqlx::query!(
+        "SELECT date_debut, id_specialite FROM residents WHERE id_resident = $1 FOR UPDATE",
+        id_resident
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to fetch resident: {}", e))?
+    .ok_or_else(|| ValidationError::NotFound.to_string())?;
+
+    Ok((record.date_debut, record.id_specialite))
+}
+
 #[tauri::command]
 pub async fn delete_resident(pool: State<'_, AppState>, id: i32) -> Result<(), String> {
     let pool = pool.pool.lock().await;
     let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    verrouiller_resident(&mut tx, id).await?;
+    let locked = count_locked_payments(&mut tx, &[id])
+        .await
+        .map_err(|e| format!("Failed to query database: {}", e))?;
+    if locked > 0 {
+        return Err(ValidationError::LockedResident.to_string());
+    }
   
     sqlx::query!(
         "DELETE FROM residents WHERE id_resident = $1",
         id
     )
-    .execute(pool)
+    .execute(&mut tx)
     .await
     .map_err(|e| format!("Failed to delete resident: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to delete resident: {}", e))?;
   
     Ok(())
 }
@@ -339,6 +786,24 @@ pub async fn modify_resident(pool: State<'_, AppState>, resident: Resident) -> R
 
     validate_resident(&resident).map_err(|e| e.to_string())?;
 
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // Start date and specialty drive the grid row of every past period, freeze them once paid.
+    // The number of children only affects runs generated after the change.
+    let (date_debut, id_specialite) = verrouiller_resident(&mut tx, resident.id_resident).await?;
+
+    if (date_debut != resident.date_debut || id_specialite != resident.id_specialite)
+        && count_locked_payments(&mut tx, &[resident.id_resident])
+            .await
+            .map_err(|e| format!("Failed to query database: {}", e))?
+            > 0
+    {
+        return Err(ValidationError::LockedPayFields.to_string());
+    }
+
     sqlx::query!(
         "UPDATE residents SET 
             nom_prenom = $1, 
@@ -346,8 +811,10 @@ pub async fn modify_resident(pool: State<'_, AppState>, resident: Resident) -> R
             id_specialite = $3,
             id_banque = $4,
             rib = $5, 
-            nombre_enfants = $6
-            WHERE id_resident = $7",
+            nombre_enfants = $6,
+            cin = $7,
+            adresse = $8
+            WHERE id_resident = $9",
 
         resident.nom_prenom,
         resident.date_debut,
@@ -355,12 +822,15 @@ pub async fn modify_resident(pool: State<'_, AppState>, resident: Resident) -> R
         resident.id_banque,
         resident.rib,
         resident.nombre_enfants,
+        normalize_cin(resident.cin.as_deref()),
+        normalize_adresse(resident.adresse.as_deref()),
         resident.id_resident
     )
-    .execute(pool)
+    .execute(&mut tx)
     .await
-    .map_err(|e| format!("Failed to modify resident: {}", e))?;
-    
+    .map_err(|e| map_resident_error(e, "modify"))?;
+
+    tx.commit().await.map_err(|e| map_resident_error(e, "modify"))?;
 
     Ok(())
 }
@@ -373,6 +843,10 @@ pub async fn get_paiments(state: State<'_, AppState>) -> Result<Vec<PaiementMens
     let pool = state.pool.lock().await;
     let conn = pool.as_ref().ok_or("Database not connected")?;
 
+    fetch_paiements(conn).await
+}
+
+pub(crate) async fn fetch_paiements(conn: &sqlx::PgPool) -> Result<Vec<PaiementMensuel>, String> {
     let records = sqlx::query!(
         r#"
         SELECT 
@@ -382,6 +856,15 @@ pub async fn get_paiments(state: State<'_, AppState>) -> Result<Vec<PaiementMens
             paiement_mensuel.allocations_familiales,
             paiement_mensuel.montant,
             paiement_mensuel.date_paiement,
+            paiement_mensuel.id_bareme,
+            paiement_mensuel.salaire_base,
+            paiement_mensuel.indemnite_fonction,
+            paiement_mensuel.indemnite_logement,
+            paiement_mensuel.indemnite_transport,
+            paiement_mensuel.salaire_brut,
+            paiement_mensuel.total_retenues,
+            paiement_mensuel.statut_virement,
+            paiement_mensuel.motif_rejet,
             residents.nom_prenom as nom_resident,
             residents.rib as rib_string,
             banque.nom as nom_banque
@@ -406,30 +889,21 @@ pub async fn get_paiments(state: State<'_, AppState>) -> Result<Vec<PaiementMens
             nom_resident: record.nom_resident,
             rib: record.rib_string.expect("Failed to parse rib as i32"),
             nom_banque: record.nom_banque,
+            id_bareme: record.id_bareme,
+            salaire_base: record.salaire_base,
+            indemnite_fonction: record.indemnite_fonction,
+            indemnite_logement: record.indemnite_logement,
+            indemnite_transport: record.indemnite_transport,
+            salaire_brut: record.salaire_brut,
+            total_retenues: record.total_retenues,
+            statut_virement: record.statut_virement,
+            motif_rejet: record.motif_rejet,
         })
         .collect();
 
     Ok(payments)
 }
 
-#[tauri::command]
-pub async fn generate_payments(pool: State<'_, AppState>) -> Result<(), String> {
-    let pool = pool.pool.lock().await;
-    let pool = pool.as_ref().ok_or("Database not connected")?;
-
-    let current_date: NaiveDate = Local::now().naive_local().date();
-
-    sqlx::query!(
-        "SELECT generate_monthly_payments($1)",
-        current_date
-    )
-    .execute(pool)
-    .await
-    .map_err(|e| format!("Failed to generate payments: {}", e))?;
-
-    Ok(())
-}
-
 //manage rappels annuels
 
 #[tauri::command]
@@ -437,6 +911,10 @@ pub async fn get_rappels(pool: State<'_, AppState>) -> Result<Vec<RappelAnnuel>,
     let pool_guard = pool.pool.lock().await;
     let pool_ref = pool_guard.as_ref().ok_or("Database not connected")?;
 
+    fetch_rappels(pool_ref).await
+}
+
+pub(crate) async fn fetch_rappels(pool_ref: &sqlx::PgPool) -> Result<Vec<RappelAnnuel>, String> {
     let records = sqlx::query!(
         r#"
         SELECT 
@@ -446,6 +924,9 @@ pub async fn get_rappels(pool: State<'_, AppState>) -> Result<Vec<RappelAnnuel>,
             rappels_annuels.duree_rappel as "duree_rappel?",
             rappels_annuels.montant as "montant?",
             rappels_annuels.date_generation as "date_generation?",
+            rappels_annuels.retenue_recouvrement,
+            rappels_annuels.statut_virement,
+            rappels_annuels.motif_rejet,
             residents.nom_prenom as "nom_resident?",
             residents.rib as "rib?",
             banque.nom as "nom_banque?"
@@ -470,6 +951,9 @@ pub async fn get_rappels(pool: State<'_, AppState>) -> Result<Vec<RappelAnnuel>,
     nom_resident: record.nom_resident,
     rib: record.rib.expect("Failed to parse rib as i32"),
     nom_banque: record.nom_banque,
+    retenue_recouvrement: record.retenue_recouvrement,
+    statut_virement: record.statut_virement,
+    motif_rejet: record.motif_rejet,
     })
     .collect();
 
@@ -478,20 +962,37 @@ pub async fn get_rappels(pool: State<'_, AppState>) -> Result<Vec<RappelAnnuel>,
 
 
 #[tauri::command]
-pub async fn generate_rappel(pool: State<'_, AppState>, resident_id: i32) -> Result<(), String> {
+pub async fn generate_rappel(pool: State<'_, AppState>, resident_id: i32, exercice: i32) -> Result<(), String> {
     let pool = pool.pool.lock().await;
     let pool = pool.as_ref().ok_or("Database not connected")?;
 
-    let current_date: NaiveDate = Local::now().naive_local().date();
+    let today: NaiveDate = Local::now().naive_local().date();
+    valider_exercice(exercice, today).map_err(|e| e.to_string())?;
+
+    // Close of the exercice, or today while it is still running
+    let date_rappel = NaiveDate::from_ymd_opt(exercice, 12, 31)
+        .ok_or("Invalid exercice")?
+        .min(today);
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
 
     sqlx::query!(
         "SELECT generate_yearly_payments($1, $2)",
         resident_id,
-        current_date
+        date_rappel
     )
-    .execute(pool)
+    .execute(&mut tx)
     .await
     .map_err(|e| format!("Failed to generate rappel: {}", e))?;
 
+    imputer_echeances_rappel(&mut tx, resident_id, exercice).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to generate rappel: {}", e))?;
+
     Ok(())
 }
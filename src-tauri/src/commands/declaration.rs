@@ -0,0 +1,204 @@
+use crate::commands::retenues::fetch_regime_retenues;
+use crate::documents::{feuilles_declaration, rendre_classeur, rendre_declaration_xml, Employeur};
+use crate::models::{AppState, RapportDeclaration};
+use crate::payroll::{controler, declarer, frais_professionnels, ElementDeclare, LigneDeclaration, Periode, SalarieDeclare};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Datelike, Local, NaiveDate};
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::State;
+
+
+//declaring the salaries of the year (état 9421)
+
+// Monthly payments of the year and rappels paid during it, totalled per resident.
+// Payments of draft runs and transfers the bank rejected are not declared.
+async fn charger_declaration(pool: &sqlx::PgPool, annee: i32) -> Result<Vec<LigneDeclaration>, String> {
+    let debut = NaiveDate::from_ymd_opt(annee, 1, 1).ok_or_else(|| format!("Année invalide : {}", annee))?;
+    let fin = NaiveDate::from_ymd_opt(annee, 12, 31).ok_or_else(|| format!("Année invalide : {}", annee))?;
+
+    let regime = fetch_regime_retenues(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch deduction rates: {}", e))?;
+
+    let residents = sqlx::query!(
+        r#"
+        SELECT residents.id_resident, residents.nom_prenom, residents.cin, residents.adresse, residents.nombre_enfants
+        FROM residents
+        WHERE EXISTS (
+                SELECT 1 FROM paiement_mensuel
+                LEFT JOIN payroll_run ON paiement_mensuel.id_run = payroll_run.id_run
+                WHERE paiement_mensuel.id_resident = residents.id_resident AND paiement_mensuel.periode BETWEEN $1 AND $2
+                  AND (paiement_mensuel.id_run IS NULL OR payroll_run.statut IN ('valide', 'envoye', 'paye'))
+                  AND paiement_mensuel.statut_virement <> 'rejete'
+            )
+           OR EXISTS (
+                SELECT 1 FROM rappels_annuels
+                WHERE rappels_annuels.id_resident = residents.id_resident
+                  AND rappels_annuels.date_generation BETWEEN $1 AND $2 AND rappels_annuels.montant > 0
+                  AND rappels_annuels.statut_virement <> 'rejete'
+            )
+        ORDER BY residents.nom_prenom
+        "#,
+        debut,
+        fin
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch residents: {}", e))?;
+
+    // Contributions are the deductions whose code is a contribution rate; the rest
+    // of the deductions, other than income tax, are adjustments and recoveries
+    let paiements = sqlx::query!(
+        r#"
+        SELECT
+            paiement_mensuel.id_resident as "id_resident!",
+            paiement_mensuel.periode,
+            paiement_mensuel.jours_travail,
+            paiement_mensuel.montant,
+            paiement_mensuel.allocations_familiales,
+            paiement_mensuel.salaire_base,
+            paiement_mensuel.salaire_brut,
+            paiement_mensuel.total_retenues,
+            (SELECT COALESCE(SUM(lignes_paiement.montant), 0) FROM lignes_paiement
+             WHERE lignes_paiement.id_paiement = paiement_mensuel.id_paiement AND lignes_paiement.categorie = 'retenue'
+               AND lignes_paiement.code IN (SELECT code FROM taux_cotisation)) as "cotisations!",
+            (SELECT COALESCE(SUM(lignes_paiement.montant), 0) FROM lignes_paiement
+             WHERE lignes_paiement.id_paiement = paiement_mensuel.id_paiement AND lignes_paiement.code = 'IR') as "ir!",
+            EXISTS (SELECT 1 FROM lignes_paiement
+             WHERE lignes_paiement.id_paiement = paiement_mensuel.id_paiement AND lignes_paiement.categorie = 'retenue') as "detaille!",
+            paiement_mensuel.id_run IS NULL as "hors_run!"
+        FROM paiement_mensuel
+        LEFT JOIN payroll_run ON paiement_mensuel.id_run = payroll_run.id_run
+        WHERE paiement_mensuel.periode BETWEEN $1 AND $2 AND paiement_mensuel.id_resident IS NOT NULL
+          AND (paiement_mensuel.id_run IS NULL OR payroll_run.statut IN ('valide', 'envoye', 'paye'))
+          AND paiement_mensuel.statut_virement <> 'rejete'
+        "#,
+        debut,
+        fin
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch payments: {}", e))?;
+
+    let rappels = sqlx::query!(
+        r#"
+        SELECT id_resident as "id_resident!", montant, retenue_recouvrement
+        FROM rappels_annuels
+        WHERE date_generation BETWEEN $1 AND $2 AND montant > 0 AND id_resident IS NOT NULL
+          AND statut_virement <> 'rejete'
+        "#,
+        debut,
+        fin
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch rappels: {}", e))?;
+
+    let mut elements: HashMap<i32, Vec<ElementDeclare>> = HashMap::new();
+
+    for record in paiements {
+        let allocations = record.allocations_familiales.unwrap_or_else(BigDecimal::zero);
+        let retenues = record.total_retenues.unwrap_or_else(BigDecimal::zero);
+        let sans_detail = record.salaire_brut.is_none() || (!record.detaille && !retenues.is_zero());
+        let brut = record
+            .salaire_brut
+            .unwrap_or_else(|| &record.montant + &retenues - &allocations);
+        let autres_retenues = &retenues - &record.cotisations - &record.ir;
+
+        elements.entry(record.id_resident).or_default().push(ElementDeclare {
+            salaire_base: record.salaire_base.unwrap_or_else(BigDecimal::zero),
+            frais_professionnels: frais_professionnels(&regime, Periode::contenant(record.periode), &brut),
+            brut_imposable: brut,
+            exonere: allocations,
+            cotisations: record.cotisations,
+            ir: record.ir,
+            autres_retenues,
+            jours: record.jours_travail,
+            sans_detail,
+            hors_run: record.hors_run,
+        });
+    }
+
+    // The overpayment recovered on a rappel was taxable income all the same
+    for record in rappels {
+        elements.entry(record.id_resident).or_default().push(ElementDeclare {
+            brut_imposable: &record.montant + &record.retenue_recouvrement,
+            autres_retenues: record.retenue_recouvrement,
+            ..Default::default()
+        });
+    }
+
+    Ok(residents
+        .into_iter()
+        .map(|resident| {
+            let salarie = SalarieDeclare {
+                id_resident: resident.id_resident,
+                nom_prenom: resident.nom_prenom,
+                cin: resident.cin,
+                adresse: resident.adresse,
+                nombre_charges: resident.nombre_enfants.unwrap_or(0),
+            };
+            declarer(salarie, elements.get(&resident.id_resident).map(Vec::as_slice).unwrap_or_default())
+        })
+        .collect())
+}
+
+// Totals the salaries paid in `annee` per resident and checks what the tax office
+// requires. With `chemin`, also writes the declaration there: `format` "xml" for
+// filing, refused while blocking problems remain, or "xlsx" to review it.
+#[tauri::command]
+pub async fn export_tax_declaration(
+    state: State<'_, AppState>,
+    annee: i32,
+    format: String,
+    chemin: Option<String>,
+) -> Result<RapportDeclaration, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    if format != "xml" && format != "xlsx" {
+        return Err(format!("Format inconnu : {} (xml ou xlsx)", format));
+    }
+    if annee > Local::now().naive_local().date().year() {
+        return Err(format!("L'année {} n'est pas encore commencée", annee));
+    }
+
+    let lignes = charger_declaration(pool, annee).await?;
+    if lignes.is_empty() {
+        return Err(format!("Aucun paiement en {}", annee));
+    }
+
+    let employeur = Employeur::depuis_env();
+    let anomalies = controler(employeur.identifiant_fiscal.as_deref(), &lignes);
+    let bloquee = anomalies.iter().any(|anomalie| anomalie.bloquante);
+
+    let fichier = match chemin {
+        Some(chemin) if format == "xlsx" || !bloquee => {
+            let contenu = if format == "xml" {
+                rendre_declaration_xml(&employeur, annee, &lignes).map_err(|e| e.to_string())?.into_bytes()
+            } else {
+                rendre_classeur(&feuilles_declaration(&employeur, annee, &lignes, &anomalies)).map_err(|e| e.to_string())?
+            };
+
+            let chemin = Path::new(&chemin);
+            std::fs::write(chemin, contenu).map_err(|e| format!("Failed to write {}: {}", chemin.display(), e))?;
+            Some(chemin.display().to_string())
+        }
+        _ => None,
+    };
+
+    let total = |montant: fn(&LigneDeclaration) -> &BigDecimal| {
+        lignes.iter().fold(BigDecimal::zero(), |total, ligne| total + montant(ligne))
+    };
+
+    Ok(RapportDeclaration {
+        annee,
+        nombre_salaries: lignes.len(),
+        brut_imposable: total(|ligne| &ligne.brut_imposable),
+        net_imposable: total(|ligne| &ligne.net_imposable),
+        ir: total(|ligne| &ligne.ir),
+        fichier,
+        anomalies,
+    })
+}
@@ -0,0 +1,68 @@
+use crate::commands::db::{fetch_paiements, fetch_rappels, fetch_residents};
+use crate::documents::{rendre_liste, retenir, Exportable, FormatExport};
+use crate::models::{AppState, FiltreListe, Specialite};
+use std::path::Path;
+use tauri::State;
+
+
+//exporting lists to CSV/XLSX
+
+fn ecrire_liste<T: Exportable>(
+    titre: &str,
+    lignes: &[T],
+    filtre: &FiltreListe,
+    colonnes: &[String],
+    format: FormatExport,
+    virgule: bool,
+    chemin: &Path,
+) -> Result<usize, String> {
+    let lignes: Vec<&T> = lignes.iter().filter(|ligne| retenir(filtre, *ligne)).collect();
+    let contenu = rendre_liste(titre, &lignes, colonnes, format, virgule).map_err(|e| e.to_string())?;
+    std::fs::write(chemin, contenu).map_err(|e| format!("Failed to write {}: {}", chemin.display(), e))?;
+    Ok(lignes.len())
+}
+
+// Writes the residents, specialties, payments or rappels matching `filtre` to
+// `chemin`, with the chosen columns in that order (all of them when none is
+// given). Returns the number of rows written.
+#[tauri::command]
+pub async fn export_list(
+    state: State<'_, AppState>,
+    liste: String,
+    filtre: Option<FiltreListe>,
+    colonnes: Option<Vec<String>>,
+    format: String,
+    virgule: bool,
+    chemin: String,
+) -> Result<usize, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let format = FormatExport::parse(&format).ok_or_else(|| format!("Format d'export inconnu : {}", format))?;
+    let filtre = filtre.unwrap_or_default();
+    let colonnes = colonnes.unwrap_or_default();
+    let chemin = Path::new(&chemin);
+
+    match liste.as_str() {
+        "residents" => {
+            let residents = fetch_residents(pool).await?;
+            ecrire_liste("Résidents", &residents, &filtre, &colonnes, format, virgule, chemin)
+        }
+        "specialites" => {
+            let specialites = sqlx::query_as::<_, Specialite>("SELECT id_specialite, nom, nombre_annees FROM specialites ORDER BY nom")
+                .fetch_all(pool)
+                .await
+                .map_err(|e| format!("Failed to fetch specialties: {}", e))?;
+            ecrire_liste("Spécialités", &specialites, &filtre, &colonnes, format, virgule, chemin)
+        }
+        "paiements" => {
+            let paiements = fetch_paiements(pool).await?;
+            ecrire_liste("Paiements", &paiements, &filtre, &colonnes, format, virgule, chemin)
+        }
+        "rappels" => {
+            let rappels = fetch_rappels(pool).await?;
+            ecrire_liste("Rappels", &rappels, &filtre, &colonnes, format, virgule, chemin)
+        }
+        autre => Err(format!("Liste inconnue : {}", autre)),
+    }
+}
@@ -0,0 +1,386 @@
+use crate::commands::db::{map_resident_error, normalize_adresse, normalize_cin, validate_resident, validate_rib};
+use crate::models::{AppState, ImportReport, ImportRowError, NewResident};
+use calamine::{open_workbook_auto, Data, Reader};
+use chrono::{Duration, NaiveDate};
+use std::collections::{HashMap, HashSet};
+use std::path::Path;
+use tauri::State;
+
+
+//importing residents from CSV/XLSX
+
+#[derive(Default)]
+struct ColumnMap {
+    nom_prenom: Option<usize>,
+    date_debut: Option<usize>,
+    specialite: Option<usize>,
+    rib: Option<usize>,
+    banque: Option<usize>,
+    nombre_enfants: Option<usize>,
+    cin: Option<usize>,
+    adresse: Option<usize>,
+}
+
+impl ColumnMap {
+    fn from_header(header: &[String]) -> Result<Self, String> {
+        let mut columns = ColumnMap::default();
+
+        for (index, name) in header.iter().enumerate() {
+            match normalize(name).as_str() {
+                "nomprenom" | "nometprenom" | "nom" | "resident" => columns.nom_prenom = Some(index),
+                "datedebut" | "datededebut" | "debut" => columns.date_debut = Some(index),
+                "specialite" | "nomspecialite" => columns.specialite = Some(index),
+                "rib" => columns.rib = Some(index),
+                "banque" | "nombanque" => columns.banque = Some(index),
+                "nombreenfants" | "nombredenfants" | "enfants" => columns.nombre_enfants = Some(index),
+                "cin" | "cni" | "numerocin" => columns.cin = Some(index),
+                "adresse" | "adressepersonnelle" => columns.adresse = Some(index),
+                _ => {}
+            }
+        }
+
+        let missing: Vec<&str> = [
+            ("nom_prenom", columns.nom_prenom),
+            ("date_debut", columns.date_debut),
+            ("specialite", columns.specialite),
+            ("rib", columns.rib),
+            ("banque", columns.banque),
+        ]
+        .iter()
+        .filter(|(_, index)| index.is_none())
+        .map(|(name, _)| *name)
+        .collect();
+
+        if !missing.is_empty() {
+            return Err(format!("Colonnes manquantes : {}", missing.join(", ")));
+        }
+
+        Ok(columns)
+    }
+}
+
+// Lowercase, strip accents and separators so "Date de début" and "date_debut" compare equal
+pub(crate) fn normalize(value: &str) -> String {
+    value
+        .trim()
+        .to_lowercase()
+        .chars()
+        .map(|c| match c {
+            'à' | 'â' | 'ä' => 'a',
+            'é' | 'è' | 'ê' | 'ë' => 'e',
+            'î' | 'ï' => 'i',
+            'ô' | 'ö' => 'o',
+            'ù' | 'û' | 'ü' => 'u',
+            'ç' => 'c',
+            other => other,
+        })
+        .filter(|c| c.is_alphanumeric())
+        .collect()
+}
+
+pub(crate) fn cell(row: &[String], index: Option<usize>) -> &str {
+    index
+        .and_then(|i| row.get(i))
+        .map(|value| value.trim())
+        .unwrap_or("")
+}
+
+fn parse_date(value: &str) -> Option<NaiveDate> {
+    ["%Y-%m-%d", "%d/%m/%Y", "%d-%m-%Y"]
+        .iter()
+        .find_map(|format| NaiveDate::parse_from_str(value, format).ok())
+}
+
+// Excel stores dates as days since 1899-12-30
+fn excel_serial_to_date(serial: f64) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt(1899, 12, 30)?.checked_add_signed(Duration::days(serial.trunc() as i64))
+}
+
+fn data_to_string(data: &Data) -> String {
+    match data {
+        Data::Empty => String::new(),
+        Data::String(value) => value.trim().to_string(),
+        Data::Float(value) if value.fract() == 0.0 => format!("{:.0}", value),
+        Data::DateTime(value) => excel_serial_to_date(value.as_f64())
+            .map(|date| date.format("%Y-%m-%d").to_string())
+            .unwrap_or_default(),
+        other => other.to_string(),
+    }
+}
+
+fn read_csv(path: &Path) -> Result<Vec<Vec<String>>, String> {
+    let content = std::fs::read_to_string(path)
+        .map_err(|e| format!("Failed to read file: {}", e))?;
+    let content = content.trim_start_matches('\u{feff}');
+
+    // Excel in French locales exports CSV with ';'
+    let first_line = content.lines().next().unwrap_or("");
+    let delimiter = if first_line.contains(';') { b';' } else { b',' };
+
+    let mut reader = csv::ReaderBuilder::new()
+        .has_headers(false)
+        .flexible(true)
+        .delimiter(delimiter)
+        .from_reader(content.as_bytes());
+
+    reader
+        .records()
+        .map(|record| {
+            record
+                .map(|r| r.iter().map(|value| value.trim().to_string()).collect())
+                .map_err(|e| format!("Failed to parse CSV: {}", e))
+        })
+        .collect()
+}
+
+fn read_xlsx(path: &Path) -> Result<Vec<Vec<String>>, String> {
+    let mut workbook = open_workbook_auto(path)
+        .map_err(|e| format!("Failed to open workbook: {}", e))?;
+
+    let range = workbook
+        .worksheet_range_at(0)
+        .ok_or("The workbook has no worksheet")?
+        .map_err(|e| format!("Failed to read worksheet: {}", e))?;
+
+    Ok(range
+        .rows()
+        .map(|row| row.iter().map(data_to_string).collect())
+        .collect())
+}
+
+pub(crate) fn read_rows(path: &Path) -> Result<Vec<Vec<String>>, String> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "csv" => read_csv(path),
+        "xlsx" | "xls" | "ods" => read_xlsx(path),
+        _ => Err("Unsupported file type, expected .csv or .xlsx".to_string()),
+    }
+}
+
+fn parse_row(
+    row: &[String],
+    columns: &ColumnMap,
+    specialites: &HashMap<String, i32>,
+    banques: &HashMap<String, i32>,
+) -> Result<NewResident, String> {
+    let nom_prenom = cell(row, columns.nom_prenom);
+    if nom_prenom.is_empty() {
+        return Err("Le nom et prénom est obligatoire".to_string());
+    }
+
+    let date_debut = parse_date(cell(row, columns.date_debut))
+        .ok_or("Date de début invalide (attendu AAAA-MM-JJ ou JJ/MM/AAAA)")?;
+
+    let specialite = cell(row, columns.specialite);
+    let id_specialite = specialites
+        .get(&normalize(specialite))
+        .copied()
+        .ok_or_else(|| format!("Spécialité inconnue : {}", specialite))?;
+
+    let banque = cell(row, columns.banque);
+    let id_banque = banques
+        .get(&normalize(banque))
+        .copied()
+        .ok_or_else(|| format!("Banque inconnue : {}", banque))?;
+
+    let nombre_enfants = match cell(row, columns.nombre_enfants) {
+        "" => 0,
+        value => value
+            .parse::<i32>()
+            .map_err(|_| format!("Nombre d'enfants invalide : {}", value))?,
+    };
+
+    let resident = NewResident {
+        nom_prenom: nom_prenom.to_string(),
+        date_debut,
+        id_specialite: Some(id_specialite),
+        rib: cell(row, columns.rib).split_whitespace().collect(),
+        id_banque: Some(id_banque),
+        nombre_enfants: Some(nombre_enfants),
+        cin: normalize_cin(Some(cell(row, columns.cin))),
+        adresse: normalize_adresse(Some(cell(row, columns.adresse))),
+    };
+
+    validate_resident(&resident)?;
+    validate_rib(&resident.rib)?;
+
+    Ok(resident)
+}
+
+#[tauri::command]
+pub async fn import_residents(state: State<'_, AppState>, path: String, dry_run: bool) -> Result<ImportReport, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let rows = read_rows(Path::new(&path))?;
+    let (header, rows) = rows.split_first().ok_or("The file is empty")?;
+    let columns = ColumnMap::from_header(header)?;
+
+    let specialites: HashMap<String, i32> = sqlx::query!("SELECT id_specialite, nom FROM specialites")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch specialties: {}", e))?
+        .into_iter()
+        .map(|record| (normalize(&record.nom), record.id_specialite))
+        .collect();
+
+    let banques: HashMap<String, i32> = sqlx::query!("SELECT id_banque, nom FROM banque")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch banks: {}", e))?
+        .into_iter()
+        .map(|record| (normalize(&record.nom), record.id_banque))
+        .collect();
+
+    // RIBs are stored without spaces, as parse_row leaves them
+    let ribs_existants: HashSet<String> = sqlx::query!("SELECT rib FROM residents WHERE rib IS NOT NULL")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch residents: {}", e))?
+        .into_iter()
+        .filter_map(|record| record.rib)
+        .map(|rib| rib.split_whitespace().collect())
+        .collect();
+
+    let mut valid_residents = Vec::new();
+    let mut erreurs = Vec::new();
+    let mut total_lignes = 0;
+
+    for (index, row) in rows.iter().enumerate() {
+        if row.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+        total_lignes += 1;
+
+        // Line numbers as shown in a spreadsheet: 1-based, header on line 1
+        let ligne = index + 2;
+
+        match parse_row(row, &columns, &specialites, &banques) {
+            Ok(resident) => {
+                let doublon = if ribs_existants.contains(&resident.rib) {
+                    Some("RIB déjà attribué à un résident existant")
+                } else if valid_residents.iter().any(|r: &NewResident| r.rib == resident.rib) {
+                    Some("RIB en double dans le fichier")
+                } else {
+                    None
+                };
+
+                match doublon {
+                    Some(message) => erreurs.push(ImportRowError {
+                        ligne,
+                        nom_prenom: Some(resident.nom_prenom),
+                        message: message.to_string(),
+                    }),
+                    None => valid_residents.push(resident),
+                }
+            }
+            Err(message) => erreurs.push(ImportRowError {
+                ligne,
+                nom_prenom: Some(cell(row, columns.nom_prenom).to_string()).filter(|nom| !nom.is_empty()),
+                message,
+            }),
+        }
+    }
+
+    let lignes_valides = valid_residents.len();
+
+    if !dry_run && !valid_residents.is_empty() {
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        for resident in &valid_residents {
+            sqlx::query!(
+                "INSERT INTO residents (nom_prenom, date_debut, id_specialite, rib, nombre_enfants, id_banque, cin, adresse) VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                resident.nom_prenom,
+                resident.date_debut,
+                resident.id_specialite,
+                resident.rib,
+                resident.nombre_enfants.unwrap_or(0),
+                resident.id_banque,
+                resident.cin,
+                resident.adresse
+            )
+            .execute(&mut tx)
+            .await
+            .map_err(|e| format!("{} : {}", resident.nom_prenom, map_resident_error(e, "import")))?;
+        }
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit import: {}", e))?;
+    }
+
+    Ok(ImportReport {
+        total_lignes,
+        lignes_valides,
+        lignes_importees: if dry_run { 0 } else { lignes_valides },
+        dry_run,
+        erreurs,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(names: &[&str]) -> Vec<String> {
+        names.iter().map(|name| name.to_string()).collect()
+    }
+
+    #[test]
+    fn normalize_ignores_case_accents_and_separators() {
+        assert_eq!(normalize(" Date de Début "), "datededebut");
+        assert_eq!(normalize("date_debut"), "datedebut");
+        assert_eq!(normalize("Spécialité"), "specialite");
+        assert_eq!(normalize("Nombre d'enfants"), "nombredenfants");
+        assert_eq!(normalize("Crédit Agricole du Maroc"), "creditagricoledumaroc");
+    }
+
+    #[test]
+    fn dates_are_read_in_iso_and_french_formats() {
+        let date = NaiveDate::from_ymd_opt(2025, 3, 7);
+        assert_eq!(parse_date("2025-03-07"), date);
+        assert_eq!(parse_date("07/03/2025"), date);
+        assert_eq!(parse_date("07-03-2025"), date);
+        assert_eq!(parse_date("2025/03/07"), None);
+        assert_eq!(parse_date("31/02/2025"), None);
+        assert_eq!(parse_date(""), None);
+        assert_eq!(excel_serial_to_date(45723.0), date);
+    }
+
+    #[test]
+    fn header_columns_are_found_by_any_known_name() {
+        let columns = ColumnMap::from_header(&header(&[
+            "Banque",
+            "Nom et prénom",
+            "RIB",
+            "Date de début",
+            "Spécialité",
+            "Enfants",
+            "CIN",
+        ]))
+        .unwrap();
+
+        assert_eq!(columns.banque, Some(0));
+        assert_eq!(columns.nom_prenom, Some(1));
+        assert_eq!(columns.rib, Some(2));
+        assert_eq!(columns.date_debut, Some(3));
+        assert_eq!(columns.specialite, Some(4));
+        assert_eq!(columns.nombre_enfants, Some(5));
+        assert_eq!(columns.cin, Some(6));
+        assert_eq!(columns.adresse, None);
+    }
+
+    #[test]
+    fn missing_required_columns_are_all_reported() {
+        let erreur = ColumnMap::from_header(&header(&["nom_prenom", "rib", "adresse"])).err().unwrap();
+        assert_eq!(erreur, "Colonnes manquantes : date_debut, specialite, banque");
+    }
+}
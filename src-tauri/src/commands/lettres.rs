@@ -0,0 +1,15 @@
+use crate::documents::{montant_en_lettres, montant_en_lettres_arabe};
+use bigdecimal::BigDecimal;
+
+
+//amounts in words
+
+// `langue` is "fr" or "ar"
+#[tauri::command]
+pub fn amount_in_words(montant: BigDecimal, langue: String) -> Result<String, String> {
+    match langue.as_str() {
+        "fr" => montant_en_lettres(&montant).map_err(|e| e.to_string()),
+        "ar" => montant_en_lettres_arabe(&montant).map_err(|e| e.to_string()),
+        _ => Err(format!("Langue non prise en charge : {}", langue)),
+    }
+}
@@ -1,21 +1,106 @@
 pub mod auth;
 pub mod db;
+pub mod import;
+pub mod bareme;
+pub mod payroll;
+pub mod absences;
+pub mod retenues;
+pub mod ajustements;
+pub mod recouvrements;
+pub mod bulletins;
+pub mod ov;
+pub mod lettres;
+pub mod virements;
+pub mod rapprochement;
+pub mod export;
+pub mod attestations;
+pub mod declaration;
 
-pub use auth::login;
+pub use auth::{login, get_session};
 pub use db::{
     connect_db,
     get_banques, 
+    add_banque,
+    modify_banque,
+    delete_banque,
     get_specialites, 
     add_specialite,
     get_residents,
     delete_specialite,
+    merge_specialites,
     modify_specialite, 
     add_resident,
     delete_resident,
     modify_resident,
     get_paiments,
-    generate_payments,
     get_rappels,
     generate_rappel,
     get_resident_id
 };
+pub use import::import_residents;
+pub use bareme::{
+    get_baremes,
+    add_bareme,
+    modify_bareme,
+    delete_bareme,
+    get_bareme_applicable
+};
+pub use payroll::{
+    generate_payments,
+    get_payroll_runs,
+    get_payroll_run_audit,
+    preview_payments,
+    change_payroll_run_status,
+    cancel_payroll_run,
+    get_payment_detail
+};
+pub use absences::{
+    get_absences,
+    add_absence,
+    modify_absence,
+    delete_absence
+};
+pub use retenues::{
+    get_taux_cotisations,
+    add_taux_cotisation,
+    delete_taux_cotisation,
+    get_tranches_ir,
+    add_tranche_ir,
+    delete_tranche_ir,
+    get_parametres_ir,
+    add_parametre_ir,
+    delete_parametre_ir
+};
+pub use ajustements::{
+    get_ajustements,
+    add_ajustement,
+    modify_ajustement,
+    delete_ajustement
+};
+pub use recouvrements::{
+    detect_overpayments,
+    get_recovery_schedules,
+    create_recovery_schedule
+};
+pub use bulletins::{
+    generate_payslip,
+    generate_payslips
+};
+pub use ov::{
+    generate_ov,
+    generate_ov_rappel,
+    generate_ov_par_banque,
+    generate_ov_reemission
+};
+pub use lettres::amount_in_words;
+pub use virements::{
+    export_bank_files,
+    export_pain001
+};
+pub use rapprochement::import_bank_return;
+pub use export::export_list;
+pub use attestations::{
+    add_modele_attestation, delete_modele_attestation, generate_salary_attestation, generate_work_certificate,
+    get_attestations_travail, get_modeles_attestation, modify_modele_attestation,
+};
+pub use declaration::export_tax_declaration;
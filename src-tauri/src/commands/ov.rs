@@ -0,0 +1,703 @@
+use crate::commands::auth::session_courante;
+use crate::commands::db::validate_rib;
+use crate::commands::virements::enregistrer_rib_envoye;
+use crate::documents::{
+    nom_fichier, remplir_ov, rendre_classeur, Cellule, Feuille, LigneOv, NumeroOrdre, OrdreVirement, GABARIT_PAIE,
+    GABARIT_RAPPEL, MOIS,
+};
+use crate::models::{AppState, DemandeReemission, ExportOrdres, OrdreBanque, OrdreReemission, PayrollRun};
+use crate::payroll::{peut_rapprocher, peut_reemettre, Origine, Periode, StatutRun};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Datelike, Local, NaiveDate};
+use std::path::Path;
+use tauri::State;
+
+
+//generating transfer orders (OV)
+
+fn ecrire_ov(chemin: &str, ov: &OrdreVirement, rappel: bool) -> Result<String, String> {
+    let gabarit = if rappel { &GABARIT_RAPPEL } else { &GABARIT_PAIE };
+    let contenu = remplir_ov(gabarit, ov).map_err(|e| e.to_string())?;
+    let chemin = Path::new(chemin);
+    std::fs::write(chemin, contenu).map_err(|e| format!("Failed to write {}: {}", chemin.display(), e))?;
+    Ok(chemin.display().to_string())
+}
+
+// Run of `periode`, which must have been validated before its transfers are ordered
+async fn charger_run_valide(pool: &sqlx::PgPool, periode: Periode) -> Result<i32, String> {
+    let run = sqlx::query_as::<_, PayrollRun>("SELECT * FROM payroll_run WHERE annee = $1 AND mois = $2")
+        .bind(periode.annee)
+        .bind(periode.mois as i32)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch payroll run: {}", e))?
+        .ok_or_else(|| format!("Aucun run de paie pour la période {}", periode))?;
+
+    if !StatutRun::parse(&run.statut).map_err(|e| e.to_string())?.est_verrouille() {
+        return Err("Le run de paie doit être validé avant l'édition des ordres de virement".to_string());
+    }
+
+    Ok(run.id_run)
+}
+
+// Writes the transfer order of the monthly payments of `annee`/`mois` to `chemin`,
+// optionally restricted to the residents of one bank
+#[tauri::command]
+pub async fn generate_ov(
+    state: State<'_, AppState>,
+    annee: i32,
+    mois: u32,
+    id_banque: Option<i32>,
+    chemin: String,
+) -> Result<String, String> {
+    let session = session_courante(&state).await?;
+    peut_rapprocher(session.role).map_err(|e| e.to_string())?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let periode = Periode::new(annee, mois).map_err(|e| e.to_string())?;
+    let id_run = charger_run_valide(pool, periode).await?;
+    enregistrer_rib_envoye(pool, id_run, id_banque).await?;
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            residents.nom_prenom as "nom_prenom?",
+            COALESCE(paiement_mensuel.rib_envoye, residents.rib) as "rib?",
+            banque.nom as "nom_banque?",
+            paiement_mensuel.montant
+        FROM paiement_mensuel
+        LEFT JOIN residents ON paiement_mensuel.id_resident = residents.id_resident
+        LEFT JOIN banque ON residents.id_banque = banque.id_banque
+        WHERE paiement_mensuel.id_run = $1 AND paiement_mensuel.montant > 0
+          AND ($2::int IS NULL OR residents.id_banque = $2)
+        ORDER BY residents.nom_prenom
+        "#,
+        id_run,
+        id_banque
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch payments: {}", e))?;
+
+    if records.is_empty() {
+        return Err(format!("Aucun paiement pour la période {}", periode));
+    }
+
+    let ov = OrdreVirement {
+        lignes: records
+            .into_iter()
+            .map(|record| LigneOv {
+                nom_prenom: record.nom_prenom.unwrap_or_default(),
+                rib: record.rib.unwrap_or_default(),
+                nom_banque: record.nom_banque.unwrap_or_default(),
+                montant: record.montant,
+            })
+            .collect(),
+        objet: objet_paie(periode),
+        numero: None,
+    };
+
+    ecrire_ov(&chemin, &ov, false)
+}
+
+fn objet_paie(periode: Periode) -> String {
+    format!(
+        "Le règlement des indemnités de fonction et allocations familiales des résidents au titre du mois de {} {}",
+        MOIS[periode.mois as usize - 1],
+        periode.annee
+    )
+}
+
+// Treasury summary of the per-bank orders of a month
+fn recapitulatif(periode: Periode, ordres: &[OrdreBanque], nombre: i32, total: &BigDecimal) -> Feuille {
+    Feuille {
+        nom: "Récapitulatif".to_string(),
+        titre: Some(format!(
+            "Récapitulatif des ordres de virement - {} {}",
+            MOIS[periode.mois as usize - 1],
+            periode.annee
+        )),
+        entetes: ["N° OV", "Banque", "Nombre de virements", "Montant"].iter().map(|e| e.to_string()).collect(),
+        lignes: ordres
+            .iter()
+            .map(|ordre| {
+                vec![
+                    Cellule::Texte(format!("{}/{}", ordre.numero, ordre.exercice)),
+                    Cellule::Texte(ordre.nom_banque.clone()),
+                    Cellule::Entier(ordre.nombre_virements as i64),
+                    Cellule::Montant(ordre.total.clone()),
+                ]
+            })
+            .collect(),
+        totaux: vec![vec![
+            Cellule::Texte("TOTAL".to_string()),
+            Cellule::Texte(format!("{} banque(s)", ordres.len())),
+            Cellule::Entier(nombre as i64),
+            Cellule::Montant(total.clone()),
+        ]],
+    }
+}
+
+// Writes one transfer order per bank for the monthly payments of `annee`/`mois`
+// into `dossier`, each numbered in the yearly sequence, plus a summary workbook
+// for the treasury
+#[tauri::command]
+pub async fn generate_ov_par_banque(
+    state: State<'_, AppState>,
+    annee: i32,
+    mois: u32,
+    dossier: String,
+) -> Result<ExportOrdres, String> {
+    let session = session_courante(&state).await?;
+    peut_rapprocher(session.role).map_err(|e| e.to_string())?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let periode = Periode::new(annee, mois).map_err(|e| e.to_string())?;
+    let id_run = charger_run_valide(pool, periode).await?;
+    enregistrer_rib_envoye(pool, id_run, None).await?;
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            residents.nom_prenom as "nom_prenom?",
+            COALESCE(paiement_mensuel.rib_envoye, residents.rib) as "rib?",
+            banque.id_banque as "id_banque?",
+            banque.nom as "nom_banque?",
+            paiement_mensuel.montant
+        FROM paiement_mensuel
+        LEFT JOIN residents ON paiement_mensuel.id_resident = residents.id_resident
+        LEFT JOIN banque ON residents.id_banque = banque.id_banque
+        WHERE paiement_mensuel.id_run = $1 AND paiement_mensuel.montant > 0
+        ORDER BY banque.nom, residents.nom_prenom
+        "#,
+        id_run
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch payments: {}", e))?;
+
+    if records.is_empty() {
+        return Err(format!("Aucun paiement pour la période {}", periode));
+    }
+
+    // Rows come sorted by bank: consecutive rows of the same bank form one order
+    let mut banques: Vec<(i32, Vec<LigneOv>)> = Vec::new();
+    for record in records {
+        let nom_prenom = record.nom_prenom.unwrap_or_default();
+        let id_banque = record
+            .id_banque
+            .ok_or_else(|| format!("Aucune banque n'est renseignée pour {}", nom_prenom))?;
+        let ligne = LigneOv {
+            nom_prenom,
+            rib: record.rib.unwrap_or_default(),
+            nom_banque: record.nom_banque.unwrap_or_default(),
+            montant: record.montant,
+        };
+
+        match banques.last_mut() {
+            Some((id, lignes)) if *id == id_banque => lignes.push(ligne),
+            _ => banques.push((id_banque, vec![ligne])),
+        }
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // Numbers are taken one after the other, never twice in the same year
+    sqlx::query!("LOCK TABLE ordres_virement IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to number transfer orders: {}", e))?;
+
+    let mut ordres = Vec::new();
+    for (id_banque, lignes) in banques {
+        let nom_banque = lignes[0].nom_banque.clone();
+        let total = lignes.iter().fold(BigDecimal::zero(), |total, ligne| total + &ligne.montant);
+        let nombre_virements = lignes.len() as i32;
+
+        let numero = sqlx::query!(
+            "INSERT INTO ordres_virement (exercice, numero, periode, id_banque, nombre_virements, total, created_by)
+             VALUES ($1, COALESCE((SELECT MAX(numero) FROM ordres_virement WHERE exercice = $1), 0) + 1, $2, $3, $4, $5, $6)
+             ON CONFLICT (periode, id_banque) WHERE NOT reemission DO UPDATE
+             SET nombre_virements = EXCLUDED.nombre_virements, total = EXCLUDED.total,
+                 created_by = EXCLUDED.created_by, created_at = NOW()
+             RETURNING numero",
+            periode.annee,
+            periode.debut(),
+            id_banque,
+            nombre_virements,
+            total,
+            session.username
+        )
+        .fetch_one(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to number transfer orders: {}", e))?
+        .numero;
+
+        let ov = OrdreVirement {
+            lignes,
+            objet: objet_paie(periode),
+            numero: Some(NumeroOrdre { numero, exercice: periode.annee }),
+        };
+        let chemin = Path::new(&dossier).join(format!(
+            "OV {} {} - {}.xlsx",
+            MOIS[periode.mois as usize - 1],
+            periode.annee,
+            nom_fichier(&nom_banque)
+        ));
+
+        ordres.push(OrdreBanque {
+            numero,
+            exercice: periode.annee,
+            nom_banque,
+            nombre_virements,
+            total,
+            fichier: ecrire_ov(&chemin.display().to_string(), &ov, false)?,
+        });
+    }
+
+    let nombre_virements: i32 = ordres.iter().map(|ordre| ordre.nombre_virements).sum();
+    let total = ordres.iter().fold(BigDecimal::zero(), |total, ordre| total + &ordre.total);
+
+    let classeur = rendre_classeur(&[recapitulatif(periode, &ordres, nombre_virements, &total)]).map_err(|e| e.to_string())?;
+    let chemin = Path::new(&dossier).join(format!(
+        "Récapitulatif OV {} {}.xlsx",
+        MOIS[periode.mois as usize - 1],
+        periode.annee
+    ));
+    std::fs::write(&chemin, classeur).map_err(|e| format!("Failed to write {}: {}", chemin.display(), e))?;
+
+    // Numbers are only kept once every file has been written
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to number transfer orders: {}", e))?;
+
+    Ok(ExportOrdres {
+        ordres,
+        recapitulatif: chemin.display().to_string(),
+        nombre_virements,
+        total,
+    })
+}
+
+// Writes the transfer order of the rappels generated during `annee`/`mois` to `chemin`,
+// one line per resident with the sum of their rappels
+#[tauri::command]
+pub async fn generate_ov_rappel(
+    state: State<'_, AppState>,
+    annee: i32,
+    mois: u32,
+    id_banque: Option<i32>,
+    chemin: String,
+) -> Result<String, String> {
+    let session = session_courante(&state).await?;
+    peut_rapprocher(session.role).map_err(|e| e.to_string())?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let periode = Periode::new(annee, mois).map_err(|e| e.to_string())?;
+    sqlx::query!(
+        "UPDATE rappels_annuels SET rib_envoye = residents.rib
+         FROM residents
+         WHERE rappels_annuels.id_resident = residents.id_resident
+           AND rappels_annuels.date_generation BETWEEN $1 AND $2
+           AND rappels_annuels.montant > 0 AND rappels_annuels.statut_virement = 'en_attente'
+           AND ($3::int IS NULL OR residents.id_banque = $3)",
+        periode.debut(),
+        periode.fin(),
+        id_banque
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record sent RIBs: {}", e))?;
+
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            residents.nom_prenom as "nom_prenom?",
+            residents.rib as "rib?",
+            banque.nom as "nom_banque?",
+            SUM(rappels_annuels.montant) as "montant!",
+            STRING_AGG(DISTINCT rappels_annuels.exercice::text, ', ') as "exercices!"
+        FROM rappels_annuels
+        LEFT JOIN residents ON rappels_annuels.id_resident = residents.id_resident
+        LEFT JOIN banque ON residents.id_banque = banque.id_banque
+        WHERE rappels_annuels.date_generation BETWEEN $1 AND $2
+          AND rappels_annuels.montant > 0
+          AND ($3::int IS NULL OR residents.id_banque = $3)
+        GROUP BY rappels_annuels.id_resident, residents.nom_prenom, residents.rib, banque.nom
+        ORDER BY residents.nom_prenom
+        "#,
+        periode.debut(),
+        periode.fin(),
+        id_banque
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch rappels: {}", e))?;
+
+    if records.is_empty() {
+        return Err(format!("Aucun rappel généré pour la période {}", periode));
+    }
+
+    let mut exercices: Vec<&str> = records.iter().flat_map(|record| record.exercices.split(", ")).collect();
+    exercices.sort_unstable();
+    exercices.dedup();
+    let objet = format!(
+        "RAPPEL DE L'INDEMNITE DE FONCTION DES MEDECINS RESIDENTS AU TITRE DE L'EXERCICE {}",
+        exercices.join(", ")
+    );
+
+    let ov = OrdreVirement {
+        lignes: records
+            .into_iter()
+            .map(|record| LigneOv {
+                nom_prenom: record.nom_prenom.unwrap_or_default(),
+                rib: record.rib.unwrap_or_default(),
+                nom_banque: record.nom_banque.unwrap_or_default(),
+                montant: record.montant,
+            })
+            .collect(),
+        objet,
+        numero: None,
+    };
+
+    ecrire_ov(&chemin, &ov, true)
+}
+
+// A rejected transfer as it stands before being reissued
+struct VirementRejete {
+    id: i32,
+    id_resident: i32,
+    nom_prenom: String,
+    rib: String,
+    nom_banque: String,
+    montant: BigDecimal,
+    // Month of the payment, or year of the rappel, and the date to sort them by
+    libelle: String,
+    date_reference: NaiveDate,
+    statut_virement: String,
+    motif_rejet: Option<String>,
+    rib_rejete: Option<String>,
+    statut_reemission: Option<String>,
+    rib_reemission: Option<String>,
+    motif_reemission: Option<String>,
+}
+
+async fn charger_rejet<'e>(
+    executor: impl sqlx::postgres::PgExecutor<'e>,
+    origine: Origine,
+    id: i32,
+) -> Result<Option<VirementRejete>, String> {
+    let rejet = match origine {
+        Origine::Paiement => sqlx::query!(
+            r#"
+            SELECT
+                paiement_mensuel.id_paiement,
+                paiement_mensuel.montant,
+                paiement_mensuel.periode,
+                paiement_mensuel.statut_virement,
+                paiement_mensuel.motif_rejet,
+                paiement_mensuel.rib_rejete,
+                residents.id_resident,
+                residents.nom_prenom,
+                residents.rib,
+                banque.nom as "nom_banque?",
+                derniere.statut_virement as "statut_reemission?",
+                derniere.rib as "rib_reemission?",
+                derniere.motif_rejet as "motif_reemission?"
+            FROM paiement_mensuel
+            JOIN residents ON paiement_mensuel.id_resident = residents.id_resident
+            LEFT JOIN banque ON residents.id_banque = banque.id_banque
+            LEFT JOIN LATERAL (
+                SELECT statut_virement, rib, motif_rejet FROM reemissions
+                WHERE reemissions.id_paiement = paiement_mensuel.id_paiement
+                ORDER BY id_reemission DESC LIMIT 1
+            ) derniere ON TRUE
+            WHERE paiement_mensuel.id_paiement = $1
+            "#,
+            id
+        )
+        .fetch_optional(executor)
+        .await
+        .map_err(|e| format!("Failed to fetch payment: {}", e))?
+        .map(|record| VirementRejete {
+            id: record.id_paiement,
+            id_resident: record.id_resident,
+            nom_prenom: record.nom_prenom,
+            rib: record.rib,
+            nom_banque: record.nom_banque.unwrap_or_default(),
+            montant: record.montant,
+            libelle: format!("{} {}", MOIS[record.periode.month0() as usize], record.periode.year()),
+            date_reference: record.periode,
+            statut_virement: record.statut_virement,
+            motif_rejet: record.motif_rejet,
+            rib_rejete: record.rib_rejete,
+            statut_reemission: record.statut_reemission,
+            rib_reemission: record.rib_reemission,
+            motif_reemission: record.motif_reemission,
+        }),
+        Origine::Rappel => sqlx::query!(
+            r#"
+            SELECT
+                rappels_annuels.id_rappel,
+                rappels_annuels.montant,
+                rappels_annuels.exercice,
+                rappels_annuels.statut_virement,
+                rappels_annuels.motif_rejet,
+                rappels_annuels.rib_rejete,
+                residents.id_resident,
+                residents.nom_prenom,
+                residents.rib,
+                banque.nom as "nom_banque?",
+                derniere.statut_virement as "statut_reemission?",
+                derniere.rib as "rib_reemission?",
+                derniere.motif_rejet as "motif_reemission?"
+            FROM rappels_annuels
+            JOIN residents ON rappels_annuels.id_resident = residents.id_resident
+            LEFT JOIN banque ON residents.id_banque = banque.id_banque
+            LEFT JOIN LATERAL (
+                SELECT statut_virement, rib, motif_rejet FROM reemissions
+                WHERE reemissions.id_rappel = rappels_annuels.id_rappel
+                ORDER BY id_reemission DESC LIMIT 1
+            ) derniere ON TRUE
+            WHERE rappels_annuels.id_rappel = $1
+            "#,
+            id
+        )
+        .fetch_optional(executor)
+        .await
+        .map_err(|e| format!("Failed to fetch rappel: {}", e))?
+        .map(|record| VirementRejete {
+            id: record.id_rappel,
+            id_resident: record.id_resident,
+            nom_prenom: record.nom_prenom,
+            rib: record.rib,
+            nom_banque: record.nom_banque.unwrap_or_default(),
+            montant: record.montant,
+            libelle: record.exercice.to_string(),
+            date_reference: NaiveDate::from_ymd_opt(record.exercice, 1, 1).unwrap_or(NaiveDate::MIN),
+            statut_virement: record.statut_virement,
+            motif_rejet: record.motif_rejet,
+            rib_rejete: record.rib_rejete,
+            statut_reemission: record.statut_reemission,
+            rib_reemission: record.rib_reemission,
+            motif_reemission: record.motif_reemission,
+        }),
+        Origine::Reemission => return Err("Sélectionnez le paiement ou le rappel d'origine à réémettre".to_string()),
+    };
+
+    Ok(rejet)
+}
+
+// Writes a transfer order for the selected rejected payments (or rappels) to
+// `chemin`, numbered in the yearly sequence. Each reissue is linked to its
+// original row, which keeps the rejection and moves to "reemis"; the resident's
+// RIB is corrected first when a new one is given.
+#[tauri::command]
+pub async fn generate_ov_reemission(
+    state: State<'_, AppState>,
+    origine: Origine,
+    reemissions: Vec<DemandeReemission>,
+    chemin: String,
+) -> Result<OrdreReemission, String> {
+    let session = session_courante(&state).await?;
+    peut_rapprocher(session.role).map_err(|e| e.to_string())?;
+
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    if reemissions.is_empty() {
+        return Err("Aucun virement à réémettre".to_string());
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    //checking the selection
+    let mut rejets = Vec::new();
+    for demande in &reemissions {
+        if reemissions.iter().filter(|autre| autre.id == demande.id).count() > 1 {
+            return Err(format!("Le virement {} est sélectionné plusieurs fois", demande.id));
+        }
+
+        // Locking the original row first makes a concurrent reissue of the same
+        // transfer wait for this one, then see its reissue when it reads the row
+        let verrou = match origine {
+            Origine::Rappel => sqlx::query!("SELECT id_rappel FROM rappels_annuels WHERE id_rappel = $1 FOR UPDATE", demande.id)
+                .fetch_optional(&mut tx)
+                .await
+                .map(|_| ()),
+            _ => sqlx::query!("SELECT id_paiement FROM paiement_mensuel WHERE id_paiement = $1 FOR UPDATE", demande.id)
+                .fetch_optional(&mut tx)
+                .await
+                .map(|_| ()),
+        };
+        verrou.map_err(|e| format!("Failed to lock transfer: {}", e))?;
+
+        let rejet = charger_rejet(&mut tx, origine, demande.id)
+            .await?
+            .ok_or_else(|| format!("Virement {} introuvable", demande.id))?;
+        if !peut_reemettre(&rejet.statut_virement, rejet.statut_reemission.as_deref()) {
+            return Err(format!("Le virement de {} ({}) n'a pas été rejeté", rejet.nom_prenom, rejet.libelle));
+        }
+
+        let rib = match &demande.rib {
+            Some(rib) => {
+                validate_rib(rib)?;
+                rib.split_whitespace().collect()
+            }
+            None => rejet.rib.clone(),
+        };
+        rejets.push((rejet, rib));
+    }
+
+    let aujourd_hui = Local::now().naive_local().date();
+    let periode = Periode::contenant(aujourd_hui);
+    let total = rejets.iter().fold(BigDecimal::zero(), |total, (rejet, _)| total + &rejet.montant);
+    let nombre_virements = rejets.len() as i32;
+
+    sqlx::query!("LOCK TABLE ordres_virement IN SHARE ROW EXCLUSIVE MODE")
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to number transfer orders: {}", e))?;
+
+    let ordre = sqlx::query!(
+        "INSERT INTO ordres_virement (exercice, numero, periode, id_banque, nombre_virements, total, created_by, reemission)
+         VALUES ($1, COALESCE((SELECT MAX(numero) FROM ordres_virement WHERE exercice = $1), 0) + 1, $2, NULL, $3, $4, $5, TRUE)
+         RETURNING id_ordre, numero",
+        periode.annee,
+        periode.debut(),
+        nombre_virements,
+        total,
+        session.username
+    )
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to number transfer orders: {}", e))?;
+
+    let mut periodes: Vec<(NaiveDate, String)> = rejets
+        .iter()
+        .map(|(rejet, _)| (rejet.date_reference, rejet.libelle.clone()))
+        .collect();
+    periodes.sort();
+    periodes.dedup();
+    let periodes: Vec<String> = periodes.into_iter().map(|(_, libelle)| libelle).collect();
+
+    //correcting RIBs and linking each reissue to its original transfer
+    let mut ribs_corriges = 0;
+    let mut lignes = Vec::new();
+    for (rejet, rib) in rejets {
+        let mut nom_banque = rejet.nom_banque.clone();
+        if rib != rejet.rib {
+            // The bank follows the RIB, whose first three digits are the bank code
+            let banque = sqlx::query!(
+                r#"
+                UPDATE residents
+                SET rib = $1, id_banque = COALESCE((SELECT id_banque FROM banque WHERE code_banque = LEFT($1, 3)), id_banque)
+                WHERE id_resident = $2
+                RETURNING (SELECT nom FROM banque WHERE banque.id_banque = residents.id_banque) as "nom_banque?"
+                "#,
+                rib,
+                rejet.id_resident
+            )
+            .fetch_one(&mut tx)
+            .await
+            .map_err(|e| format!("Failed to update the RIB of {}: {}", rejet.nom_prenom, e))?;
+            ribs_corriges += 1;
+            nom_banque = banque.nom_banque.unwrap_or_default();
+        }
+
+        let (id_paiement, id_rappel) = match origine {
+            Origine::Rappel => (None, Some(rejet.id)),
+            _ => (Some(rejet.id), None),
+        };
+        // The RIB last sent to the bank: the previous reissue's, else the one read
+        // from the return file, else the resident's current one
+        let rib_rejete = rejet
+            .rib_reemission
+            .clone()
+            .or_else(|| rejet.rib_rejete.clone())
+            .unwrap_or_else(|| rejet.rib.clone());
+        let motif_initial = rejet.motif_reemission.clone().or_else(|| rejet.motif_rejet.clone());
+
+        sqlx::query!(
+            "INSERT INTO reemissions (id_ordre, id_paiement, id_rappel, rib_rejete, rib, motif_rejet_initial, montant, created_by)
+             VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+            ordre.id_ordre,
+            id_paiement,
+            id_rappel,
+            rib_rejete,
+            rib,
+            motif_initial,
+            rejet.montant,
+            session.username
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to record reissue for {}: {}", rejet.nom_prenom, e))?;
+
+        let resultat = match origine {
+            Origine::Rappel => sqlx::query!(
+                "UPDATE rappels_annuels SET statut_virement = 'reemis'
+                 WHERE id_rappel = $1 AND statut_virement IN ('rejete', 'reemis')",
+                rejet.id
+            )
+            .execute(&mut tx)
+            .await,
+            _ => sqlx::query!(
+                "UPDATE paiement_mensuel SET statut_virement = 'reemis'
+                 WHERE id_paiement = $1 AND statut_virement IN ('rejete', 'reemis')",
+                rejet.id
+            )
+            .execute(&mut tx)
+            .await,
+        };
+        if resultat.map_err(|e| format!("Failed to update transfer status: {}", e))?.rows_affected() != 1 {
+            return Err(format!("Le virement de {} ({}) n'a pas été rejeté", rejet.nom_prenom, rejet.libelle));
+        }
+
+        lignes.push(LigneOv {
+            nom_prenom: rejet.nom_prenom,
+            rib,
+            nom_banque,
+            montant: rejet.montant,
+        });
+    }
+    lignes.sort_by(|a, b| a.nom_prenom.cmp(&b.nom_prenom));
+
+    let objet = match origine {
+        Origine::Rappel => format!(
+            "REEMISSION DU RAPPEL DE L'INDEMNITE DE FONCTION DES MEDECINS RESIDENTS AU TITRE DE L'EXERCICE {}",
+            periodes.join(", ")
+        ),
+        _ => format!(
+            "Le règlement, après rejet du premier virement, des indemnités de fonction et allocations familiales des résidents au titre de {}",
+            periodes.join(", ")
+        ),
+    };
+    let ov = OrdreVirement {
+        lignes,
+        objet,
+        numero: Some(NumeroOrdre { numero: ordre.numero, exercice: periode.annee }),
+    };
+    let fichier = ecrire_ov(&chemin, &ov, origine == Origine::Rappel)?;
+
+    // The order is only numbered once its file has been written
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit reissue: {}", e))?;
+
+    Ok(OrdreReemission {
+        numero: ordre.numero,
+        exercice: periode.annee,
+        fichier,
+        nombre_virements,
+        total,
+        ribs_corriges,
+    })
+}
@@ -0,0 +1,503 @@
+use crate::commands::auth::session_courante;
+use crate::commands::absences::fetch_absences;
+use crate::commands::ajustements::fetch_ajustements;
+use crate::commands::bareme::fetch_grille;
+use crate::commands::retenues::fetch_regime_retenues;
+use crate::models::{AppState, LignePaiement, PaiementDetail, PaiementMensuel, PayrollRun, PayrollRunAudit};
+use crate::payroll::{calculer_paie, construire_apercu, peut_preparer, ApercuPaie, PaiementPrecedent, Periode, ResidentPaie, ResultatPaie, StatutRun};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Local, NaiveDate};
+use tauri::State;
+
+
+//managing payroll runs
+
+async fn journaliser(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    run: &PayrollRun,
+    action: &str,
+    auteur: &str,
+    motif: Option<&str>,
+    details: Option<String>,
+) -> Result<(), String> {
+    sqlx::query!(
+        "INSERT INTO payroll_run_audit (id_run, annee, mois, action, auteur, motif, details) VALUES ($1, $2, $3, $4, $5, $6, $7)",
+        run.id_run,
+        run.annee,
+        run.mois,
+        action,
+        auteur,
+        motif,
+        details
+    )
+    .execute(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to record payroll run audit: {}", e))?;
+
+    Ok(())
+}
+
+pub(crate) async fn fetch_residents_paie(pool: &sqlx::PgPool) -> Result<Vec<ResidentPaie>, sqlx::Error> {
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            residents.id_resident,
+            residents.nom_prenom,
+            residents.date_debut,
+            residents.date_fin,
+            residents.id_specialite,
+            residents.nombre_enfants,
+            specialites.nombre_annees as "nombre_annees?",
+            specialites.nom as "nom_specialite?",
+            banque.nom as "nom_banque?"
+        FROM residents
+        LEFT JOIN specialites ON residents.id_specialite = specialites.id_specialite
+        LEFT JOIN banque ON residents.id_banque = banque.id_banque
+        "#
+    )
+    .fetch_all(pool)
+    .await?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| ResidentPaie {
+            id_resident: record.id_resident,
+            nom_prenom: record.nom_prenom,
+            date_debut: record.date_debut,
+            date_fin: record.date_fin,
+            id_specialite: record.id_specialite,
+            nombre_annees: record.nombre_annees.unwrap_or(1),
+            nombre_enfants: record.nombre_enfants.unwrap_or(0),
+            nom_specialite: record.nom_specialite,
+            nom_banque: record.nom_banque,
+        })
+        .collect())
+}
+
+async fn calculer_periode(
+    pool: &sqlx::PgPool,
+    periode: Periode,
+    today: NaiveDate,
+) -> Result<(Vec<ResidentPaie>, ResultatPaie), String> {
+    let residents = fetch_residents_paie(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch residents: {}", e))?;
+    let grille = fetch_grille(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch salary grid: {}", e))?;
+
+    let absences = fetch_absences(pool, periode.debut(), periode.fin())
+        .await
+        .map_err(|e| format!("Failed to fetch absences: {}", e))?;
+
+    let retenues = fetch_regime_retenues(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch deduction rates: {}", e))?;
+
+    let ajustements = fetch_ajustements(pool, Some(periode.debut()))
+        .await
+        .map_err(|e| format!("Failed to fetch adjustments: {}", e))?;
+
+    let resultat = calculer_paie(&residents, &grille, &retenues, &absences, &ajustements, periode.date_paiement(today));
+
+    Ok((residents, resultat))
+}
+
+#[tauri::command]
+pub async fn get_payroll_runs(state: State<'_, AppState>) -> Result<Vec<PayrollRun>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    sqlx::query_as::<_, PayrollRun>("SELECT * FROM payroll_run ORDER BY annee DESC, mois DESC")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch payroll runs: {}", e))
+}
+
+#[tauri::command]
+pub async fn get_payroll_run_audit(state: State<'_, AppState>, id_run: i32) -> Result<Vec<PayrollRunAudit>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    sqlx::query_as::<_, PayrollRunAudit>("SELECT * FROM payroll_run_audit WHERE id_run = $1 ORDER BY date_action")
+        .bind(id_run)
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch payroll run audit: {}", e))
+}
+
+// Generates the payroll run of a month. Calling it again for a month that already has a run
+// returns that run unchanged, unless `regenerer` is set: a draft run is then recomputed from
+// scratch and the regeneration is recorded in payroll_run_audit with its `motif`.
+#[tauri::command]
+pub async fn generate_payments(
+    state: State<'_, AppState>,
+    annee: i32,
+    mois: u32,
+    regenerer: Option<bool>,
+    motif: Option<String>,
+) -> Result<PayrollRun, String> {
+    let session = session_courante(&state).await?;
+    peut_preparer(session.role).map_err(|e| e.to_string())?;
+    let auteur = session.username;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let today: NaiveDate = Local::now().naive_local().date();
+    let periode = Periode::new(annee, mois).map_err(|e| e.to_string())?;
+    periode.valider(today).map_err(|e| e.to_string())?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    // The unique (annee, mois) constraint serializes concurrent runs for the same month
+    let created = sqlx::query!(
+        "INSERT INTO payroll_run (annee, mois, statut, created_by) VALUES ($1, $2, $3, $4)
+         ON CONFLICT (annee, mois) DO NOTHING",
+        periode.annee,
+        periode.mois as i32,
+        StatutRun::Brouillon.as_str(),
+        auteur
+    )
+    .execute(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to create payroll run: {}", e))?
+    .rows_affected()
+        == 1;
+
+    let run = sqlx::query_as::<_, PayrollRun>("SELECT * FROM payroll_run WHERE annee = $1 AND mois = $2 FOR UPDATE")
+        .bind(periode.annee)
+        .bind(periode.mois as i32)
+        .fetch_one(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to fetch payroll run: {}", e))?;
+
+    let action = if created {
+        let legacy = sqlx::query!(
+            "SELECT COUNT(*) FROM paiement_mensuel WHERE periode = $1 AND id_run IS NULL",
+            periode.debut()
+        )
+        .fetch_one(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to query database: {}", e))?;
+
+        if legacy.count.unwrap_or(0) > 0 {
+            return Err(format!(
+                "Des paiements existent déjà pour {} en dehors de tout run de paie",
+                periode
+            ));
+        }
+
+        "creation"
+    } else {
+        if !regenerer.unwrap_or(false) {
+            return Ok(run);
+        }
+
+        if StatutRun::parse(&run.statut).map_err(|e| e.to_string())?.est_verrouille() {
+            return Err(format!("Le run de paie {} n'est plus un brouillon et ne peut pas être régénéré", periode));
+        }
+
+        if motif.as_deref().is_none_or(|m| m.trim().is_empty()) {
+            return Err("Un motif est obligatoire pour régénérer un run de paie".to_string());
+        }
+
+        sqlx::query!("DELETE FROM paiement_mensuel WHERE id_run = $1", run.id_run)
+            .execute(&mut tx)
+            .await
+            .map_err(|e| format!("Failed to delete draft payments: {}", e))?;
+
+        "regeneration"
+    };
+
+    let (_, resultat) = calculer_periode(pool, periode, today).await?;
+
+    // A resident must never be silently left out of a payroll run
+    if !resultat.anomalies.is_empty() {
+        let details: Vec<String> = resultat
+            .anomalies
+            .iter()
+            .map(|anomalie| format!("{} : {}", anomalie.nom_prenom, anomalie.message))
+            .collect();
+        return Err(format!("Failed to generate payments: {}", details.join("; ")));
+    }
+
+    for ligne in &resultat.lignes {
+        let record = sqlx::query!(
+            "INSERT INTO paiement_mensuel (
+                id_run, periode, id_resident, id_bareme, jours_travail, salaire_base, indemnite_fonction,
+                indemnite_logement, indemnite_transport, allocations_familiales, salaire_brut, total_retenues,
+                montant, date_paiement
+            ) VALUES ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14)
+            RETURNING id_paiement",
+            run.id_run,
+            periode.debut(),
+            ligne.id_resident,
+            ligne.id_bareme,
+            ligne.jours_travail,
+            ligne.salaire_base,
+            ligne.indemnite_fonction,
+            ligne.indemnite_logement,
+            ligne.indemnite_transport,
+            ligne.allocations_familiales,
+            ligne.salaire_brut,
+            ligne.total_retenues,
+            ligne.montant,
+            ligne.date_paiement
+        )
+        .fetch_one(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to generate payments: {}", e))?;
+
+        for (ordre, detail) in ligne.lignes.iter().enumerate() {
+            sqlx::query!(
+                "INSERT INTO lignes_paiement (id_paiement, ordre, code, libelle, categorie, base, taux, montant)
+                 VALUES ($1, $2, $3, $4, $5, $6, $7, $8)",
+                record.id_paiement,
+                ordre as i32,
+                detail.code,
+                detail.libelle,
+                detail.categorie,
+                detail.base,
+                detail.taux,
+                detail.montant
+            )
+            .execute(&mut tx)
+            .await
+            .map_err(|e| format!("Failed to generate payment lines: {}", e))?;
+        }
+
+        sqlx::query!(
+            "UPDATE ajustements SET id_paiement = $1 WHERE id_ajustement = ANY($2)",
+            record.id_paiement,
+            &ligne.id_ajustements
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to apply adjustments: {}", e))?;
+    }
+
+    let total_montant = resultat
+        .lignes
+        .iter()
+        .fold(BigDecimal::zero(), |total, ligne| total + &ligne.montant);
+    let total_allocations = resultat
+        .lignes
+        .iter()
+        .fold(BigDecimal::zero(), |total, ligne| total + &ligne.allocations_familiales);
+
+    let updated = sqlx::query_as::<_, PayrollRun>(
+        "UPDATE payroll_run SET nombre_paiements = $1, total_montant = $2, total_allocations = $3
+         WHERE id_run = $4 RETURNING *",
+    )
+    .bind(resultat.lignes.len() as i32)
+    .bind(&total_montant)
+    .bind(&total_allocations)
+    .bind(run.id_run)
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to update payroll run totals: {}", e))?;
+
+    let details = format!(
+        "{} paiement(s), total {} (précédent : {} paiement(s), total {})",
+        updated.nombre_paiements, updated.total_montant, run.nombre_paiements, run.total_montant
+    );
+    journaliser(&mut tx, &run, action, &auteur, motif.as_deref(), Some(details)).await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to generate payments: {}", e))?;
+
+    Ok(updated)
+}
+
+// Everything generate_payments would create for a month, without writing anything
+#[tauri::command]
+pub async fn preview_payments(state: State<'_, AppState>, annee: i32, mois: u32) -> Result<ApercuPaie, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let today: NaiveDate = Local::now().naive_local().date();
+    let periode = Periode::new(annee, mois).map_err(|e| e.to_string())?;
+    periode.valider(today).map_err(|e| e.to_string())?;
+
+    let (residents, resultat) = calculer_periode(pool, periode, today).await?;
+
+    let precedents: Vec<PaiementPrecedent> = sqlx::query!(
+        r#"
+        SELECT
+            paiement_mensuel.id_resident as "id_resident!",
+            residents.nom_prenom,
+            paiement_mensuel.montant
+        FROM paiement_mensuel
+        JOIN residents ON paiement_mensuel.id_resident = residents.id_resident
+        WHERE paiement_mensuel.periode = $1
+        "#,
+        periode.precedente().debut()
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch previous payments: {}", e))?
+    .into_iter()
+    .map(|record| PaiementPrecedent {
+        id_resident: record.id_resident,
+        nom_prenom: record.nom_prenom,
+        montant: record.montant,
+    })
+    .collect();
+
+    Ok(construire_apercu(periode, resultat, &residents, &precedents))
+}
+
+// Moves a run to the next step of its lifecycle (valide, envoye, paye)
+#[tauri::command]
+pub async fn change_payroll_run_status(
+    state: State<'_, AppState>,
+    id_run: i32,
+    statut: StatutRun,
+    motif: Option<String>,
+) -> Result<PayrollRun, String> {
+    let session = session_courante(&state).await?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let run = sqlx::query_as::<_, PayrollRun>("SELECT * FROM payroll_run WHERE id_run = $1 FOR UPDATE")
+        .bind(id_run)
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to fetch payroll run: {}", e))?
+        .ok_or("Run de paie introuvable")?;
+
+    let actuel = StatutRun::parse(&run.statut).map_err(|e| e.to_string())?;
+    let cible = actuel.transition(statut, session.role).map_err(|e| e.to_string())?;
+
+    let updated = sqlx::query_as::<_, PayrollRun>("UPDATE payroll_run SET statut = $1 WHERE id_run = $2 RETURNING *")
+        .bind(cible.as_str())
+        .bind(id_run)
+        .fetch_one(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to update payroll run: {}", e))?;
+
+    journaliser(
+        &mut tx,
+        &run,
+        cible.as_str(),
+        &session.username,
+        motif.as_deref(),
+        Some(format!("{} -> {}", actuel.as_str(), cible.as_str())),
+    )
+    .await?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to update payroll run: {}", e))?;
+
+    Ok(updated)
+}
+
+// Deletes a draft run and its payments; the audit trail keeps the period and the reason
+#[tauri::command]
+pub async fn cancel_payroll_run(state: State<'_, AppState>, id_run: i32, motif: String) -> Result<(), String> {
+    let session = session_courante(&state).await?;
+    peut_preparer(session.role).map_err(|e| e.to_string())?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    if motif.trim().is_empty() {
+        return Err("Un motif est obligatoire pour annuler un run de paie".to_string());
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let run = sqlx::query_as::<_, PayrollRun>("SELECT * FROM payroll_run WHERE id_run = $1 FOR UPDATE")
+        .bind(id_run)
+        .fetch_optional(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to fetch payroll run: {}", e))?
+        .ok_or("Run de paie introuvable")?;
+
+    if StatutRun::parse(&run.statut).map_err(|e| e.to_string())?.est_verrouille() {
+        return Err(format!("Seul un brouillon peut être annulé (statut actuel : {})", run.statut));
+    }
+
+    journaliser(
+        &mut tx,
+        &run,
+        "annulation",
+        &session.username,
+        Some(&motif),
+        Some(format!("{} paiement(s), total {}", run.nombre_paiements, run.total_montant)),
+    )
+    .await?;
+
+    sqlx::query!("DELETE FROM payroll_run WHERE id_run = $1", id_run)
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to cancel payroll run: {}", e))?;
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to cancel payroll run: {}", e))?;
+
+    Ok(())
+}
+
+// Full gross-to-net breakdown of a payment, as shown on the payslip
+#[tauri::command]
+pub async fn get_payment_detail(state: State<'_, AppState>, id_paiement: i32) -> Result<PaiementDetail, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let paiement = sqlx::query_as::<_, PaiementMensuel>(
+        r#"
+        SELECT
+            paiement_mensuel.id_paiement,
+            paiement_mensuel.id_resident,
+            paiement_mensuel.jours_travail,
+            paiement_mensuel.allocations_familiales,
+            paiement_mensuel.montant,
+            paiement_mensuel.date_paiement,
+            paiement_mensuel.id_bareme,
+            paiement_mensuel.salaire_base,
+            paiement_mensuel.indemnite_fonction,
+            paiement_mensuel.indemnite_logement,
+            paiement_mensuel.indemnite_transport,
+            paiement_mensuel.salaire_brut,
+            paiement_mensuel.total_retenues,
+            paiement_mensuel.statut_virement,
+            paiement_mensuel.motif_rejet,
+            residents.nom_prenom as nom_resident,
+            residents.rib,
+            banque.nom as nom_banque
+        FROM paiement_mensuel
+        LEFT JOIN residents ON paiement_mensuel.id_resident = residents.id_resident
+        LEFT JOIN banque ON residents.id_banque = banque.id_banque
+        WHERE paiement_mensuel.id_paiement = $1
+        "#,
+    )
+    .bind(id_paiement)
+    .fetch_optional(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch payment: {}", e))?
+    .ok_or_else(|| format!("Paiement {} introuvable", id_paiement))?;
+
+    let lignes = sqlx::query_as::<_, LignePaiement>(
+        "SELECT code, libelle, categorie, base, taux, montant FROM lignes_paiement WHERE id_paiement = $1 ORDER BY ordre",
+    )
+    .bind(id_paiement)
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch payment lines: {}", e))?;
+
+    Ok(PaiementDetail { paiement, lignes })
+}
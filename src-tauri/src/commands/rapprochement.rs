@@ -0,0 +1,308 @@
+use crate::commands::auth::session_courante;
+use crate::commands::import::{cell, normalize, read_rows};
+use crate::documents::virements::{code_execute, lire_montant, lire_retour_fixe, motif_rejet, LigneRetour};
+use crate::models::{AppState, ImportRowError, LigneNonRapprochee, RapportRetour};
+use crate::payroll::{peut_rapprocher, rapprocher, Origine, VirementEmis};
+use chrono::{Local, NaiveDate};
+use std::path::Path;
+use tauri::State;
+
+
+//reconciling bank return files
+
+#[derive(Default)]
+struct ColonnesRetour {
+    rib: Option<usize>,
+    montant: Option<usize>,
+    statut: Option<usize>,
+    code: Option<usize>,
+    motif: Option<usize>,
+    nom: Option<usize>,
+}
+
+impl ColonnesRetour {
+    fn from_header(header: &[String]) -> Result<Self, String> {
+        let mut colonnes = ColonnesRetour::default();
+
+        for (index, name) in header.iter().enumerate() {
+            match normalize(name).as_str() {
+                "rib" | "compte" | "numerodecompte" => colonnes.rib = Some(index),
+                "montant" | "montantvire" => colonnes.montant = Some(index),
+                "statut" | "etat" | "resultat" => colonnes.statut = Some(index),
+                "code" | "coderejet" | "codemotif" => colonnes.code = Some(index),
+                "motif" | "motifrejet" | "libellerejet" => colonnes.motif = Some(index),
+                "nom" | "nomprenom" | "beneficiaire" => colonnes.nom = Some(index),
+                _ => {}
+            }
+        }
+
+        match (colonnes.rib, colonnes.montant, colonnes.statut.or(colonnes.code)) {
+            (Some(_), Some(_), Some(_)) => Ok(colonnes),
+            _ => Err("Colonnes manquantes : rib, montant et statut (ou code de rejet) sont obligatoires".to_string()),
+        }
+    }
+}
+
+// Banks write the outcome in words ("Exécuté", "Rejeté", "KO") or as a reject code
+fn est_rejete(statut: &str, code: &str) -> bool {
+    let statut = normalize(statut);
+    statut.starts_with("rej") || statut.starts_with("refus") || statut.starts_with("echec") || statut == "ko" || !code_execute(code)
+}
+
+fn lire_retour_tableur(path: &Path) -> Result<(Vec<LigneRetour>, Vec<ImportRowError>), String> {
+    let rows = read_rows(path)?;
+    let (header, rows) = rows.split_first().ok_or("The file is empty")?;
+    let colonnes = ColonnesRetour::from_header(header)?;
+
+    let mut lignes = Vec::new();
+    let mut erreurs = Vec::new();
+
+    for (index, row) in rows.iter().enumerate() {
+        if row.iter().all(|value| value.trim().is_empty()) {
+            continue;
+        }
+
+        let ligne = index + 2;
+        let beneficiaire = Some(cell(row, colonnes.nom).to_string()).filter(|nom| !nom.is_empty());
+
+        let rib: String = cell(row, colonnes.rib).chars().filter(|c| !c.is_whitespace() && *c != '-').collect();
+        if rib.len() != 24 || !rib.chars().all(|c| c.is_ascii_digit()) {
+            erreurs.push(ImportRowError {
+                ligne,
+                nom_prenom: beneficiaire,
+                message: format!("RIB invalide : {}", cell(row, colonnes.rib)),
+            });
+            continue;
+        }
+
+        let montant = match lire_montant(cell(row, colonnes.montant)) {
+            Some(montant) => montant,
+            None => {
+                erreurs.push(ImportRowError {
+                    ligne,
+                    nom_prenom: beneficiaire,
+                    message: format!("Montant invalide : {}", cell(row, colonnes.montant)),
+                });
+                continue;
+            }
+        };
+
+        let code = cell(row, colonnes.code);
+        let rejet = est_rejete(cell(row, colonnes.statut), code).then(|| motif_rejet(code, cell(row, colonnes.motif)));
+
+        lignes.push(LigneRetour { ligne, rib, montant, beneficiaire, rejet });
+    }
+
+    Ok((lignes, erreurs))
+}
+
+// Spreadsheets and CSV exports are read by column name, anything else as a
+// fixed-width return in the layout of the transfer files we send
+fn lire_retour(path: &Path) -> Result<(Vec<LigneRetour>, Vec<ImportRowError>), String> {
+    let extension = path
+        .extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.to_lowercase())
+        .unwrap_or_default();
+
+    match extension.as_str() {
+        "csv" | "xlsx" | "xls" | "ods" => lire_retour_tableur(path),
+        _ => {
+            let contenu = std::fs::read_to_string(path).map_err(|e| format!("Failed to read file: {}", e))?;
+            lire_retour_fixe(contenu.trim_start_matches('\u{feff}')).map_err(|e| e.to_string())
+        }
+    }
+}
+
+// Transfers sent to the bank whose outcome is not known yet, with the RIB
+// recorded when they were exported
+async fn virements_en_attente(pool: &sqlx::PgPool) -> Result<Vec<VirementEmis>, String> {
+    let paiements = sqlx::query!(
+        r#"
+        SELECT paiement_mensuel.id_paiement, paiement_mensuel.montant, paiement_mensuel.date_paiement,
+               paiement_mensuel.rib_envoye as "rib!"
+        FROM paiement_mensuel
+        JOIN payroll_run ON paiement_mensuel.id_run = payroll_run.id_run
+        WHERE payroll_run.statut = 'envoye'
+          AND paiement_mensuel.statut_virement = 'en_attente' AND paiement_mensuel.montant > 0
+          AND paiement_mensuel.rib_envoye IS NOT NULL
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch payments: {}", e))?;
+
+    let rappels = sqlx::query!(
+        r#"
+        SELECT rappels_annuels.id_rappel, rappels_annuels.montant, rappels_annuels.date_generation,
+               rappels_annuels.rib_envoye as "rib!"
+        FROM rappels_annuels
+        WHERE rappels_annuels.statut_virement = 'en_attente' AND rappels_annuels.montant > 0
+          AND rappels_annuels.rib_envoye IS NOT NULL
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch rappels: {}", e))?;
+
+    let reemissions = sqlx::query!(
+        r#"
+        SELECT reemissions.id_reemission, reemissions.montant, reemissions.rib, reemissions.created_at
+        FROM reemissions
+        WHERE reemissions.statut_virement = 'en_attente'
+        "#
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch reissued transfers: {}", e))?;
+
+    let rib = |rib: &str| -> String { rib.chars().filter(|c| c.is_ascii_digit()).collect() };
+
+    let mut virements: Vec<VirementEmis> = paiements
+        .into_iter()
+        .map(|record| VirementEmis {
+            origine: Origine::Paiement,
+            id: record.id_paiement,
+            rib: rib(&record.rib),
+            montant: record.montant,
+            date: record.date_paiement,
+        })
+        .collect();
+
+    virements.extend(rappels.into_iter().map(|record| VirementEmis {
+        origine: Origine::Rappel,
+        id: record.id_rappel,
+        rib: rib(&record.rib),
+        montant: record.montant,
+        date: record.date_generation.unwrap_or(NaiveDate::MIN),
+    }));
+
+    virements.extend(reemissions.into_iter().map(|record| VirementEmis {
+        origine: Origine::Reemission,
+        id: record.id_reemission,
+        rib: rib(&record.rib),
+        montant: record.montant,
+        date: record.created_at.date(),
+    }));
+
+    Ok(virements)
+}
+
+// Reads a bank return file, matches each line to a pending payment, rappel or
+// reissue by RIB and amount, and marks the transfers paid or rejected with the bank's reason
+#[tauri::command]
+pub async fn import_bank_return(state: State<'_, AppState>, path: String, dry_run: bool) -> Result<RapportRetour, String> {
+    let session = session_courante(&state).await?;
+    peut_rapprocher(session.role).map_err(|e| e.to_string())?;
+
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let path = Path::new(&path);
+    let (lignes, erreurs) = lire_retour(path)?;
+    if lignes.is_empty() && erreurs.is_empty() {
+        return Err("Le fichier ne contient aucune ligne de retour".to_string());
+    }
+
+    let virements = virements_en_attente(pool).await?;
+    let retours: Vec<(&str, _)> = lignes.iter().map(|ligne| (ligne.rib.as_str(), &ligne.montant)).collect();
+    let correspondances = rapprocher(&retours, &virements);
+
+    let mut rapproches = Vec::new();
+    let mut non_rapprochees = Vec::new();
+    for (ligne, correspondance) in lignes.into_iter().zip(correspondances) {
+        match correspondance {
+            Some(index) => rapproches.push((&virements[index], ligne.rejet)),
+            None => non_rapprochees.push(LigneNonRapprochee {
+                ligne: ligne.ligne,
+                rib: ligne.rib,
+                montant: ligne.montant,
+                beneficiaire: ligne.beneficiaire,
+                motif_rejet: ligne.rejet,
+            }),
+        }
+    }
+
+    let rejetes = rapproches.iter().filter(|(_, rejet)| rejet.is_some()).count();
+    let payes = rapproches.len() - rejetes;
+
+    if !dry_run {
+        let aujourd_hui = Local::now().naive_local().date();
+        let mut tx = pool
+            .begin()
+            .await
+            .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+        for (virement, rejet) in &rapproches {
+            let statut = if rejet.is_some() { "rejete" } else { "paye" };
+            let resultat = match virement.origine {
+                Origine::Paiement => sqlx::query!(
+                    "UPDATE paiement_mensuel SET statut_virement = $1, motif_rejet = $2, date_retour = $3, rib_rejete = $4
+                     WHERE id_paiement = $5 AND statut_virement = 'en_attente'",
+                    statut,
+                    rejet.as_deref(),
+                    aujourd_hui,
+                    rejet.as_ref().map(|_| virement.rib.as_str()),
+                    virement.id
+                )
+                .execute(&mut tx)
+                .await,
+                Origine::Rappel => sqlx::query!(
+                    "UPDATE rappels_annuels SET statut_virement = $1, motif_rejet = $2, date_retour = $3, rib_rejete = $4
+                     WHERE id_rappel = $5 AND statut_virement = 'en_attente'",
+                    statut,
+                    rejet.as_deref(),
+                    aujourd_hui,
+                    rejet.as_ref().map(|_| virement.rib.as_str()),
+                    virement.id
+                )
+                .execute(&mut tx)
+                .await,
+                Origine::Reemission => sqlx::query!(
+                    "UPDATE reemissions SET statut_virement = $1, motif_rejet = $2, date_retour = $3
+                     WHERE id_reemission = $4 AND statut_virement = 'en_attente'",
+                    statut,
+                    rejet.as_deref(),
+                    aujourd_hui,
+                    virement.id
+                )
+                .execute(&mut tx)
+                .await,
+            };
+            // Another import or a reissue settled the transfer since it was read
+            if resultat.map_err(|e| format!("Failed to update transfer status: {}", e))?.rows_affected() != 1 {
+                return Err(format!(
+                    "Le virement de {} vers le RIB {} a déjà été traité entre-temps, relancez l'import",
+                    virement.montant, virement.rib
+                ));
+            }
+        }
+
+        let fichier = path.file_name().map(|nom| nom.to_string_lossy().to_string()).unwrap_or_default();
+        sqlx::query!(
+            "INSERT INTO retours_banque (fichier, nombre_lignes, nombre_payes, nombre_rejetes, nombre_non_rapproches, importe_par) VALUES ($1, $2, $3, $4, $5, $6)",
+            fichier,
+            (rapproches.len() + non_rapprochees.len()) as i32,
+            payes as i32,
+            rejetes as i32,
+            non_rapprochees.len() as i32,
+            session.username
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to record bank return: {}", e))?;
+
+        tx.commit()
+            .await
+            .map_err(|e| format!("Failed to commit bank return: {}", e))?;
+    }
+
+    Ok(RapportRetour {
+        total_lignes: rapproches.len() + non_rapprochees.len() + erreurs.len(),
+        payes,
+        rejetes,
+        dry_run,
+        non_rapprochees,
+        erreurs,
+    })
+}
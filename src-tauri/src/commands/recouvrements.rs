@@ -0,0 +1,439 @@
+use crate::commands::absences::{fetch_absences, periode_verrouillee};
+use crate::commands::ajustements::fetch_ajustements;
+use crate::commands::auth::session_courante;
+use crate::commands::bareme::fetch_grille;
+use crate::commands::payroll::fetch_residents_paie;
+use crate::commands::retenues::fetch_regime_retenues;
+use crate::models::{AppState, EcheanceRecouvrement, NewRecouvrement, Recouvrement};
+use crate::payroll::{
+    detecter_trop_percus, echeancier, imputer_sur_rappel, peut_preparer, valider_exercice, PaiementVerse, Periode, PeriodeError,
+    TropPercu, IMPUTATIONS,
+};
+use bigdecimal::{BigDecimal, ToPrimitive, Zero};
+use chrono::{Datelike, Local};
+use tauri::State;
+use thiserror::Error;
+
+
+//managing overpayments and their recovery
+
+#[derive(Debug, Error)]
+pub enum RecouvrementError {
+    #[error("Mode d'imputation inconnu : {0}")]
+    UnknownImputation(String),
+    #[error("Le nombre d'échéances doit être compris entre 1 et {0}")]
+    InvalidInstallments(i64),
+    #[error("Le motif du recouvrement est obligatoire")]
+    EmptyReason,
+    #[error("Aucun paiement à recouvrer n'a été sélectionné")]
+    NoPayment,
+    #[error("Le paiement {0} ne présente pas de trop-perçu ou est déjà en recouvrement")]
+    NotOverpaid(i32),
+    #[error("La première échéance doit tomber le premier jour d'un mois")]
+    InvalidFirstPeriod,
+    #[error("La paie de {0} est verrouillée, choisissez une première échéance plus tardive")]
+    LockedPeriod(String),
+    #[error("L'exercice {0} est clôturé, aucun rappel ne peut plus y être imputé")]
+    ClosedExercice(i32),
+}
+
+// Recomputes the entitlement of every payment matching the filters that the resident
+// actually received and that is not already covered by a recovery. A payment counts as
+// received once its run has been sent and the bank has confirmed the transfer, or the
+// reissue of a rejected one. Payments made before payroll runs were sent outside the
+// application and count unless the bank rejected them.
+async fn detecter(pool: &sqlx::PgPool, exercice: Option<i32>, id_paiements: Option<&[i32]>) -> Result<Vec<TropPercu>, String> {
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            paiement_mensuel.id_paiement,
+            paiement_mensuel.id_resident as "id_resident!",
+            paiement_mensuel.periode,
+            paiement_mensuel.date_paiement,
+            paiement_mensuel.jours_travail,
+            paiement_mensuel.montant
+        FROM paiement_mensuel
+        LEFT JOIN payroll_run ON paiement_mensuel.id_run = payroll_run.id_run
+        WHERE ((paiement_mensuel.id_run IS NULL AND paiement_mensuel.statut_virement <> 'rejete')
+               OR (payroll_run.statut IN ('envoye', 'paye')
+                   AND (paiement_mensuel.statut_virement = 'paye'
+                        OR (paiement_mensuel.statut_virement = 'reemis' AND EXISTS (
+                            SELECT 1 FROM reemissions
+                            WHERE reemissions.id_paiement = paiement_mensuel.id_paiement
+                              AND reemissions.statut_virement = 'paye'
+                        )))))
+          AND paiement_mensuel.id_resident IS NOT NULL
+          AND ($1::int IS NULL OR EXTRACT(YEAR FROM paiement_mensuel.periode) = $1)
+          AND ($2::int[] IS NULL OR paiement_mensuel.id_paiement = ANY($2))
+          AND NOT EXISTS (
+              SELECT 1 FROM recouvrement_paiements
+              WHERE recouvrement_paiements.id_paiement = paiement_mensuel.id_paiement
+          )
+        ORDER BY paiement_mensuel.periode
+        "#,
+        exercice,
+        id_paiements
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch payments: {}", e))?;
+
+    let paiements: Vec<PaiementVerse> = records
+        .into_iter()
+        .map(|record| PaiementVerse {
+            id_paiement: record.id_paiement,
+            id_resident: record.id_resident,
+            periode: Periode::contenant(record.periode),
+            date_paiement: record.date_paiement,
+            jours_travail: record.jours_travail,
+            montant: record.montant,
+        })
+        .collect();
+
+    let (Some(premier), Some(dernier)) = (paiements.first(), paiements.last()) else {
+        return Ok(Vec::new());
+    };
+
+    let residents = fetch_residents_paie(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch residents: {}", e))?;
+    let grille = fetch_grille(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch salary grid: {}", e))?;
+    let retenues = fetch_regime_retenues(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch deduction rates: {}", e))?;
+    let absences = fetch_absences(pool, premier.periode.debut(), dernier.periode.fin())
+        .await
+        .map_err(|e| format!("Failed to fetch absences: {}", e))?;
+    let ajustements = fetch_ajustements(pool, None)
+        .await
+        .map_err(|e| format!("Failed to fetch adjustments: {}", e))?;
+
+    Ok(detecter_trop_percus(&paiements, &residents, &grille, &retenues, &absences, &ajustements))
+}
+
+#[tauri::command]
+pub async fn detect_overpayments(state: State<'_, AppState>, exercice: i32) -> Result<Vec<TropPercu>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    detecter(pool, Some(exercice), None).await
+}
+
+#[tauri::command]
+pub async fn get_recovery_schedules(state: State<'_, AppState>, id_resident: Option<i32>) -> Result<Vec<Recouvrement>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            recouvrements.id_recouvrement,
+            recouvrements.id_resident,
+            residents.nom_prenom as "nom_resident?",
+            recouvrements.montant_total,
+            recouvrements.imputation,
+            recouvrements.motif,
+            recouvrements.created_by,
+            recouvrements.created_at
+        FROM recouvrements
+        LEFT JOIN residents ON recouvrements.id_resident = residents.id_resident
+        WHERE $1::int IS NULL OR recouvrements.id_resident = $1
+        ORDER BY recouvrements.created_at DESC
+        "#,
+        id_resident
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch recovery schedules: {}", e))?;
+
+    let echeances = sqlx::query_as::<_, EcheanceRecouvrement>(
+        r#"
+        SELECT
+            echeances_recouvrement.id_echeance,
+            echeances_recouvrement.id_recouvrement,
+            echeances_recouvrement.rang,
+            echeances_recouvrement.montant,
+            echeances_recouvrement.periode,
+            echeances_recouvrement.exercice,
+            echeances_recouvrement.id_ajustement,
+            echeances_recouvrement.id_rappel,
+            (echeances_recouvrement.id_rappel IS NOT NULL
+                OR COALESCE(payroll_run.statut IN ('envoye', 'paye')
+                    AND (paiement_mensuel.statut_virement = 'paye'
+                         OR (paiement_mensuel.statut_virement = 'reemis' AND EXISTS (
+                             SELECT 1 FROM reemissions
+                             WHERE reemissions.id_paiement = paiement_mensuel.id_paiement
+                               AND reemissions.statut_virement = 'paye'
+                         ))), FALSE)) as recouvre
+        FROM echeances_recouvrement
+        LEFT JOIN ajustements ON echeances_recouvrement.id_ajustement = ajustements.id_ajustement
+        LEFT JOIN paiement_mensuel ON ajustements.id_paiement = paiement_mensuel.id_paiement
+        LEFT JOIN payroll_run ON paiement_mensuel.id_run = payroll_run.id_run
+        ORDER BY echeances_recouvrement.id_recouvrement, echeances_recouvrement.rang
+        "#,
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch recovery installments: {}", e))?;
+
+    Ok(records
+        .into_iter()
+        .map(|record| Recouvrement {
+            id_recouvrement: record.id_recouvrement,
+            id_resident: record.id_resident,
+            nom_resident: record.nom_resident,
+            montant_total: record.montant_total,
+            imputation: record.imputation,
+            motif: record.motif,
+            created_by: record.created_by,
+            created_at: record.created_at,
+            echeances: echeances
+                .iter()
+                .filter(|echeance| echeance.id_recouvrement == record.id_recouvrement)
+                .cloned()
+                .collect(),
+        })
+        .collect())
+}
+
+// Creates a recovery for the selected overpaid payments. The amount is recomputed here,
+// never taken from the client. Payroll installments become withholding adjustments of
+// their month; rappel installments are deducted when the rappel of their exercice is generated.
+#[tauri::command]
+pub async fn create_recovery_schedule(state: State<'_, AppState>, recouvrement: NewRecouvrement) -> Result<i32, String> {
+    let session = session_courante(&state).await?;
+    peut_preparer(session.role).map_err(|e| e.to_string())?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    if !IMPUTATIONS.contains(&recouvrement.imputation.as_str()) {
+        return Err(RecouvrementError::UnknownImputation(recouvrement.imputation).to_string());
+    }
+    if recouvrement.motif.trim().is_empty() {
+        return Err(RecouvrementError::EmptyReason.to_string());
+    }
+    if recouvrement.id_paiements.is_empty() {
+        return Err(RecouvrementError::NoPayment.to_string());
+    }
+
+    let trop_percus = detecter(pool, None, Some(&recouvrement.id_paiements)).await?;
+    for id_paiement in &recouvrement.id_paiements {
+        if !trop_percus
+            .iter()
+            .any(|t| t.id_paiement == *id_paiement && t.id_resident == recouvrement.id_resident)
+        {
+            return Err(RecouvrementError::NotOverpaid(*id_paiement).to_string());
+        }
+    }
+
+    let total = trop_percus
+        .iter()
+        .fold(BigDecimal::zero(), |total, t| total + &t.trop_percu);
+
+    // Every installment must be at least one cent
+    let max_echeances = (&total * BigDecimal::from(100)).to_i64().unwrap_or(1);
+    if recouvrement.nombre_echeances < 1 || i64::from(recouvrement.nombre_echeances) > max_echeances {
+        return Err(RecouvrementError::InvalidInstallments(max_echeances).to_string());
+    }
+
+    let premiere = Periode::contenant(recouvrement.premiere_periode);
+    if recouvrement.imputation == "paie" {
+        if recouvrement.premiere_periode.day() != 1 {
+            return Err(RecouvrementError::InvalidFirstPeriod.to_string());
+        }
+        if periode_verrouillee(pool, recouvrement.id_resident, premiere.debut(), premiere.fin()).await? {
+            return Err(RecouvrementError::LockedPeriod(premiere.to_string()).to_string());
+        }
+    } else if let Err(PeriodeError::Closed(_)) = valider_exercice(premiere.annee, Local::now().naive_local().date()) {
+        return Err(RecouvrementError::ClosedExercice(premiere.annee).to_string());
+    }
+
+    let mut tx = pool
+        .begin()
+        .await
+        .map_err(|e| format!("Failed to start transaction: {}", e))?;
+
+    let record = sqlx::query!(
+        "INSERT INTO recouvrements (id_resident, montant_total, imputation, motif, created_by)
+         VALUES ($1, $2, $3, $4, $5) RETURNING id_recouvrement",
+        recouvrement.id_resident,
+        total,
+        recouvrement.imputation,
+        recouvrement.motif.trim(),
+        session.username
+    )
+    .fetch_one(&mut tx)
+    .await
+    .map_err(|e| format!("Failed to create recovery schedule: {}", e))?;
+    let id_recouvrement = record.id_recouvrement;
+
+    for trop_percu in &trop_percus {
+        sqlx::query!(
+            "INSERT INTO recouvrement_paiements (id_recouvrement, id_paiement, trop_percu) VALUES ($1, $2, $3)",
+            id_recouvrement,
+            trop_percu.id_paiement,
+            trop_percu.trop_percu
+        )
+        .execute(&mut tx)
+        .await
+        .map_err(|e| format!("Failed to create recovery schedule: {}", e))?;
+    }
+
+    let echeances = echeancier(&total, recouvrement.nombre_echeances);
+    let mut periode = premiere;
+
+    for (rang, montant) in echeances.iter().enumerate() {
+        let rang = rang as i32 + 1;
+
+        if recouvrement.imputation == "paie" {
+            let motif = format!("Recouvrement trop-perçu n°{} ({}/{})", id_recouvrement, rang, echeances.len());
+            let ajustement = sqlx::query!(
+                "INSERT INTO ajustements (id_resident, periode, type_ajustement, montant, motif, created_by)
+                 VALUES ($1, $2, 'retenue', $3, $4, $5) RETURNING id_ajustement",
+                recouvrement.id_resident,
+                periode.debut(),
+                montant,
+                motif,
+                session.username
+            )
+            .fetch_one(&mut tx)
+            .await
+            .map_err(|e| format!("Failed to create recovery installment: {}", e))?;
+
+            sqlx::query!(
+                "INSERT INTO echeances_recouvrement (id_recouvrement, rang, montant, periode, id_ajustement) VALUES ($1, $2, $3, $4, $5)",
+                id_recouvrement,
+                rang,
+                montant,
+                periode.debut(),
+                ajustement.id_ajustement
+            )
+            .execute(&mut tx)
+            .await
+            .map_err(|e| format!("Failed to create recovery installment: {}", e))?;
+
+            periode = periode.suivante();
+        } else {
+            sqlx::query!(
+                "INSERT INTO echeances_recouvrement (id_recouvrement, rang, montant, exercice) VALUES ($1, $2, $3, $4)",
+                id_recouvrement,
+                rang,
+                montant,
+                premiere.annee + rang - 1
+            )
+            .execute(&mut tx)
+            .await
+            .map_err(|e| format!("Failed to create recovery installment: {}", e))?;
+        }
+    }
+
+    tx.commit()
+        .await
+        .map_err(|e| format!("Failed to commit transaction: {}", e))?;
+
+    Ok(id_recouvrement)
+}
+
+// Deducts the pending installments of `exercice` from the rappel just generated.
+// What the rappel does not cover is carried over to the next exercice, as a new
+// installment when the rappel covers part of one. Runs in the transaction that
+// generated the rappel, so it is never stored without them.
+pub(crate) async fn imputer_echeances_rappel(
+    tx: &mut sqlx::Transaction<'_, sqlx::Postgres>,
+    id_resident: i32,
+    exercice: i32,
+) -> Result<(), String> {
+    let rappel = sqlx::query!(
+        r#"
+        SELECT id_rappel, montant as "montant!"
+        FROM rappels_annuels
+        WHERE id_resident = $1 AND exercice = $2
+        ORDER BY id_rappel DESC
+        LIMIT 1
+        FOR UPDATE
+        "#,
+        id_resident,
+        exercice
+    )
+    .fetch_optional(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to fetch rappel: {}", e))?;
+
+    let Some(rappel) = rappel else {
+        return Ok(());
+    };
+
+    let echeances = sqlx::query!(
+        r#"
+        SELECT echeances_recouvrement.id_echeance, echeances_recouvrement.id_recouvrement, echeances_recouvrement.montant
+        FROM echeances_recouvrement
+        JOIN recouvrements ON echeances_recouvrement.id_recouvrement = recouvrements.id_recouvrement
+        WHERE recouvrements.id_resident = $1
+          AND echeances_recouvrement.exercice = $2
+          AND echeances_recouvrement.id_rappel IS NULL
+        ORDER BY recouvrements.created_at, echeances_recouvrement.rang
+        "#,
+        id_resident,
+        exercice
+    )
+    .fetch_all(&mut *tx)
+    .await
+    .map_err(|e| format!("Failed to fetch recovery installments: {}", e))?;
+
+    let montants: Vec<BigDecimal> = echeances.iter().map(|echeance| echeance.montant.clone()).collect();
+    let imputations = imputer_sur_rappel(&rappel.montant, &montants);
+    let mut retenue = BigDecimal::zero();
+
+    for (echeance, (impute, reporte)) in echeances.iter().zip(imputations) {
+        if impute.is_zero() {
+            sqlx::query!(
+                "UPDATE echeances_recouvrement SET exercice = $1 WHERE id_echeance = $2",
+                exercice + 1,
+                echeance.id_echeance
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to carry over recovery installment: {}", e))?;
+            continue;
+        }
+
+        sqlx::query!(
+            "UPDATE echeances_recouvrement SET montant = $1, id_rappel = $2 WHERE id_echeance = $3",
+            impute,
+            rappel.id_rappel,
+            echeance.id_echeance
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to apply recovery installment: {}", e))?;
+        retenue += &impute;
+
+        if !reporte.is_zero() {
+            sqlx::query!(
+                "INSERT INTO echeances_recouvrement (id_recouvrement, rang, montant, exercice)
+                 VALUES ($1, (SELECT MAX(rang) + 1 FROM echeances_recouvrement WHERE id_recouvrement = $1), $2, $3)",
+                echeance.id_recouvrement,
+                reporte,
+                exercice + 1
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(|e| format!("Failed to carry over recovery installment: {}", e))?;
+        }
+    }
+
+    if !retenue.is_zero() {
+        sqlx::query!(
+            "UPDATE rappels_annuels SET montant = montant - $1, retenue_recouvrement = retenue_recouvrement + $1 WHERE id_rappel = $2",
+            retenue,
+            rappel.id_rappel
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(|e| format!("Failed to apply recovery installment: {}", e))?;
+    }
+
+    Ok(())
+}
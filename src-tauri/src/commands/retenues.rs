@@ -0,0 +1,233 @@
+use crate::models::{AppState, NewParametreIr, NewTauxCotisation, NewTrancheIr, ParametreIr, TauxCotisation, TrancheIr};
+use crate::payroll::{RegimeRetenues, ASSIETTES};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::{Datelike, NaiveDate};
+use tauri::State;
+use thiserror::Error;
+
+
+//managing deduction rates (contributions, income tax)
+
+#[derive(Debug, Error)]
+pub enum RetenueValidationError {
+    #[error("Le code de la cotisation est obligatoire")]
+    EmptyCode,
+    #[error("Le taux doit être compris entre 0 et 100")]
+    InvalidRate,
+    #[error("Assiette inconnue : {0}")]
+    UnknownBase(String),
+    #[error("Les montants ne peuvent pas être négatifs")]
+    NegativeAmount,
+    #[error("La borne supérieure de la tranche doit dépasser sa borne inférieure")]
+    InvalidBracket,
+    #[error("La date d'effet doit être le premier jour d'un mois")]
+    InvalidEffectiveDate,
+    #[error("Une version existe déjà pour cette date d'effet")]
+    Duplicate,
+}
+
+fn validate_taux(taux: &BigDecimal, date_effet: NaiveDate) -> Result<(), String> {
+    if taux < &BigDecimal::zero() || taux > &BigDecimal::from(100) {
+        return Err(RetenueValidationError::InvalidRate.to_string());
+    }
+
+    if date_effet.day() != 1 {
+        return Err(RetenueValidationError::InvalidEffectiveDate.to_string());
+    }
+
+    Ok(())
+}
+
+fn validate_montants(montants: &[&BigDecimal]) -> Result<(), String> {
+    if montants.iter().any(|montant| *montant < &BigDecimal::zero()) {
+        return Err(RetenueValidationError::NegativeAmount.to_string());
+    }
+
+    Ok(())
+}
+
+fn map_retenue_error(error: sqlx::Error, action: &str) -> String {
+    match &error {
+        sqlx::Error::Database(db_error) if db_error.code().as_deref() == Some("23505") => {
+            RetenueValidationError::Duplicate.to_string()
+        }
+        _ => format!("Failed to {} deduction rate: {}", action, error),
+    }
+}
+
+pub(crate) async fn fetch_regime_retenues(pool: &sqlx::PgPool) -> Result<RegimeRetenues, sqlx::Error> {
+    let cotisations = sqlx::query_as::<_, TauxCotisation>("SELECT * FROM taux_cotisation ORDER BY code, date_effet DESC")
+        .fetch_all(pool)
+        .await?;
+    let tranches_ir = sqlx::query_as::<_, TrancheIr>("SELECT * FROM tranche_ir ORDER BY date_effet DESC, revenu_min")
+        .fetch_all(pool)
+        .await?;
+    let parametres_ir = sqlx::query_as::<_, ParametreIr>("SELECT * FROM parametre_ir ORDER BY date_effet DESC")
+        .fetch_all(pool)
+        .await?;
+
+    Ok(RegimeRetenues {
+        cotisations,
+        tranches_ir,
+        parametres_ir,
+    })
+}
+
+#[tauri::command]
+pub async fn get_taux_cotisations(state: State<'_, AppState>) -> Result<Vec<TauxCotisation>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    sqlx::query_as::<_, TauxCotisation>("SELECT * FROM taux_cotisation ORDER BY code, date_effet DESC")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch contribution rates: {}", e))
+}
+
+// Rates are never edited in place: a new version is added with its own effective date,
+// so payrolls already computed keep the rates they were computed with
+#[tauri::command]
+pub async fn add_taux_cotisation(state: State<'_, AppState>, taux: NewTauxCotisation) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let code = taux.code.trim().to_uppercase();
+    if code.is_empty() {
+        return Err(RetenueValidationError::EmptyCode.to_string());
+    }
+    if !ASSIETTES.contains(&taux.assiette.as_str()) {
+        return Err(RetenueValidationError::UnknownBase(taux.assiette).to_string());
+    }
+    validate_taux(&taux.taux, taux.date_effet)?;
+    if let Some(plafond) = &taux.plafond {
+        validate_montants(&[plafond])?;
+    }
+
+    sqlx::query!(
+        "INSERT INTO taux_cotisation (code, libelle, taux, assiette, plafond, date_effet) VALUES ($1, $2, $3, $4, $5, $6)",
+        code,
+        taux.libelle.trim(),
+        taux.taux,
+        taux.assiette,
+        taux.plafond,
+        taux.date_effet
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| map_retenue_error(e, "add"))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_taux_cotisation(state: State<'_, AppState>, id_taux: i32) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    sqlx::query!("DELETE FROM taux_cotisation WHERE id_taux = $1", id_taux)
+        .execute(pool)
+        .await
+        .map_err(|e| map_retenue_error(e, "delete"))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_tranches_ir(state: State<'_, AppState>) -> Result<Vec<TrancheIr>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    sqlx::query_as::<_, TrancheIr>("SELECT * FROM tranche_ir ORDER BY date_effet DESC, revenu_min")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch income tax brackets: {}", e))
+}
+
+#[tauri::command]
+pub async fn add_tranche_ir(state: State<'_, AppState>, tranche: NewTrancheIr) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_taux(&tranche.taux, tranche.date_effet)?;
+    validate_montants(&[&tranche.revenu_min, &tranche.somme_a_deduire])?;
+    if tranche.revenu_max.as_ref().is_some_and(|max| max <= &tranche.revenu_min) {
+        return Err(RetenueValidationError::InvalidBracket.to_string());
+    }
+
+    sqlx::query!(
+        "INSERT INTO tranche_ir (revenu_min, revenu_max, taux, somme_a_deduire, date_effet) VALUES ($1, $2, $3, $4, $5)",
+        tranche.revenu_min,
+        tranche.revenu_max,
+        tranche.taux,
+        tranche.somme_a_deduire,
+        tranche.date_effet
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| map_retenue_error(e, "add"))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_tranche_ir(state: State<'_, AppState>, id_tranche: i32) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    sqlx::query!("DELETE FROM tranche_ir WHERE id_tranche = $1", id_tranche)
+        .execute(pool)
+        .await
+        .map_err(|e| map_retenue_error(e, "delete"))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn get_parametres_ir(state: State<'_, AppState>) -> Result<Vec<ParametreIr>, String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    sqlx::query_as::<_, ParametreIr>("SELECT * FROM parametre_ir ORDER BY date_effet DESC")
+        .fetch_all(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch income tax settings: {}", e))
+}
+
+#[tauri::command]
+pub async fn add_parametre_ir(state: State<'_, AppState>, parametre: NewParametreIr) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    validate_taux(&parametre.taux_frais_pro, parametre.date_effet)?;
+    validate_montants(&[&parametre.deduction_par_charge, &parametre.plafond_frais_pro])?;
+    if parametre.max_charges < 0 {
+        return Err(RetenueValidationError::NegativeAmount.to_string());
+    }
+
+    sqlx::query!(
+        "INSERT INTO parametre_ir (deduction_par_charge, max_charges, taux_frais_pro, plafond_frais_pro, date_effet) VALUES ($1, $2, $3, $4, $5)",
+        parametre.deduction_par_charge,
+        parametre.max_charges,
+        parametre.taux_frais_pro,
+        parametre.plafond_frais_pro,
+        parametre.date_effet
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| map_retenue_error(e, "add"))?;
+
+    Ok(())
+}
+
+#[tauri::command]
+pub async fn delete_parametre_ir(state: State<'_, AppState>, id_parametre: i32) -> Result<(), String> {
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    sqlx::query!("DELETE FROM parametre_ir WHERE id_parametre = $1", id_parametre)
+        .execute(pool)
+        .await
+        .map_err(|e| map_retenue_error(e, "delete"))?;
+
+    Ok(())
+}
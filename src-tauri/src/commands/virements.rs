@@ -0,0 +1,172 @@
+use crate::commands::auth::session_courante;
+use crate::documents::virements::{format_fixe, grouper_par_banque, rendre_fichier_fixe, rendre_pain001, LotBanque, Remise, Virement};
+use crate::documents::{nom_fichier, Employeur};
+use crate::models::{AppState, ExportVirements, PayrollRun, TotalBanque};
+use crate::payroll::{peut_rapprocher, Periode, StatutRun};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::Local;
+use std::collections::HashMap;
+use std::path::Path;
+use tauri::State;
+
+
+//exporting bulk-transfer files
+
+struct Export {
+    remise: Remise,
+    lots: Vec<LotBanque>,
+    // Fixed-width layout of each bank, by bank name
+    formats: HashMap<String, String>,
+}
+
+// Records the RIB each pending transfer of the run is sent to, the one its
+// return will be matched on, optionally for the residents of one bank
+pub(crate) async fn enregistrer_rib_envoye(pool: &sqlx::PgPool, id_run: i32, id_banque: Option<i32>) -> Result<(), String> {
+    sqlx::query!(
+        "UPDATE paiement_mensuel SET rib_envoye = residents.rib
+         FROM residents
+         WHERE paiement_mensuel.id_resident = residents.id_resident
+           AND paiement_mensuel.id_run = $1 AND paiement_mensuel.montant > 0
+           AND paiement_mensuel.statut_virement = 'en_attente'
+           AND ($2::int IS NULL OR residents.id_banque = $2)",
+        id_run,
+        id_banque
+    )
+    .execute(pool)
+    .await
+    .map_err(|e| format!("Failed to record sent RIBs: {}", e))?;
+
+    Ok(())
+}
+
+// Transfers of the run of `periode`, which must have been validated
+async fn charger_export(pool: &sqlx::PgPool, periode: Periode) -> Result<Export, String> {
+    let run = sqlx::query_as::<_, PayrollRun>("SELECT * FROM payroll_run WHERE annee = $1 AND mois = $2")
+        .bind(periode.annee)
+        .bind(periode.mois as i32)
+        .fetch_optional(pool)
+        .await
+        .map_err(|e| format!("Failed to fetch payroll run: {}", e))?
+        .ok_or_else(|| format!("Aucun run de paie pour la période {}", periode))?;
+
+    if !StatutRun::parse(&run.statut).map_err(|e| e.to_string())?.est_verrouille() {
+        return Err("Le run de paie doit être validé avant l'export des virements".to_string());
+    }
+
+    enregistrer_rib_envoye(pool, run.id_run, None).await?;
+    let records = sqlx::query!(
+        r#"
+        SELECT
+            paiement_mensuel.id_paiement,
+            paiement_mensuel.montant,
+            residents.nom_prenom as "nom_prenom?",
+            COALESCE(paiement_mensuel.rib_envoye, residents.rib) as "rib?",
+            banque.nom as "nom_banque?",
+            banque.code_banque as "code_banque?",
+            banque.swift_bic as "swift_bic?",
+            banque.format_virement as "format_virement?"
+        FROM paiement_mensuel
+        LEFT JOIN residents ON paiement_mensuel.id_resident = residents.id_resident
+        LEFT JOIN banque ON residents.id_banque = banque.id_banque
+        WHERE paiement_mensuel.id_run = $1 AND paiement_mensuel.montant > 0
+        "#,
+        run.id_run
+    )
+    .fetch_all(pool)
+    .await
+    .map_err(|e| format!("Failed to fetch payments: {}", e))?;
+
+    let mut formats = HashMap::new();
+    let mut virements = Vec::new();
+    for record in records {
+        let beneficiaire = record.nom_prenom.unwrap_or_default();
+        let nom_banque = record
+            .nom_banque
+            .ok_or_else(|| format!("Aucune banque n'est renseignée pour {}", beneficiaire))?;
+
+        formats.insert(nom_banque.clone(), record.format_virement.unwrap_or_else(|| "fixe160".to_string()));
+        virements.push(Virement {
+            id_paiement: record.id_paiement,
+            beneficiaire,
+            rib: record.rib.unwrap_or_default(),
+            nom_banque,
+            code_banque: record.code_banque,
+            bic: record.swift_bic,
+            montant: record.montant,
+        });
+    }
+
+    let lots = grouper_par_banque(&virements).map_err(|e| e.to_string())?;
+    let remise = Remise {
+        reference: format!("PAIE{}{:02}", periode.annee, periode.mois),
+        date_execution: Local::now().naive_local().date(),
+        libelle: format!("Paie residents {:02}/{}", periode.mois, periode.annee),
+    };
+
+    Ok(Export { remise, lots, formats })
+}
+
+fn bilan(remise: &Remise, lots: &[LotBanque], fichiers: Vec<String>) -> ExportVirements {
+    ExportVirements {
+        reference: remise.reference.clone(),
+        fichiers,
+        banques: lots
+            .iter()
+            .map(|lot| TotalBanque {
+                nom_banque: lot.nom_banque.clone(),
+                nombre_virements: lot.nombre() as i32,
+                total: lot.total.clone(),
+            })
+            .collect(),
+        nombre_virements: lots.iter().map(|lot| lot.nombre() as i32).sum(),
+        total: lots.iter().fold(BigDecimal::zero(), |total, lot| total + &lot.total),
+    }
+}
+
+fn ecrire(chemin: &Path, contenu: &str) -> Result<String, String> {
+    std::fs::write(chemin, contenu).map_err(|e| format!("Failed to write {}: {}", chemin.display(), e))?;
+    Ok(chemin.display().to_string())
+}
+
+// Writes one fixed-width transfer file per bank into `dossier`, in the layout
+// configured for the bank
+#[tauri::command]
+pub async fn export_bank_files(state: State<'_, AppState>, annee: i32, mois: u32, dossier: String) -> Result<ExportVirements, String> {
+    let session = session_courante(&state).await?;
+    peut_rapprocher(session.role).map_err(|e| e.to_string())?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let periode = Periode::new(annee, mois).map_err(|e| e.to_string())?;
+    let export = charger_export(pool, periode).await?;
+    let employeur = Employeur::depuis_env();
+
+    let mut fichiers = Vec::new();
+    for lot in &export.lots {
+        let format = format_fixe(&export.formats[&lot.nom_banque]).map_err(|e| e.to_string())?;
+        let contenu = rendre_fichier_fixe(format, &employeur, &export.remise, lot).map_err(|e| e.to_string())?;
+        let chemin = Path::new(&dossier).join(format!("{} {}.txt", export.remise.reference, nom_fichier(&lot.nom_banque)));
+        fichiers.push(ecrire(&chemin, &contenu)?);
+    }
+
+    Ok(bilan(&export.remise, &export.lots, fichiers))
+}
+
+// Writes the ISO 20022 pain.001 file of the month to `chemin`, one payment
+// information block per bank
+#[tauri::command]
+pub async fn export_pain001(state: State<'_, AppState>, annee: i32, mois: u32, chemin: String) -> Result<ExportVirements, String> {
+    let session = session_courante(&state).await?;
+    peut_rapprocher(session.role).map_err(|e| e.to_string())?;
+    let pool = state.pool.lock().await;
+    let pool = pool.as_ref().ok_or("Database not connected")?;
+
+    let periode = Periode::new(annee, mois).map_err(|e| e.to_string())?;
+    let export = charger_export(pool, periode).await?;
+
+    let xml = rendre_pain001(&Employeur::depuis_env(), &export.remise, &export.lots, Local::now().naive_local())
+        .map_err(|e| e.to_string())?;
+    let fichier = ecrire(Path::new(&chemin), &xml)?;
+
+    Ok(bilan(&export.remise, &export.lots, vec![fichier]))
+}
@@ -0,0 +1,471 @@
+use crate::documents::bulletin::{
+    montant_a_droite, nouvelle_page, trait_horizontal, tronquer, Polices, HAUTEUR_LIGNE, HAUTEUR_PAGE, LARGEUR_PAGE, MARGE,
+};
+use crate::documents::{montant_en_lettres, DocumentError, Employeur, NumeroOrdre};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use printpdf::{BuiltinFont, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference};
+
+// Characters per line of the body text at 11 pt across the page
+const LARGEUR_TEXTE: usize = 92;
+// Date of issue and signature block drawn under the text
+const HAUTEUR_SIGNATURE: f32 = 10.0 + 2.0 * HAUTEUR_LIGNE + 2.0;
+
+// Placeholders a work certificate template may use, written `{nom_prenom}`
+pub const CHAMPS_MODELE: [&str; 9] = [
+    "nom_prenom",
+    "specialite",
+    "date_debut",
+    "date_fin",
+    "periode",
+    "statut",
+    "numero",
+    "date_edition",
+    "employeur",
+];
+
+// One payment received during the year: a monthly payment or a rappel
+#[derive(Debug, Clone)]
+pub struct LigneAttestation {
+    pub libelle: String,
+    pub date_paiement: NaiveDate,
+    pub brut: BigDecimal,
+    pub allocations: BigDecimal,
+    pub retenues: BigDecimal,
+    pub net: BigDecimal,
+}
+
+// Salary attestation: what a resident was paid over a calendar year
+#[derive(Debug, Clone)]
+pub struct AttestationSalaire {
+    pub nom_prenom: String,
+    pub nom_specialite: Option<String>,
+    pub date_debut: NaiveDate,
+    pub annee: i32,
+    pub lignes: Vec<LigneAttestation>,
+    pub date_edition: NaiveDate,
+}
+
+impl AttestationSalaire {
+    // Gross, family allowances, deductions and net over the year
+    pub fn totaux(&self) -> [BigDecimal; 4] {
+        self.lignes.iter().fold(
+            [BigDecimal::zero(), BigDecimal::zero(), BigDecimal::zero(), BigDecimal::zero()],
+            |[brut, allocations, retenues, net], ligne| {
+                [brut + &ligne.brut, allocations + &ligne.allocations, retenues + &ligne.retenues, net + &ligne.net]
+            },
+        )
+    }
+}
+
+// Work certificate: a template filled with the resident's career
+#[derive(Debug, Clone)]
+pub struct AttestationTravail {
+    pub numero: NumeroOrdre,
+    pub nom_prenom: String,
+    pub nom_specialite: Option<String>,
+    pub date_debut: NaiveDate,
+    pub date_fin: Option<NaiveDate>,
+    pub date_edition: NaiveDate,
+    pub titre: String,
+    pub corps: String,
+}
+
+impl AttestationTravail {
+    // A resident is in post until the day their end date has passed
+    pub fn en_fonction(&self) -> bool {
+        !matches!(self.date_fin, Some(fin) if fin < self.date_edition)
+    }
+
+    fn valeurs(&self, employeur: &Employeur) -> Vec<(&'static str, Option<String>)> {
+        let date = |date: NaiveDate| date.format("%d/%m/%Y").to_string();
+        let debut = date(self.date_debut);
+        let fin = self.date_fin.map(date);
+
+        let (periode, statut) = match (&fin, self.en_fonction()) {
+            (Some(fin), false) => (format!("du {} au {}", debut, fin), format!("ayant cessé ses fonctions le {}", fin)),
+            _ => (format!("depuis le {}", debut), "en fonction".to_string()),
+        };
+
+        vec![
+            ("nom_prenom", Some(self.nom_prenom.clone())),
+            ("specialite", self.nom_specialite.clone()),
+            ("date_debut", Some(debut)),
+            ("date_fin", fin),
+            ("periode", Some(periode)),
+            ("statut", Some(statut)),
+            ("numero", Some(format!("{}/{}", self.numero.numero, self.numero.exercice))),
+            ("date_edition", Some(date(self.date_edition))),
+            ("employeur", Some(employeur.nom.clone())),
+        ]
+    }
+
+    // The template's text with every placeholder replaced
+    pub fn texte(&self, employeur: &Employeur) -> Result<String, DocumentError> {
+        remplir_modele(&self.corps, &self.valeurs(employeur))
+    }
+}
+
+// Copies `modele`, replacing each `{champ}` with what `valeur` returns for it
+fn remplacer(modele: &str, mut valeur: impl FnMut(&str) -> Result<String, DocumentError>) -> Result<String, DocumentError> {
+    let mut texte = String::with_capacity(modele.len());
+    let mut reste = modele;
+
+    while let Some(debut) = reste.find('{') {
+        texte.push_str(&reste[..debut]);
+        let fin = reste[debut..].find('}').ok_or(DocumentError::AccoladeOuverte)? + debut;
+        texte.push_str(&valeur(reste[debut + 1..fin].trim())?);
+        reste = &reste[fin + 1..];
+    }
+    texte.push_str(reste);
+
+    Ok(texte)
+}
+
+// Checks a template before it is saved: braces closed, placeholders known
+pub fn verifier_modele(modele: &str) -> Result<(), DocumentError> {
+    remplacer(modele, |champ| {
+        if CHAMPS_MODELE.contains(&champ) {
+            Ok(String::new())
+        } else {
+            Err(DocumentError::ChampInconnu(champ.to_string()))
+        }
+    })
+    .map(|_| ())
+}
+
+// Fills a template; a placeholder without a value for this resident is an error
+// rather than a blank in a signed document
+pub fn remplir_modele(modele: &str, valeurs: &[(&str, Option<String>)]) -> Result<String, DocumentError> {
+    remplacer(modele, |champ| match valeurs.iter().find(|(nom, _)| *nom == champ) {
+        Some((_, Some(valeur))) => Ok(valeur.clone()),
+        Some((_, None)) => Err(DocumentError::ChampVide(champ.to_string())),
+        None => Err(DocumentError::ChampInconnu(champ.to_string())),
+    })
+}
+
+// Splits a paragraph into lines of at most `longueur` characters, between words
+fn couper(texte: &str, longueur: usize) -> Vec<String> {
+    let mut lignes: Vec<String> = Vec::new();
+    let mut courante = String::new();
+
+    for mot in texte.split_whitespace() {
+        if !courante.is_empty() && courante.chars().count() + 1 + mot.chars().count() > longueur {
+            lignes.push(std::mem::take(&mut courante));
+        }
+        if !courante.is_empty() {
+            courante.push(' ');
+        }
+        courante.push_str(mot);
+    }
+    if !courante.is_empty() {
+        lignes.push(courante);
+    }
+
+    lignes
+}
+
+fn paragraphe(calque: &PdfLayerReference, polices: &Polices, texte: &str, y: &mut f32) {
+    for ligne in couper(texte, LARGEUR_TEXTE) {
+        calque.use_text(ligne, 11.0, Mm(MARGE), Mm(*y), &polices.normale);
+        *y -= HAUTEUR_LIGNE;
+    }
+}
+
+// Left edge of a line of `texte` centred on the page, from the average glyph
+// width of Helvetica (about half the font size, more for bold capitals)
+fn centrer(texte: &str, taille: f32, chasse: f32) -> f32 {
+    let largeur = texte.chars().count() as f32 * taille * chasse * 0.3528;
+    ((LARGEUR_PAGE - largeur) / 2.0).max(MARGE)
+}
+
+// Employer block then the centred title and subtitle; returns where the body starts
+fn entete(calque: &PdfLayerReference, polices: &Polices, employeur: &Employeur, titre: &str, sous_titre: &str) -> f32 {
+    let mut y = HAUTEUR_PAGE - MARGE - 5.0;
+
+    calque.use_text(employeur.nom.as_str(), 13.0, Mm(MARGE), Mm(y), &polices.grasse);
+    if !employeur.adresse.is_empty() {
+        y -= 5.0;
+        calque.use_text(employeur.adresse.as_str(), 9.0, Mm(MARGE), Mm(y), &polices.normale);
+    }
+
+    y -= 20.0;
+    calque.use_text(titre, 16.0, Mm(centrer(titre, 16.0, 0.62)), Mm(y), &polices.grasse);
+    y -= 7.0;
+    calque.use_text(sous_titre, 11.0, Mm(centrer(sous_titre, 11.0, 0.5)), Mm(y), &polices.normale);
+
+    y - 16.0
+}
+
+// Date of issue and room for the signature and the stamp, under the text
+fn signature(calque: &PdfLayerReference, polices: &Polices, date_edition: NaiveDate, mut y: f32) {
+    y -= 10.0;
+    calque.use_text(format!("Fait le {}", date_edition.format("%d/%m/%Y")), 11.0, Mm(125.0), Mm(y), &polices.normale);
+    y -= HAUTEUR_LIGNE + 2.0;
+    calque.use_text("Le Directeur", 11.0, Mm(125.0), Mm(y), &polices.grasse);
+    y -= HAUTEUR_LIGNE;
+    calque.use_text("(signature et cachet)", 9.0, Mm(125.0), Mm(y), &polices.normale);
+}
+
+fn dessiner_attestation(
+    calque: &PdfLayerReference,
+    polices: &Polices,
+    employeur: &Employeur,
+    attestation: &AttestationSalaire,
+) -> Result<(), DocumentError> {
+    let mut y = entete(calque, polices, employeur, "ATTESTATION DE SALAIRE", &format!("Année {}", attestation.annee));
+
+    let specialite = attestation
+        .nom_specialite
+        .as_deref()
+        .map(|specialite| format!(" en {}", specialite))
+        .unwrap_or_default();
+    paragraphe(
+        calque,
+        polices,
+        &format!(
+            "Le Directeur du {} atteste que le Docteur {}, médecin résident{} depuis le {}, a perçu au titre de l'année {} les rémunérations détaillées ci-après :",
+            employeur.nom,
+            attestation.nom_prenom,
+            specialite,
+            attestation.date_debut.format("%d/%m/%Y"),
+            attestation.annee
+        ),
+        &mut y,
+    );
+
+    // Columns: period, payment date, gross, family allowances, deductions, net
+    let colonnes_droite = [112.0, 139.0, 166.0, LARGEUR_PAGE - MARGE];
+    y -= 6.0;
+    trait_horizontal(calque, y + 4.5);
+    calque.use_text("Période", 10.0, Mm(MARGE), Mm(y), &polices.grasse);
+    calque.use_text("Payé le", 10.0, Mm(62.0), Mm(y), &polices.grasse);
+    for (titre, x) in ["Brut", "Alloc. fam.", "Retenues", "Net"].iter().zip(colonnes_droite) {
+        calque.use_text(*titre, 10.0, Mm(x - 20.0), Mm(y), &polices.grasse);
+    }
+    trait_horizontal(calque, y - 2.0);
+    y -= HAUTEUR_LIGNE + 1.0;
+
+    for ligne in &attestation.lignes {
+        calque.use_text(tronquer(&ligne.libelle, 26), 9.0, Mm(MARGE), Mm(y), &polices.normale);
+        calque.use_text(ligne.date_paiement.format("%d/%m/%Y").to_string(), 9.0, Mm(62.0), Mm(y), &polices.normale);
+        for (montant, x) in [&ligne.brut, &ligne.allocations, &ligne.retenues, &ligne.net].into_iter().zip(colonnes_droite) {
+            montant_a_droite(calque, montant, x, y, 9.0, &polices.normale);
+        }
+        y -= HAUTEUR_LIGNE;
+    }
+
+    trait_horizontal(calque, y + 3.0);
+    y -= 3.0;
+    let totaux = attestation.totaux();
+    calque.use_text("TOTAL", 10.0, Mm(MARGE), Mm(y), &polices.grasse);
+    for (montant, x) in totaux.iter().zip(colonnes_droite) {
+        montant_a_droite(calque, montant, x, y, 10.0, &polices.grasse);
+    }
+
+    y -= 12.0;
+    calque.use_text("Arrêtée la présente attestation à la somme nette de :", 11.0, Mm(MARGE), Mm(y), &polices.normale);
+    y -= HAUTEUR_LIGNE;
+    for ligne in couper(&montant_en_lettres(&totaux[3])?, LARGEUR_TEXTE - 6) {
+        calque.use_text(ligne, 11.0, Mm(MARGE), Mm(y), &polices.grasse);
+        y -= HAUTEUR_LIGNE;
+    }
+
+    y -= 6.0;
+    paragraphe(
+        calque,
+        polices,
+        "La présente attestation est délivrée à l'intéressé(e), sur sa demande, pour servir et valoir ce que de droit.",
+        &mut y,
+    );
+
+    signature(calque, polices, attestation.date_edition, y);
+
+    Ok(())
+}
+
+// Renders a one-page income attestation for the year
+pub fn rendre_attestation(employeur: &Employeur, attestation: &AttestationSalaire) -> Result<Vec<u8>, DocumentError> {
+    if attestation.lignes.is_empty() {
+        return Err(DocumentError::Empty);
+    }
+
+    let (document, page, calque) = PdfDocument::new("Attestation de salaire", Mm(LARGEUR_PAGE), Mm(HAUTEUR_PAGE), "Attestation");
+    let polices = Polices {
+        normale: document.add_builtin_font(BuiltinFont::Helvetica)?,
+        grasse: document.add_builtin_font(BuiltinFont::HelveticaBold)?,
+    };
+
+    dessiner_attestation(&document.get_page(page).get_layer(calque), &polices, employeur, attestation)?;
+
+    Ok(document.save_to_bytes()?)
+}
+
+// Continues a certificate on a new page headed with its title and number;
+// returns where the text goes on
+fn page_suivante(document: &PdfDocumentReference, polices: &Polices, titre: &str, numero: &str) -> (PdfLayerReference, f32) {
+    let calque = nouvelle_page(document, "Attestation");
+    let y = HAUTEUR_PAGE - MARGE - 5.0;
+    calque.use_text(format!("{} {} (suite)", titre, numero), 10.0, Mm(MARGE), Mm(y), &polices.grasse);
+    (calque, y - 12.0)
+}
+
+// Renders a numbered work certificate, one paragraph per line of the template,
+// going on to further pages when the template is longer than one
+pub fn rendre_attestation_travail(employeur: &Employeur, attestation: &AttestationTravail) -> Result<Vec<u8>, DocumentError> {
+    let texte = attestation.texte(employeur)?;
+
+    let (document, page, calque) = PdfDocument::new("Attestation de travail", Mm(LARGEUR_PAGE), Mm(HAUTEUR_PAGE), "Attestation");
+    let polices = Polices {
+        normale: document.add_builtin_font(BuiltinFont::Helvetica)?,
+        grasse: document.add_builtin_font(BuiltinFont::HelveticaBold)?,
+    };
+    let mut calque = document.get_page(page).get_layer(calque);
+
+    let titre = attestation.titre.trim().to_uppercase();
+    let numero = format!("N° {}/{}", attestation.numero.numero, attestation.numero.exercice);
+    let mut y = entete(&calque, &polices, employeur, &titre, &numero);
+
+    for ligne in texte.lines() {
+        if ligne.trim().is_empty() {
+            y -= HAUTEUR_LIGNE;
+            continue;
+        }
+
+        for morceau in couper(ligne, LARGEUR_TEXTE) {
+            if y < MARGE {
+                (calque, y) = page_suivante(&document, &polices, &titre, &numero);
+            }
+            calque.use_text(morceau, 11.0, Mm(MARGE), Mm(y), &polices.normale);
+            y -= HAUTEUR_LIGNE;
+        }
+        y -= 2.0;
+    }
+
+    if y - HAUTEUR_SIGNATURE < MARGE {
+        (calque, y) = page_suivante(&document, &polices, &titre, &numero);
+    }
+    signature(&calque, &polices, attestation.date_edition, y);
+
+    Ok(document.save_to_bytes()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn ligne(libelle: &str, brut: &str, allocations: &str, retenues: &str, net: &str) -> LigneAttestation {
+        LigneAttestation {
+            libelle: libelle.to_string(),
+            date_paiement: NaiveDate::from_ymd_opt(2024, 1, 31).unwrap(),
+            brut: BigDecimal::from_str(brut).unwrap(),
+            allocations: BigDecimal::from_str(allocations).unwrap(),
+            retenues: BigDecimal::from_str(retenues).unwrap(),
+            net: BigDecimal::from_str(net).unwrap(),
+        }
+    }
+
+    #[test]
+    fn totals_add_up_every_payment_of_the_year() {
+        let attestation = AttestationSalaire {
+            nom_prenom: "Sara Alami".to_string(),
+            nom_specialite: Some("Cardiologie".to_string()),
+            date_debut: NaiveDate::from_ymd_opt(2022, 11, 1).unwrap(),
+            annee: 2024,
+            lignes: vec![
+                ligne("Janvier 2024", "9000", "300", "900.50", "8399.50"),
+                ligne("Février 2024", "9000", "300", "900.50", "8399.50"),
+                ligne("Rappel exercice 2023", "1200", "0", "200", "1000"),
+            ],
+            date_edition: NaiveDate::from_ymd_opt(2025, 1, 15).unwrap(),
+        };
+
+        let [brut, allocations, retenues, net] = attestation.totaux();
+        assert_eq!(brut, BigDecimal::from(19200));
+        assert_eq!(allocations, BigDecimal::from(600));
+        assert_eq!(retenues, BigDecimal::from_str("2001").unwrap());
+        assert_eq!(net, BigDecimal::from_str("17799").unwrap());
+
+        let pdf = rendre_attestation(&Employeur::depuis_env(), &attestation).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+
+    fn attestation_travail(date_fin: Option<NaiveDate>, corps: &str) -> AttestationTravail {
+        AttestationTravail {
+            numero: NumeroOrdre { numero: 7, exercice: 2026 },
+            nom_prenom: "Sara Alami".to_string(),
+            nom_specialite: Some("Cardiologie".to_string()),
+            date_debut: NaiveDate::from_ymd_opt(2022, 11, 1).unwrap(),
+            date_fin,
+            date_edition: NaiveDate::from_ymd_opt(2026, 10, 19).unwrap(),
+            titre: "Attestation de travail".to_string(),
+            corps: corps.to_string(),
+        }
+    }
+
+    #[test]
+    fn templates_are_filled_from_the_resident_career() {
+        let employeur = Employeur::depuis_env();
+        let corps = "Le Docteur {nom_prenom}, résident en {specialite}, {statut}, exerce {periode}. N° { numero }.";
+
+        let en_poste = attestation_travail(None, corps);
+        assert!(en_poste.en_fonction());
+        assert_eq!(
+            en_poste.texte(&employeur).unwrap(),
+            "Le Docteur Sara Alami, résident en Cardiologie, en fonction, exerce depuis le 01/11/2022. N° 7/2026."
+        );
+
+        let sorti = attestation_travail(NaiveDate::from_ymd_opt(2026, 6, 30), "{periode}, {statut}");
+        assert!(!sorti.en_fonction());
+        assert_eq!(
+            sorti.texte(&employeur).unwrap(),
+            "du 01/11/2022 au 30/06/2026, ayant cessé ses fonctions le 30/06/2026"
+        );
+
+        // An end date still to come leaves the resident in post
+        let fin_prevue = attestation_travail(NaiveDate::from_ymd_opt(2026, 10, 31), "{statut} jusqu'au {date_fin}");
+        assert_eq!(fin_prevue.texte(&employeur).unwrap(), "en fonction jusqu'au 31/10/2026");
+
+        let pdf = rendre_attestation_travail(&employeur, &en_poste).unwrap();
+        assert!(pdf.starts_with(b"%PDF"));
+    }
+
+    #[test]
+    fn templates_reject_unknown_and_missing_fields() {
+        assert!(verifier_modele("{nom_prenom} {periode}").is_ok());
+        assert!(matches!(verifier_modele("{nom}"), Err(DocumentError::ChampInconnu(champ)) if champ == "nom"));
+        assert!(matches!(verifier_modele("{nom_prenom"), Err(DocumentError::AccoladeOuverte)));
+
+        let en_poste = attestation_travail(None, "Sorti le {date_fin}");
+        assert!(matches!(
+            en_poste.texte(&Employeur::depuis_env()),
+            Err(DocumentError::ChampVide(champ)) if champ == "date_fin"
+        ));
+    }
+
+    fn nombre_pages(pdf: &[u8]) -> usize {
+        let texte = String::from_utf8_lossy(pdf);
+        texte.matches("/Type/Page").count() - texte.matches("/Type/Pages").count()
+    }
+
+    #[test]
+    fn long_templates_go_on_to_further_pages() {
+        let employeur = Employeur::depuis_env();
+
+        let court = attestation_travail(None, "Le Docteur {nom_prenom} exerce {periode}.");
+        assert_eq!(nombre_pages(&rendre_attestation_travail(&employeur, &court).unwrap()), 1);
+
+        // About 27 paragraphs fit under the header of the first page, 31 on the next ones
+        let corps = vec!["Le Docteur {nom_prenom} exerce {periode}."; 80].join("\n");
+        let long = attestation_travail(None, &corps);
+        assert_eq!(nombre_pages(&rendre_attestation_travail(&employeur, &long).unwrap()), 3);
+    }
+
+    #[test]
+    fn paragraphs_wrap_between_words() {
+        let lignes = couper("un deux trois quatre cinq", 10);
+        assert_eq!(lignes, vec!["un deux", "trois", "quatre", "cinq"]);
+        assert!(couper("", 10).is_empty());
+    }
+}
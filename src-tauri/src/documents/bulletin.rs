@@ -0,0 +1,314 @@
+use crate::documents::{formater_montant, montant_en_lettres, DocumentError};
+use crate::models::LignePaiement;
+use crate::payroll::Periode;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use printpdf::{BuiltinFont, IndirectFontRef, Line, Mm, PdfDocument, PdfDocumentReference, PdfLayerReference, Point};
+
+pub(crate) const LARGEUR_PAGE: f32 = 210.0;
+pub(crate) const HAUTEUR_PAGE: f32 = 297.0;
+pub(crate) const MARGE: f32 = 15.0;
+pub(crate) const HAUTEUR_LIGNE: f32 = 6.0;
+const MAX_LIBELLE: usize = 48;
+
+// Columns right of the label: base, rate, earnings, deductions
+const COLONNES_DROITE: [f32; 4] = [120.0, 140.0, 167.0, LARGEUR_PAGE - MARGE];
+// Height of the totals and the amount in words closing the payslip
+const HAUTEUR_TOTAUX: f32 = 3.0 + 3.0 * HAUTEUR_LIGNE + 6.0 + 2.0 * HAUTEUR_LIGNE;
+
+// Name and address printed at the top of every payslip, and the account
+// the transfers are ordered from
+#[derive(Debug, Clone)]
+pub struct Employeur {
+    pub nom: String,
+    pub adresse: String,
+    pub rib: String,
+    pub bic: Option<String>,
+    // Tax identification number, required on the annual salary declaration
+    pub identifiant_fiscal: Option<String>,
+}
+
+impl Employeur {
+    // Taken from the environment like the database settings, with the hospital as default
+    pub fn depuis_env() -> Self {
+        Self {
+            nom: std::env::var("EMPLOYEUR_NOM").unwrap_or_else(|_| "Centre Hospitalier Universitaire".to_string()),
+            adresse: std::env::var("EMPLOYEUR_ADRESSE").unwrap_or_default(),
+            rib: std::env::var("EMPLOYEUR_RIB").unwrap_or_default(),
+            bic: std::env::var("EMPLOYEUR_BIC").ok().filter(|bic| !bic.trim().is_empty()),
+            identifiant_fiscal: std::env::var("EMPLOYEUR_IF").ok().filter(|identifiant| !identifiant.trim().is_empty()),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct BulletinPaie {
+    pub nom_prenom: String,
+    pub nom_specialite: Option<String>,
+    pub nom_banque: Option<String>,
+    pub rib: String,
+    pub periode: Periode,
+    pub date_paiement: NaiveDate,
+    pub jours_travail: i32,
+    pub lignes: Vec<LignePaiement>,
+    pub salaire_brut: BigDecimal,
+    pub total_retenues: BigDecimal,
+    pub montant: BigDecimal,
+}
+
+// Only the bank code and the last four digits of the RIB are printed
+pub fn masquer_rib(rib: &str) -> String {
+    let chiffres: Vec<char> = rib.chars().filter(|c| !c.is_whitespace()).collect();
+    if chiffres.len() <= 7 {
+        return "*".repeat(chiffres.len());
+    }
+
+    let debut: String = chiffres[..3].iter().collect();
+    let fin: String = chiffres[chiffres.len() - 4..].iter().collect();
+    format!("{}{}{}", debut, "*".repeat(chiffres.len() - 7), fin)
+}
+
+// Helvetica widths: digits are 0.556 em, space, comma, period and minus 0.278 or 0.333
+fn largeur_nombre(texte: &str, taille: f32) -> f32 {
+    let em: f32 = texte
+        .chars()
+        .map(|c| match c {
+            '0'..='9' => 0.556,
+            '-' => 0.333,
+            _ => 0.278,
+        })
+        .sum();
+    em * taille * 0.3528
+}
+
+pub(crate) fn tronquer(texte: &str, longueur: usize) -> String {
+    if texte.chars().count() <= longueur {
+        texte.to_string()
+    } else {
+        format!("{}...", texte.chars().take(longueur - 3).collect::<String>())
+    }
+}
+
+pub(crate) struct Polices {
+    pub normale: IndirectFontRef,
+    pub grasse: IndirectFontRef,
+}
+
+pub(crate) fn trait_horizontal(calque: &PdfLayerReference, y: f32) {
+    calque.add_line(Line {
+        points: vec![
+            (Point::new(Mm(MARGE), Mm(y)), false),
+            (Point::new(Mm(LARGEUR_PAGE - MARGE), Mm(y)), false),
+        ],
+        is_closed: false,
+    });
+}
+
+pub(crate) fn montant_a_droite(calque: &PdfLayerReference, montant: &BigDecimal, x_droite: f32, y: f32, taille: f32, police: &IndirectFontRef) {
+    let texte = formater_montant(montant);
+    calque.use_text(texte.clone(), taille, Mm(x_droite - largeur_nombre(&texte, taille)), Mm(y), police);
+}
+
+pub(crate) fn nouvelle_page(document: &PdfDocumentReference, nom: &str) -> PdfLayerReference {
+    let (page, calque) = document.add_page(Mm(LARGEUR_PAGE), Mm(HAUTEUR_PAGE), nom);
+    document.get_page(page).get_layer(calque)
+}
+
+fn entete_tableau(calque: &PdfLayerReference, polices: &Polices, mut y: f32) -> f32 {
+    y -= 4.0;
+    trait_horizontal(calque, y + 4.5);
+    calque.use_text("Libellé", 10.0, Mm(MARGE), Mm(y), &polices.grasse);
+    for (titre, x) in ["Base", "Taux %", "Gains", "Retenues"].iter().zip(COLONNES_DROITE) {
+        calque.use_text(*titre, 10.0, Mm(x - 16.0), Mm(y), &polices.grasse);
+    }
+    trait_horizontal(calque, y - 2.0);
+    y - (HAUTEUR_LIGNE + 1.0)
+}
+
+// Closes the current page and starts the next one with the resident, the period
+// and the column titles again
+fn page_suivante(
+    document: &PdfDocumentReference,
+    calque: &PdfLayerReference,
+    polices: &Polices,
+    bulletin: &BulletinPaie,
+    y: f32,
+) -> (PdfLayerReference, f32) {
+    trait_horizontal(calque, y + 3.0);
+    calque.use_text("Suite page suivante", 9.0, Mm(MARGE), Mm(y - 2.0), &polices.normale);
+
+    let calque = nouvelle_page(document, "Bulletin");
+    let y = HAUTEUR_PAGE - MARGE - 5.0;
+    calque.use_text(
+        format!("BULLETIN DE PAIE (suite) - {} - {}", bulletin.nom_prenom, bulletin.periode),
+        10.0,
+        Mm(MARGE),
+        Mm(y),
+        &polices.grasse,
+    );
+    let y = entete_tableau(&calque, polices, y - 6.0);
+    (calque, y)
+}
+
+fn dessiner_bulletin(
+    document: &PdfDocumentReference,
+    calque: PdfLayerReference,
+    polices: &Polices,
+    employeur: &Employeur,
+    bulletin: &BulletinPaie,
+) -> Result<(), DocumentError> {
+    let mut calque = calque;
+    let mut y = HAUTEUR_PAGE - MARGE - 5.0;
+
+    calque.use_text(employeur.nom.as_str(), 13.0, Mm(MARGE), Mm(y), &polices.grasse);
+    if !employeur.adresse.is_empty() {
+        y -= 5.0;
+        calque.use_text(employeur.adresse.as_str(), 9.0, Mm(MARGE), Mm(y), &polices.normale);
+    }
+
+    y -= 14.0;
+    calque.use_text("BULLETIN DE PAIE", 16.0, Mm(75.0), Mm(y), &polices.grasse);
+    y -= 7.0;
+    calque.use_text(
+        format!("Période : {}    Date de paiement : {}", bulletin.periode, bulletin.date_paiement.format("%d/%m/%Y")),
+        10.0,
+        Mm(62.0),
+        Mm(y),
+        &polices.normale,
+    );
+
+    y -= 12.0;
+    let identite = [
+        ("Nom et prénom", bulletin.nom_prenom.clone()),
+        ("Spécialité", bulletin.nom_specialite.clone().unwrap_or_default()),
+        ("Banque", bulletin.nom_banque.clone().unwrap_or_default()),
+        ("RIB", masquer_rib(&bulletin.rib)),
+        ("Jours travaillés", bulletin.jours_travail.to_string()),
+    ];
+    for (libelle, valeur) in identite {
+        calque.use_text(format!("{} :", libelle), 10.0, Mm(MARGE), Mm(y), &polices.grasse);
+        calque.use_text(valeur, 10.0, Mm(MARGE + 35.0), Mm(y), &polices.normale);
+        y -= HAUTEUR_LIGNE;
+    }
+
+    y = entete_tableau(&calque, polices, y);
+
+    for (index, ligne) in bulletin.lignes.iter().enumerate() {
+        // The last line stays with the totals
+        let reserve = if index + 1 == bulletin.lignes.len() { HAUTEUR_TOTAUX } else { HAUTEUR_LIGNE };
+        if y - reserve < MARGE {
+            (calque, y) = page_suivante(document, &calque, polices, bulletin, y);
+        }
+
+        calque.use_text(tronquer(&ligne.libelle, MAX_LIBELLE), 9.0, Mm(MARGE), Mm(y), &polices.normale);
+        if let Some(base) = &ligne.base {
+            montant_a_droite(&calque, base, COLONNES_DROITE[0], y, 9.0, &polices.normale);
+        }
+        if let Some(taux) = &ligne.taux {
+            montant_a_droite(&calque, taux, COLONNES_DROITE[1], y, 9.0, &polices.normale);
+        }
+        let colonne = if ligne.categorie == "retenue" { COLONNES_DROITE[3] } else { COLONNES_DROITE[2] };
+        montant_a_droite(&calque, &ligne.montant, colonne, y, 9.0, &polices.normale);
+        y -= HAUTEUR_LIGNE;
+    }
+
+    trait_horizontal(&calque, y + 3.0);
+    y -= 3.0;
+    for (libelle, montant) in [
+        ("Salaire brut", &bulletin.salaire_brut),
+        ("Total des retenues", &bulletin.total_retenues),
+        ("NET À PAYER", &bulletin.montant),
+    ] {
+        calque.use_text(libelle, 10.0, Mm(110.0), Mm(y), &polices.grasse);
+        montant_a_droite(&calque, montant, COLONNES_DROITE[3], y, 10.0, &polices.grasse);
+        y -= HAUTEUR_LIGNE;
+    }
+
+    y -= 6.0;
+    calque.use_text("Arrêté le présent bulletin à la somme de :", 10.0, Mm(MARGE), Mm(y), &polices.normale);
+    y -= HAUTEUR_LIGNE;
+    calque.use_text(montant_en_lettres(&bulletin.montant)?, 10.0, Mm(MARGE), Mm(y), &polices.grasse);
+
+    Ok(())
+}
+
+// Renders the payslips into one PDF, each starting on a new page
+pub fn rendre_bulletins(employeur: &Employeur, bulletins: &[BulletinPaie]) -> Result<Vec<u8>, DocumentError> {
+    if bulletins.is_empty() {
+        return Err(DocumentError::Empty);
+    }
+
+    let (document, page, calque) = PdfDocument::new("Bulletin de paie", Mm(LARGEUR_PAGE), Mm(HAUTEUR_PAGE), "Bulletin");
+    let polices = Polices {
+        normale: document.add_builtin_font(BuiltinFont::Helvetica)?,
+        grasse: document.add_builtin_font(BuiltinFont::HelveticaBold)?,
+    };
+
+    for (index, bulletin) in bulletins.iter().enumerate() {
+        let calque = if index == 0 {
+            document.get_page(page).get_layer(calque)
+        } else {
+            nouvelle_page(&document, "Bulletin")
+        };
+        dessiner_bulletin(&document, calque, &polices, employeur, bulletin)?;
+    }
+
+    Ok(document.save_to_bytes()?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn bulletin(nombre_lignes: usize) -> BulletinPaie {
+        BulletinPaie {
+            nom_prenom: "ALAMI Sara".to_string(),
+            nom_specialite: Some("Cardiologie".to_string()),
+            nom_banque: Some("CIH".to_string()),
+            rib: "230780000000000000000012".to_string(),
+            periode: Periode::new(2025, 3).unwrap(),
+            date_paiement: NaiveDate::from_ymd_opt(2025, 3, 31).unwrap(),
+            jours_travail: 30,
+            lignes: (0..nombre_lignes)
+                .map(|rang| LignePaiement {
+                    code: format!("AJ{}", rang),
+                    libelle: format!("Ajustement n°{}", rang + 1),
+                    categorie: if rang % 2 == 0 { "gain" } else { "retenue" }.to_string(),
+                    base: None,
+                    taux: None,
+                    montant: BigDecimal::from(10),
+                })
+                .collect(),
+            salaire_brut: BigDecimal::from(9000),
+            total_retenues: BigDecimal::from(500),
+            montant: BigDecimal::from(8500),
+        }
+    }
+
+    fn nombre_pages(pdf: &[u8]) -> usize {
+        let texte = String::from_utf8_lossy(pdf);
+        texte.matches("/Type/Page").count() - texte.matches("/Type/Pages").count()
+    }
+
+    #[test]
+    fn long_payslips_continue_on_the_next_pages() {
+        let employeur = Employeur::depuis_env();
+
+        let court = rendre_bulletins(&employeur, &[bulletin(12)]).unwrap();
+        assert_eq!(nombre_pages(&court), 1);
+
+        // About 30 lines fit under the header of the first page, 40 on the next ones
+        let long = rendre_bulletins(&employeur, &[bulletin(80)]).unwrap();
+        assert_eq!(nombre_pages(&long), 3);
+
+        // The following payslip still starts on a page of its own
+        let lot = rendre_bulletins(&employeur, &[bulletin(80), bulletin(3)]).unwrap();
+        assert_eq!(nombre_pages(&lot), 4);
+    }
+
+    #[test]
+    fn rib_shows_only_the_bank_and_last_digits() {
+        assert_eq!(masquer_rib("230 780 0000000000000000 12"), "230*****************0012");
+        assert_eq!(masquer_rib("1234"), "****");
+    }
+}
@@ -0,0 +1,305 @@
+use crate::documents::{echapper_xml, DocumentError};
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use std::io::{Cursor, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipWriter};
+
+const LARGEUR_MAX: usize = 60;
+
+#[derive(Debug, Clone)]
+pub enum Cellule {
+    Texte(String),
+    Entier(i64),
+    Montant(BigDecimal),
+    Date(NaiveDate),
+    Vide,
+}
+
+impl Cellule {
+    fn largeur(&self) -> usize {
+        match self {
+            Cellule::Texte(texte) => texte.chars().count(),
+            Cellule::Entier(valeur) => valeur.to_string().len(),
+            Cellule::Montant(valeur) => valeur.round(2).to_string().len() + 4,
+            Cellule::Date(_) => 10,
+            Cellule::Vide => 0,
+        }
+    }
+}
+
+// A plain table: optional title above the header row, data rows, then total
+// rows in bold
+#[derive(Debug, Clone, Default)]
+pub struct Feuille {
+    pub nom: String,
+    pub titre: Option<String>,
+    pub entetes: Vec<String>,
+    pub lignes: Vec<Vec<Cellule>>,
+    pub totaux: Vec<Vec<Cellule>>,
+}
+
+// Style indexes of STYLES: plain, then bold, for text, integers, amounts and dates
+const TITRE: usize = 1;
+const ENTETE: usize = 2;
+const TEXTE: usize = 3;
+const DECALAGE_GRAS: usize = 4;
+
+const STYLES: &str = r#"<?xml version="1.0" encoding="UTF-8" standalone="yes"?>
+<styleSheet xmlns="http://schemas.openxmlformats.org/spreadsheetml/2006/main"><numFmts count="1"><numFmt numFmtId="164" formatCode="dd/mm/yyyy"/></numFmts><fonts count="3"><font><sz val="11"/><name val="Calibri"/><family val="2"/></font><font><b/><sz val="11"/><name val="Calibri"/><family val="2"/></font><font><b/><sz val="14"/><name val="Calibri"/><family val="2"/></font></fonts><fills count="3"><fill><patternFill patternType="none"/></fill><fill><patternFill patternType="gray125"/></fill><fill><patternFill patternType="solid"><fgColor rgb="FFD9D9D9"/><bgColor indexed="64"/></patternFill></fill></fills><borders count="2"><border><left/><right/><top/><bottom/><diagonal/></border><border><left style="thin"><color indexed="64"/></left><right style="thin"><color indexed="64"/></right><top style="thin"><color indexed="64"/></top><bottom style="thin"><color indexed="64"/></bottom><diagonal/></border></borders><cellStyleXfs count="1"><xf numFmtId="0" fontId="0" fillId="0" borderId="0"/></cellStyleXfs><cellXfs count="11"><xf numFmtId="0" fontId="0" fillId="0" borderId="0" xfId="0"/><xf numFmtId="0" fontId="2" fillId="0" borderId="0" xfId="0" applyFont="1"/><xf numFmtId="0" fontId="1" fillId="2" borderId="1" xfId="0" applyFont="1" applyFill="1" applyBorder="1" applyAlignment="1"><alignment horizontal="center" vertical="center" wrapText="1"/></xf><xf numFmtId="0" fontId="0" fillId="0" borderId="1" xfId="0" applyBorder="1"/><xf numFmtId="1" fontId="0" fillId="0" borderId="1" xfId="0" applyNumberFormat="1" applyBorder="1"/><xf numFmtId="4" fontId="0" fillId="0" borderId="1" xfId="0" applyNumberFormat="1" applyBorder="1"/><xf numFmtId="164" fontId="0" fillId="0" borderId="1" xfId="0" applyNumberFormat="1" applyBorder="1"/><xf numFmtId="0" fontId="1" fillId="2" borderId="1" xfId="0" applyFont="1" applyFill="1" applyBorder="1"/><xf numFmtId="1" fontId="1" fillId="2" borderId="1" xfId="0" applyNumberFormat="1" applyFont="1" applyFill="1" applyBorder="1"/><xf numFmtId="4" fontId="1" fillId="2" borderId="1" xfId="0" applyNumberFormat="1" applyFont="1" applyFill="1" applyBorder="1"/><xf numFmtId="164" fontId="1" fillId="2" borderId="1" xfId="0" applyNumberFormat="1" applyFont="1" applyFill="1" applyBorder="1"/></cellXfs><cellStyles count="1"><cellStyle name="Normal" xfId="0" builtinId="0"/></cellStyles></styleSheet>"#;
+
+fn colonne(mut index: usize) -> String {
+    let mut nom = String::new();
+    loop {
+        nom.insert(0, (b'A' + (index % 26) as u8) as char);
+        if index < 26 {
+            return nom;
+        }
+        index = index / 26 - 1;
+    }
+}
+
+fn cellule(reference: &str, valeur: &Cellule, gras: bool) -> String {
+    let decalage = if gras { DECALAGE_GRAS } else { 0 };
+    match valeur {
+        Cellule::Texte(texte) => format!(
+            "<c r=\"{}\" s=\"{}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+            reference,
+            TEXTE + decalage,
+            echapper_xml(texte)
+        ),
+        Cellule::Entier(valeur) => format!("<c r=\"{}\" s=\"{}\"><v>{}</v></c>", reference, TEXTE + 1 + decalage, valeur),
+        Cellule::Montant(valeur) => format!(
+            "<c r=\"{}\" s=\"{}\"><v>{}</v></c>",
+            reference,
+            TEXTE + 2 + decalage,
+            valeur.round(2).with_scale(2)
+        ),
+        // Excel counts days from 1899-12-30
+        Cellule::Date(date) => format!(
+            "<c r=\"{}\" s=\"{}\"><v>{}</v></c>",
+            reference,
+            TEXTE + 3 + decalage,
+            (*date - NaiveDate::from_ymd_opt(1899, 12, 30).unwrap_or_default()).num_days()
+        ),
+        Cellule::Vide => format!("<c r=\"{}\" s=\"{}\"/>", reference, TEXTE + decalage),
+    }
+}
+
+fn rendre_feuille(feuille: &Feuille) -> String {
+    let mut lignes = String::new();
+    let mut numero = 1;
+
+    if let Some(titre) = &feuille.titre {
+        lignes.push_str(&format!(
+            "<row r=\"1\"><c r=\"A1\" s=\"{}\" t=\"inlineStr\"><is><t>{}</t></is></c></row>",
+            TITRE,
+            echapper_xml(titre)
+        ));
+        numero = 3;
+    }
+
+    let entetes: String = feuille
+        .entetes
+        .iter()
+        .enumerate()
+        .map(|(index, entete)| {
+            format!(
+                "<c r=\"{}{}\" s=\"{}\" t=\"inlineStr\"><is><t>{}</t></is></c>",
+                colonne(index),
+                numero,
+                ENTETE,
+                echapper_xml(entete)
+            )
+        })
+        .collect();
+    lignes.push_str(&format!("<row r=\"{}\">{}</row>", numero, entetes));
+    let ligne_entete = numero;
+
+    for (ligne, gras) in feuille
+        .lignes
+        .iter()
+        .map(|ligne| (ligne, false))
+        .chain(feuille.totaux.iter().map(|ligne| (ligne, true)))
+    {
+        numero += 1;
+        let cellules: String = ligne
+            .iter()
+            .enumerate()
+            .map(|(index, valeur)| cellule(&format!("{}{}", colonne(index), numero), valeur, gras))
+            .collect();
+        lignes.push_str(&format!("<row r=\"{}\">{}</row>", numero, cellules));
+    }
+
+    let nombre_colonnes = feuille
+        .lignes
+        .iter()
+        .chain(&feuille.totaux)
+        .map(Vec::len)
+        .chain(std::iter::once(feuille.entetes.len()))
+        .max()
+        .unwrap_or(0);
+    let colonnes: String = (0..nombre_colonnes)
+        .map(|index| {
+            let largeur = feuille
+                .lignes
+                .iter()
+                .chain(&feuille.totaux)
+                .filter_map(|ligne| ligne.get(index).map(Cellule::largeur))
+                .chain(feuille.entetes.get(index).map(|entete| entete.chars().count()))
+                .max()
+                .unwrap_or(0)
+                .clamp(8, LARGEUR_MAX);
+            format!("<col min=\"{n}\" max=\"{n}\" width=\"{}\" customWidth=\"1\"/>", largeur + 2, n = index + 1)
+        })
+        .collect();
+
+    // The header row stays visible while scrolling
+    format!(
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <worksheet xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\">\
+         <sheetViews><sheetView workbookViewId=\"0\"><pane ySplit=\"{}\" topLeftCell=\"A{}\" activePane=\"bottomLeft\" state=\"frozen\"/></sheetView></sheetViews>\
+         {}<sheetData>{}</sheetData></worksheet>",
+        ligne_entete,
+        ligne_entete + 1,
+        if colonnes.is_empty() { String::new() } else { format!("<cols>{}</cols>", colonnes) },
+        lignes
+    )
+}
+
+// Sheet names are at most 31 characters, without []:*?/\, and unique
+fn noms_feuilles(feuilles: &[Feuille]) -> Vec<String> {
+    let mut noms: Vec<String> = Vec::new();
+    for (index, feuille) in feuilles.iter().enumerate() {
+        let mut nom: String = feuille
+            .nom
+            .chars()
+            .map(|c| if "[]:*?/\\".contains(c) { '_' } else { c })
+            .take(31)
+            .collect();
+        if nom.trim().is_empty() || noms.iter().any(|autre| autre.eq_ignore_ascii_case(&nom)) {
+            nom = format!("Feuille{}", index + 1);
+        }
+        noms.push(nom);
+    }
+    noms
+}
+
+// A minimal XLSX workbook with one sheet per `Feuille`
+pub fn rendre_classeur(feuilles: &[Feuille]) -> Result<Vec<u8>, DocumentError> {
+    if feuilles.is_empty() {
+        return Err(DocumentError::Empty);
+    }
+
+    let noms = noms_feuilles(feuilles);
+    let mut sortie = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let types: String = (1..=feuilles.len())
+        .map(|n| format!("<Override PartName=\"/xl/worksheets/sheet{}.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.worksheet+xml\"/>", n))
+        .collect();
+    sortie.start_file("[Content_Types].xml", options)?;
+    write!(
+        sortie,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <Types xmlns=\"http://schemas.openxmlformats.org/package/2006/content-types\">\
+         <Default Extension=\"rels\" ContentType=\"application/vnd.openxmlformats-package.relationships+xml\"/>\
+         <Default Extension=\"xml\" ContentType=\"application/xml\"/>\
+         <Override PartName=\"/xl/workbook.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.sheet.main+xml\"/>\
+         <Override PartName=\"/xl/styles.xml\" ContentType=\"application/vnd.openxmlformats-officedocument.spreadsheetml.styles+xml\"/>\
+         {}</Types>",
+        types
+    )?;
+
+    sortie.start_file("_rels/.rels", options)?;
+    sortie.write_all(
+        b"<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+          <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+          <Relationship Id=\"rId1\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/officeDocument\" Target=\"xl/workbook.xml\"/>\
+          </Relationships>",
+    )?;
+
+    let entrees: String = noms
+        .iter()
+        .enumerate()
+        .map(|(index, nom)| format!("<sheet name=\"{}\" sheetId=\"{n}\" r:id=\"rId{n}\"/>", echapper_xml(nom), n = index + 1))
+        .collect();
+    sortie.start_file("xl/workbook.xml", options)?;
+    write!(
+        sortie,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <workbook xmlns=\"http://schemas.openxmlformats.org/spreadsheetml/2006/main\" xmlns:r=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships\">\
+         <sheets>{}</sheets></workbook>",
+        entrees
+    )?;
+
+    let relations: String = (1..=feuilles.len())
+        .map(|n| format!("<Relationship Id=\"rId{n}\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/worksheet\" Target=\"worksheets/sheet{n}.xml\"/>", n = n))
+        .collect();
+    sortie.start_file("xl/_rels/workbook.xml.rels", options)?;
+    write!(
+        sortie,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\" standalone=\"yes\"?>\n\
+         <Relationships xmlns=\"http://schemas.openxmlformats.org/package/2006/relationships\">\
+         {}<Relationship Id=\"rId{}\" Type=\"http://schemas.openxmlformats.org/officeDocument/2006/relationships/styles\" Target=\"styles.xml\"/>\
+         </Relationships>",
+        relations,
+        feuilles.len() + 1
+    )?;
+
+    sortie.start_file("xl/styles.xml", options)?;
+    sortie.write_all(STYLES.as_bytes())?;
+
+    for (index, feuille) in feuilles.iter().enumerate() {
+        sortie.start_file(format!("xl/worksheets/sheet{}.xml", index + 1), options)?;
+        sortie.write_all(rendre_feuille(feuille).as_bytes())?;
+    }
+
+    Ok(sortie.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calamine::{Data, Reader, Xlsx};
+    use std::str::FromStr;
+
+    #[test]
+    fn column_names_go_past_z() {
+        assert_eq!(colonne(0), "A");
+        assert_eq!(colonne(25), "Z");
+        assert_eq!(colonne(26), "AA");
+        assert_eq!(colonne(701), "ZZ");
+        assert_eq!(colonne(702), "AAA");
+    }
+
+    #[test]
+    fn workbook_reads_back_with_title_rows_and_totals() {
+        let feuilles = vec![
+            Feuille {
+                nom: "Récap: juin/2024".to_string(),
+                titre: Some("Récapitulatif".to_string()),
+                entetes: vec!["Banque".to_string(), "Nombre".to_string(), "Montant".to_string(), "Date".to_string()],
+                lignes: vec![vec![
+                    Cellule::Texte("Banque <A> & fils".to_string()),
+                    Cellule::Entier(2),
+                    Cellule::Montant(BigDecimal::from_str("1234.5").unwrap()),
+                    Cellule::Date(NaiveDate::from_ymd_opt(2024, 6, 28).unwrap()),
+                ]],
+                totaux: vec![vec![Cellule::Texte("TOTAL".to_string()), Cellule::Entier(2), Cellule::Vide]],
+            },
+            Feuille { nom: "Vide".to_string(), ..Default::default() },
+        ];
+
+        let mut classeur: Xlsx<_> = Xlsx::new(Cursor::new(rendre_classeur(&feuilles).unwrap())).unwrap();
+        assert_eq!(classeur.sheet_names(), vec!["Récap_ juin_2024".to_string(), "Vide".to_string()]);
+
+        let feuille = classeur.worksheet_range("Récap_ juin_2024").unwrap();
+        assert_eq!(feuille.get_value((0, 0)), Some(&Data::String("Récapitulatif".to_string())));
+        assert_eq!(feuille.get_value((2, 2)), Some(&Data::String("Montant".to_string())));
+        assert_eq!(feuille.get_value((3, 0)), Some(&Data::String("Banque <A> & fils".to_string())));
+        assert_eq!(feuille.get_value((3, 2)), Some(&Data::Float(1234.5)));
+        assert_eq!(feuille.get_value((4, 1)), Some(&Data::Float(2.0)));
+        assert!(matches!(feuille.get_value((3, 3)), Some(Data::DateTime(date)) if date.as_f64() == 45471.0));
+    }
+}
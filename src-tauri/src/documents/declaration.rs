@@ -0,0 +1,210 @@
+use crate::documents::{echapper_xml, Cellule, DocumentError, Employeur, Feuille};
+use crate::models::AnomalieDeclaration;
+use crate::payroll::LigneDeclaration;
+use bigdecimal::{BigDecimal, Zero};
+use std::fmt::Write;
+
+fn montant(valeur: &BigDecimal) -> String {
+    valeur.round(2).with_scale(2).to_string()
+}
+
+fn total(lignes: &[LigneDeclaration], valeur: fn(&LigneDeclaration) -> BigDecimal) -> BigDecimal {
+    lignes.iter().fold(BigDecimal::zero(), |total, ligne| total + valeur(ligne))
+}
+
+// One element per line, indented under its parent
+fn balise(xml: &mut String, retrait: usize, nom: &str, valeur: &str) {
+    let _ = writeln!(xml, "{:retrait$}<{nom}>{valeur}</{nom}>", "");
+}
+
+// Annual declaration of salaries (état 9421) in the layout of the tax office's
+// electronic filing: the employer, the yearly totals, then one block per
+// permanent employee. Optional identifiers we do not hold are left out.
+pub fn rendre_declaration_xml(employeur: &Employeur, annee: i32, lignes: &[LigneDeclaration]) -> Result<String, DocumentError> {
+    if lignes.is_empty() {
+        return Err(DocumentError::Empty);
+    }
+
+    let effectif = lignes.len().to_string();
+    let mut xml = String::from("<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<TraitementEtSalaire>\n");
+    balise(&mut xml, 2, "identifiantFiscal", &echapper_xml(employeur.identifiant_fiscal.as_deref().unwrap_or_default()));
+    balise(&mut xml, 2, "raisonSociale", &echapper_xml(&employeur.nom));
+    balise(&mut xml, 2, "exerciceFiscalDu", &format!("{}-01-01", annee));
+    balise(&mut xml, 2, "exerciceFiscalAu", &format!("{}-12-31", annee));
+    balise(&mut xml, 2, "annee", &annee.to_string());
+    balise(&mut xml, 2, "adresse", &echapper_xml(&employeur.adresse));
+    balise(&mut xml, 2, "effectifTotal", &effectif);
+    balise(&mut xml, 2, "nbrPersoPermanent", &effectif);
+    balise(&mut xml, 2, "nbrPersoOccasionnel", "0");
+    balise(&mut xml, 2, "nbrStagiaires", "0");
+    balise(&mut xml, 2, "totalMtRevenuBrutImposablePP", &montant(&total(lignes, |l| l.brut_imposable.clone())));
+    balise(&mut xml, 2, "totalMtRevenuNetImposablePP", &montant(&total(lignes, |l| l.net_imposable.clone())));
+    balise(&mut xml, 2, "totalMtTotalDeductionPP", &montant(&total(lignes, LigneDeclaration::total_deductions)));
+    balise(&mut xml, 2, "totalMtIrPrelevePP", &montant(&total(lignes, |l| l.ir.clone())));
+    xml.push_str("  <listPersonnelPermanent>\n");
+
+    for ligne in lignes {
+        xml.push_str("    <PersonnelPermanent>\n");
+        balise(&mut xml, 6, "nom", &echapper_xml(&ligne.nom));
+        balise(&mut xml, 6, "prenom", &echapper_xml(&ligne.prenom));
+        balise(&mut xml, 6, "adressePersonnelle", &echapper_xml(ligne.salarie.adresse.as_deref().unwrap_or_default()));
+        balise(&mut xml, 6, "numCNI", &echapper_xml(ligne.salarie.cin.as_deref().unwrap_or_default()));
+        balise(&mut xml, 6, "salaireBaseAnnuel", &montant(&ligne.salaire_base));
+        balise(&mut xml, 6, "mtBrutTraitementSalaire", &montant(&ligne.brut));
+        balise(&mut xml, 6, "periode", &ligne.jours.to_string());
+        balise(&mut xml, 6, "mtExonere", &montant(&ligne.exonere));
+        balise(&mut xml, 6, "mtEcheances", "0.00");
+        balise(&mut xml, 6, "nbrReductions", &ligne.salarie.nombre_charges.to_string());
+        balise(&mut xml, 6, "mtIndemnite", "0.00");
+        balise(&mut xml, 6, "mtAvantages", "0.00");
+        balise(&mut xml, 6, "mtRevenuBrutImposable", &montant(&ligne.brut_imposable));
+        balise(&mut xml, 6, "mtFraisProfess", &montant(&ligne.frais_professionnels));
+        balise(&mut xml, 6, "mtCotisationAssur", &montant(&ligne.cotisations));
+        balise(&mut xml, 6, "mtAutresRetenues", &montant(&ligne.autres_retenues));
+        balise(&mut xml, 6, "mtRevenuNetImposable", &montant(&ligne.net_imposable));
+        balise(&mut xml, 6, "mtTotalDeduction", &montant(&ligne.total_deductions()));
+        balise(&mut xml, 6, "irPreleve", &montant(&ligne.ir));
+        balise(&mut xml, 6, "casSportif", "false");
+        xml.push_str("    </PersonnelPermanent>\n");
+    }
+
+    xml.push_str("  </listPersonnelPermanent>\n</TraitementEtSalaire>\n");
+    Ok(xml)
+}
+
+// The same declaration as a workbook to check before filing, with the
+// validation report on a second sheet when there is something to report
+pub fn feuilles_declaration(
+    employeur: &Employeur,
+    annee: i32,
+    lignes: &[LigneDeclaration],
+    anomalies: &[AnomalieDeclaration],
+) -> Vec<Feuille> {
+    let montants = |ligne: &LigneDeclaration| {
+        vec![
+            ligne.salaire_base.clone(),
+            ligne.brut.clone(),
+            ligne.exonere.clone(),
+            ligne.brut_imposable.clone(),
+            ligne.frais_professionnels.clone(),
+            ligne.cotisations.clone(),
+            ligne.autres_retenues.clone(),
+            ligne.net_imposable.clone(),
+            ligne.ir.clone(),
+        ]
+    };
+
+    let mut totaux = vec![Cellule::Texte("TOTAL".to_string()), Cellule::Vide, Cellule::Vide, Cellule::Vide, Cellule::Vide, Cellule::Vide];
+    let colonnes = lignes.iter().map(montants).fold(vec![BigDecimal::zero(); 9], |totaux, ligne| {
+        totaux.into_iter().zip(ligne).map(|(total, montant)| total + montant).collect()
+    });
+    totaux.extend(colonnes.into_iter().map(Cellule::Montant));
+
+    let mut feuilles = vec![Feuille {
+        nom: "Etat 9421".to_string(),
+        titre: Some(format!("État des traitements et salaires {} - {}", annee, employeur.nom)),
+        entetes: [
+            "Nom",
+            "Prénom",
+            "CIN",
+            "Adresse",
+            "Charges de famille",
+            "Jours",
+            "Salaire de base",
+            "Brut",
+            "Exonéré",
+            "Brut imposable",
+            "Frais professionnels",
+            "Cotisations",
+            "Autres retenues",
+            "Net imposable",
+            "IR retenu",
+        ]
+        .iter()
+        .map(|entete| entete.to_string())
+        .collect(),
+        lignes: lignes
+            .iter()
+            .map(|ligne| {
+                let mut cellules = vec![
+                    Cellule::Texte(ligne.nom.clone()),
+                    Cellule::Texte(ligne.prenom.clone()),
+                    ligne.salarie.cin.clone().map_or(Cellule::Vide, Cellule::Texte),
+                    ligne.salarie.adresse.clone().map_or(Cellule::Vide, Cellule::Texte),
+                    Cellule::Entier(ligne.salarie.nombre_charges as i64),
+                    Cellule::Entier(ligne.jours as i64),
+                ];
+                cellules.extend(montants(ligne).into_iter().map(Cellule::Montant));
+                cellules
+            })
+            .collect(),
+        totaux: vec![totaux],
+    }];
+
+    if !anomalies.is_empty() {
+        feuilles.push(Feuille {
+            nom: "Anomalies".to_string(),
+            titre: Some(format!("Contrôle de la déclaration {}", annee)),
+            entetes: ["Résident", "Champ", "Anomalie", "Bloquante"].iter().map(|e| e.to_string()).collect(),
+            lignes: anomalies
+                .iter()
+                .map(|anomalie| {
+                    vec![
+                        Cellule::Texte(anomalie.nom_prenom.clone()),
+                        Cellule::Texte(anomalie.champ.clone()),
+                        Cellule::Texte(anomalie.message.clone()),
+                        Cellule::Texte(if anomalie.bloquante { "Oui" } else { "Non" }.to_string()),
+                    ]
+                })
+                .collect(),
+            totaux: Vec::new(),
+        });
+    }
+
+    feuilles
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::payroll::{declarer, ElementDeclare, SalarieDeclare};
+    use std::str::FromStr;
+
+    fn ligne(nom_prenom: &str, cin: &str, brut: &str, ir: &str) -> LigneDeclaration {
+        declarer(
+            SalarieDeclare {
+                id_resident: 1,
+                nom_prenom: nom_prenom.to_string(),
+                cin: Some(cin.to_string()),
+                adresse: Some("Rue 5 & 7, Fès".to_string()),
+                nombre_charges: 1,
+            },
+            &[ElementDeclare {
+                brut_imposable: BigDecimal::from_str(brut).unwrap(),
+                ir: BigDecimal::from_str(ir).unwrap(),
+                jours: 360,
+                ..Default::default()
+            }],
+        )
+    }
+
+    #[test]
+    fn xml_lists_every_employee_under_the_yearly_totals() {
+        let employeur = Employeur {
+            identifiant_fiscal: Some("1234567".to_string()),
+            ..Employeur::depuis_env()
+        };
+        let lignes = vec![ligne("ALAMI Sara", "AB123456", "120000", "9000.5"), ligne("BENNANI Omar", "C9876", "100000", "7000")];
+
+        let xml = rendre_declaration_xml(&employeur, 2025, &lignes).unwrap();
+        assert!(xml.contains("<identifiantFiscal>1234567</identifiantFiscal>"));
+        assert!(xml.contains("<exerciceFiscalAu>2025-12-31</exerciceFiscalAu>"));
+        assert!(xml.contains("<totalMtRevenuBrutImposablePP>220000.00</totalMtRevenuBrutImposablePP>"));
+        assert!(xml.contains("<totalMtIrPrelevePP>16000.50</totalMtIrPrelevePP>"));
+        assert_eq!(xml.matches("<PersonnelPermanent>").count(), 2);
+        assert!(xml.contains("<nom>ALAMI</nom>\n      <prenom>Sara</prenom>"));
+        assert!(xml.contains("<adressePersonnelle>Rue 5 &amp; 7, Fès</adressePersonnelle>"));
+
+        assert!(matches!(rendre_declaration_xml(&employeur, 2025, &[]), Err(DocumentError::Empty)));
+    }
+}
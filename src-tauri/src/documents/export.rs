@@ -0,0 +1,376 @@
+use crate::documents::{rendre_classeur, Cellule, DocumentError, Feuille};
+use crate::models::{FiltreListe, PaiementMensuel, RappelAnnuel, Resident, Specialite};
+use bigdecimal::BigDecimal;
+use chrono::{Datelike, NaiveDate};
+
+// A row of a list page that can be exported column by column
+pub trait Exportable {
+    // Key (the field name sent to the frontend) and header of every column, in default order
+    const COLONNES: &'static [(&'static str, &'static str)];
+
+    // Name matched by the search filter
+    fn nom(&self) -> &str;
+
+    // Date matched by the year and month filters
+    fn date(&self) -> Option<NaiveDate> {
+        None
+    }
+
+    fn cellule(&self, colonne: &str) -> Cellule;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FormatExport {
+    Csv,
+    Xlsx,
+}
+
+impl FormatExport {
+    pub fn parse(format: &str) -> Option<Self> {
+        match format.to_lowercase().as_str() {
+            "csv" => Some(FormatExport::Csv),
+            "xlsx" => Some(FormatExport::Xlsx),
+            _ => None,
+        }
+    }
+}
+
+fn texte(valeur: Option<&str>) -> Cellule {
+    match valeur {
+        Some(valeur) if !valeur.is_empty() => Cellule::Texte(valeur.to_string()),
+        _ => Cellule::Vide,
+    }
+}
+
+fn montant(valeur: Option<&BigDecimal>) -> Cellule {
+    valeur.map_or(Cellule::Vide, |valeur| Cellule::Montant(valeur.clone()))
+}
+
+fn date(valeur: Option<NaiveDate>) -> Cellule {
+    valeur.map_or(Cellule::Vide, Cellule::Date)
+}
+
+// Same labels as the payments page
+fn statut_virement(statut: &str) -> Cellule {
+    let libelle = match statut {
+        "en_attente" => "En attente",
+        "paye" => "Payé",
+        "rejete" => "Rejeté",
+        "reemis" => "Réémis",
+        autre => autre,
+    };
+    Cellule::Texte(libelle.to_string())
+}
+
+impl Exportable for Resident {
+    const COLONNES: &'static [(&'static str, &'static str)] = &[
+        ("id_resident", "N°"),
+        ("nom_prenom", "Nom et prénom"),
+        ("nom_specialite", "Spécialité"),
+        ("date_debut", "Date de début"),
+        ("date_fin", "Date de fin"),
+        ("nombre_enfants", "Nombre d'enfants"),
+        ("rib", "RIB"),
+        ("nom_banque", "Banque"),
+        ("cin", "CIN"),
+        ("adresse", "Adresse"),
+    ];
+
+    fn nom(&self) -> &str {
+        &self.nom_prenom
+    }
+
+    fn cellule(&self, colonne: &str) -> Cellule {
+        match colonne {
+            "id_resident" => Cellule::Entier(self.id_resident as i64),
+            "nom_prenom" => texte(Some(&self.nom_prenom)),
+            "nom_specialite" => texte(self.nom_specialite.as_deref()),
+            "date_debut" => date(Some(self.date_debut)),
+            "date_fin" => date(self.date_fin),
+            "nombre_enfants" => Cellule::Entier(self.nombre_enfants as i64),
+            "rib" => texte(Some(&self.rib)),
+            "nom_banque" => texte(self.nom_banque.as_deref()),
+            "cin" => texte(self.cin.as_deref()),
+            "adresse" => texte(self.adresse.as_deref()),
+            _ => Cellule::Vide,
+        }
+    }
+}
+
+impl Exportable for Specialite {
+    const COLONNES: &'static [(&'static str, &'static str)] = &[
+        ("id_specialite", "N°"),
+        ("nom", "Spécialité"),
+        ("nombre_annees", "Durée (années)"),
+    ];
+
+    fn nom(&self) -> &str {
+        &self.nom
+    }
+
+    fn cellule(&self, colonne: &str) -> Cellule {
+        match colonne {
+            "id_specialite" => Cellule::Entier(self.id_specialite as i64),
+            "nom" => texte(Some(&self.nom)),
+            "nombre_annees" => Cellule::Entier(self.nombre_annees as i64),
+            _ => Cellule::Vide,
+        }
+    }
+}
+
+impl Exportable for PaiementMensuel {
+    const COLONNES: &'static [(&'static str, &'static str)] = &[
+        ("id_paiement", "N°"),
+        ("nom_resident", "Résident"),
+        ("date_paiement", "Date de paiement"),
+        ("jours_travail", "Jours travaillés"),
+        ("salaire_base", "Salaire de base"),
+        ("indemnite_fonction", "Indemnité de fonction"),
+        ("indemnite_logement", "Indemnité de logement"),
+        ("indemnite_transport", "Indemnité de transport"),
+        ("allocations_familiales", "Allocations familiales"),
+        ("salaire_brut", "Salaire brut"),
+        ("total_retenues", "Total des retenues"),
+        ("montant", "Net à payer"),
+        ("rib", "RIB"),
+        ("nom_banque", "Banque"),
+        ("statut_virement", "Virement"),
+        ("motif_rejet", "Motif du rejet"),
+    ];
+
+    fn nom(&self) -> &str {
+        self.nom_resident.as_deref().unwrap_or_default()
+    }
+
+    fn date(&self) -> Option<NaiveDate> {
+        Some(self.date_paiement)
+    }
+
+    fn cellule(&self, colonne: &str) -> Cellule {
+        match colonne {
+            "id_paiement" => Cellule::Entier(self.id_paiement as i64),
+            "nom_resident" => texte(self.nom_resident.as_deref()),
+            "date_paiement" => date(Some(self.date_paiement)),
+            "jours_travail" => Cellule::Entier(self.jours_travail as i64),
+            "salaire_base" => montant(self.salaire_base.as_ref()),
+            "indemnite_fonction" => montant(self.indemnite_fonction.as_ref()),
+            "indemnite_logement" => montant(self.indemnite_logement.as_ref()),
+            "indemnite_transport" => montant(self.indemnite_transport.as_ref()),
+            "allocations_familiales" => montant(self.allocations_familiales.as_ref()),
+            "salaire_brut" => montant(self.salaire_brut.as_ref()),
+            "total_retenues" => montant(self.total_retenues.as_ref()),
+            "montant" => montant(Some(&self.montant)),
+            "rib" => texte(Some(&self.rib)),
+            "nom_banque" => texte(self.nom_banque.as_deref()),
+            "statut_virement" => statut_virement(&self.statut_virement),
+            "motif_rejet" => texte(self.motif_rejet.as_deref()),
+            _ => Cellule::Vide,
+        }
+    }
+}
+
+impl Exportable for RappelAnnuel {
+    const COLONNES: &'static [(&'static str, &'static str)] = &[
+        ("id_rappel", "N°"),
+        ("nom_resident", "Résident"),
+        ("exercice", "Exercice"),
+        ("duree_rappel", "Durée du rappel"),
+        ("montant", "Montant"),
+        ("retenue_recouvrement", "Retenue de recouvrement"),
+        ("date_generation", "Date de génération"),
+        ("rib", "RIB"),
+        ("nom_banque", "Banque"),
+        ("statut_virement", "Virement"),
+        ("motif_rejet", "Motif du rejet"),
+    ];
+
+    fn nom(&self) -> &str {
+        self.nom_resident.as_deref().unwrap_or_default()
+    }
+
+    fn date(&self) -> Option<NaiveDate> {
+        self.date_generation
+    }
+
+    fn cellule(&self, colonne: &str) -> Cellule {
+        match colonne {
+            "id_rappel" => Cellule::Entier(self.id_rappel as i64),
+            "nom_resident" => texte(self.nom_resident.as_deref()),
+            "exercice" => Cellule::Entier(self.exercice as i64),
+            "duree_rappel" => Cellule::Entier(self.duree_rappel as i64),
+            "montant" => montant(Some(&self.montant)),
+            "retenue_recouvrement" => montant(Some(&self.retenue_recouvrement)),
+            "date_generation" => date(self.date_generation),
+            "rib" => texte(Some(&self.rib)),
+            "nom_banque" => texte(self.nom_banque.as_deref()),
+            "statut_virement" => statut_virement(&self.statut_virement),
+            "motif_rejet" => texte(self.motif_rejet.as_deref()),
+            _ => Cellule::Vide,
+        }
+    }
+}
+
+// Same rules as the list pages: case-insensitive search on the name; rows
+// without a date never match a year or month
+pub fn retenir<T: Exportable>(filtre: &FiltreListe, ligne: &T) -> bool {
+    let recherche = filtre.recherche.as_deref().map(str::trim).unwrap_or_default();
+    if !recherche.is_empty() && !ligne.nom().to_lowercase().contains(&recherche.to_lowercase()) {
+        return false;
+    }
+
+    match (filtre.annee, filtre.mois, ligne.date()) {
+        (None, None, _) => true,
+        (_, _, None) => false,
+        (annee, mois, Some(date)) => {
+            annee.is_none_or(|annee| date.year() == annee) && mois.is_none_or(|mois| date.month() == mois)
+        }
+    }
+}
+
+// Columns to write, all of them when none is chosen
+fn colonnes_choisies<T: Exportable>(colonnes: &[String]) -> Result<Vec<(&'static str, &'static str)>, DocumentError> {
+    if colonnes.is_empty() {
+        return Ok(T::COLONNES.to_vec());
+    }
+
+    colonnes
+        .iter()
+        .map(|colonne| {
+            T::COLONNES
+                .iter()
+                .find(|(cle, _)| cle == colonne)
+                .copied()
+                .ok_or_else(|| DocumentError::ColonneInconnue(colonne.clone()))
+        })
+        .collect()
+}
+
+// Plain digits for analysis, no thousands separator; with `virgule` the decimal
+// separator is a comma and fields are separated by ';' as Excel expects in French
+fn valeur_csv(cellule: &Cellule, virgule: bool) -> String {
+    match cellule {
+        Cellule::Texte(texte) => texte.clone(),
+        Cellule::Entier(valeur) => valeur.to_string(),
+        Cellule::Montant(valeur) => {
+            let texte = valeur.round(2).with_scale(2).to_string();
+            if virgule {
+                texte.replace('.', ",")
+            } else {
+                texte
+            }
+        }
+        Cellule::Date(date) => date.format("%d/%m/%Y").to_string(),
+        Cellule::Vide => String::new(),
+    }
+}
+
+// The rows of `lignes` as a CSV file (UTF-8 with BOM so Excel reads the accents)
+// or an XLSX workbook with one sheet named `titre`
+pub fn rendre_liste<T: Exportable>(
+    titre: &str,
+    lignes: &[&T],
+    colonnes: &[String],
+    format: FormatExport,
+    virgule: bool,
+) -> Result<Vec<u8>, DocumentError> {
+    let colonnes = colonnes_choisies::<T>(colonnes)?;
+    let entetes: Vec<String> = colonnes.iter().map(|(_, entete)| entete.to_string()).collect();
+    let cellules = lignes.iter().map(|ligne| colonnes.iter().map(|(cle, _)| ligne.cellule(cle)).collect::<Vec<_>>());
+
+    match format {
+        FormatExport::Xlsx => rendre_classeur(&[Feuille {
+            nom: titre.to_string(),
+            titre: None,
+            entetes,
+            lignes: cellules.collect(),
+            totaux: Vec::new(),
+        }]),
+        FormatExport::Csv => {
+            let mut sortie = "\u{feff}".as_bytes().to_vec();
+            let mut ecrivain = csv::WriterBuilder::new()
+                .delimiter(if virgule { b';' } else { b',' })
+                .terminator(csv::Terminator::CRLF)
+                .from_writer(&mut sortie);
+
+            ecrivain.write_record(&entetes).map_err(std::io::Error::from)?;
+            for ligne in cellules {
+                ecrivain
+                    .write_record(ligne.iter().map(|cellule| valeur_csv(cellule, virgule)))
+                    .map_err(std::io::Error::from)?;
+            }
+            ecrivain.flush()?;
+            drop(ecrivain);
+
+            Ok(sortie)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn specialite(id: i32, nom: &str) -> Specialite {
+        Specialite { id_specialite: id, nom: nom.to_string(), nombre_annees: 4 }
+    }
+
+    fn rappel(nom: &str, montant: &str, date: Option<NaiveDate>) -> RappelAnnuel {
+        RappelAnnuel {
+            id_rappel: 1,
+            id_resident: Some(1),
+            exercice: 2023,
+            duree_rappel: 12,
+            montant: BigDecimal::from_str(montant).unwrap(),
+            nom_resident: Some(nom.to_string()),
+            rib: "007780000000000000000112".to_string(),
+            nom_banque: Some("Banque A".to_string()),
+            date_generation: date,
+            retenue_recouvrement: BigDecimal::from(0),
+            statut_virement: "rejete".to_string(),
+            motif_rejet: Some("AC04 - Compte clôturé".to_string()),
+        }
+    }
+
+    #[test]
+    fn csv_has_bom_and_french_decimals() {
+        let rappel = rappel("Aït; \"Sara\"", "1234.5", NaiveDate::from_ymd_opt(2024, 3, 1));
+        let colonnes = ["nom_resident", "montant", "date_generation", "statut_virement"].map(String::from);
+
+        let virgule = String::from_utf8(rendre_liste("Rappels", &[&rappel], &colonnes, FormatExport::Csv, true).unwrap()).unwrap();
+        assert_eq!(
+            virgule,
+            "\u{feff}Résident;Montant;Date de génération;Virement\r\n\"Aït; \"\"Sara\"\"\";1234,50;01/03/2024;Rejeté\r\n"
+        );
+
+        let point = String::from_utf8(rendre_liste("Rappels", &[&rappel], &colonnes[..2], FormatExport::Csv, false).unwrap()).unwrap();
+        assert_eq!(point, "\u{feff}Résident,Montant\r\n\"Aït; \"\"Sara\"\"\",1234.50\r\n");
+    }
+
+    #[test]
+    fn columns_default_to_all_and_unknown_ones_are_refused() {
+        let specialites = [specialite(1, "Cardiologie")];
+        let lignes: Vec<&Specialite> = specialites.iter().collect();
+
+        let csv = String::from_utf8(rendre_liste("Spécialités", &lignes, &[], FormatExport::Csv, false).unwrap()).unwrap();
+        assert!(csv.starts_with("\u{feff}N°,Spécialité,Durée (années)\r\n1,Cardiologie,4"));
+
+        let erreur = rendre_liste("Spécialités", &lignes, &["rib".to_string()], FormatExport::Xlsx, false);
+        assert!(matches!(erreur, Err(DocumentError::ColonneInconnue(colonne)) if colonne == "rib"));
+    }
+
+    #[test]
+    fn filters_match_the_list_pages() {
+        let mars = rappel("Sara Alami", "100", NaiveDate::from_ymd_opt(2024, 3, 1));
+        let sans_date = rappel("Sara Alami", "100", None);
+        let filtre = |recherche: Option<&str>, annee, mois| FiltreListe { recherche: recherche.map(String::from), annee, mois };
+
+        assert!(retenir(&filtre(Some("alami"), None, None), &mars));
+        assert!(!retenir(&filtre(Some("bennani"), None, None), &mars));
+        assert!(retenir(&filtre(None, Some(2024), Some(3)), &mars));
+        assert!(!retenir(&filtre(None, Some(2024), Some(4)), &mars));
+        assert!(!retenir(&filtre(None, Some(2024), None), &sans_date));
+        assert!(retenir(&filtre(None, None, None), &sans_date));
+        assert!(retenir(&filtre(Some("card"), None, None), &specialite(1, "Cardiologie")));
+    }
+}
@@ -0,0 +1,421 @@
+use crate::documents::DocumentError;
+use bigdecimal::{BigDecimal, Signed, ToPrimitive};
+
+const UNITES: [&str; 17] = [
+    "zéro", "un", "deux", "trois", "quatre", "cinq", "six", "sept", "huit", "neuf", "dix", "onze", "douze",
+    "treize", "quatorze", "quinze", "seize",
+];
+const DIZAINES: [&str; 7] = ["", "dix", "vingt", "trente", "quarante", "cinquante", "soixante"];
+
+// `accord` is false when the number is directly followed by "mille":
+// "quatre-vingts" and "deux cents" then lose their final s
+fn moins_de_cent(n: u64, accord: bool) -> String {
+    let (dizaine, unite) = ((n / 10) as usize, (n % 10) as usize);
+
+    match n {
+        0..=16 => UNITES[n as usize].to_string(),
+        17..=19 => format!("dix-{}", UNITES[unite]),
+        71 => "soixante et onze".to_string(),
+        70..=79 => format!("soixante-{}", moins_de_cent(n - 60, accord)),
+        80 if accord => "quatre-vingts".to_string(),
+        80 => "quatre-vingt".to_string(),
+        81..=89 => format!("quatre-vingt-{}", UNITES[unite]),
+        90..=99 => format!("quatre-vingt-{}", moins_de_cent(n - 80, accord)),
+        _ => match unite {
+            0 => DIZAINES[dizaine].to_string(),
+            1 => format!("{} et un", DIZAINES[dizaine]),
+            _ => format!("{}-{}", DIZAINES[dizaine], UNITES[unite]),
+        },
+    }
+}
+
+fn moins_de_mille(n: u64, accord: bool) -> String {
+    let (centaine, reste) = (n / 100, n % 100);
+
+    let mut mots = match centaine {
+        0 => String::new(),
+        1 => "cent".to_string(),
+        _ if reste == 0 && accord => format!("{} cents", UNITES[centaine as usize]),
+        _ => format!("{} cent", UNITES[centaine as usize]),
+    };
+
+    if reste > 0 || centaine == 0 {
+        if !mots.is_empty() {
+            mots.push(' ');
+        }
+        mots.push_str(&moins_de_cent(reste, accord));
+    }
+
+    mots
+}
+
+// Traditional French spelling: hyphens inside numbers below one hundred,
+// "et" for 21, 31 ... 71, invariable "mille", plural "millions" and "milliards"
+pub fn nombre_en_lettres(n: u64) -> String {
+    if n == 0 {
+        return UNITES[0].to_string();
+    }
+
+    let mut mots = Vec::new();
+    let mut reste = n;
+
+    for (valeur, nom) in [(1_000_000_000, "milliard"), (1_000_000, "million")] {
+        let nombre = reste / valeur;
+        if nombre > 0 {
+            let pluriel = if nombre > 1 { "s" } else { "" };
+            mots.push(format!("{} {}{}", nombre_en_lettres(nombre), nom, pluriel));
+            reste %= valeur;
+        }
+    }
+
+    match reste / 1000 {
+        0 => {}
+        1 => mots.push("mille".to_string()),
+        milliers => mots.push(format!("{} mille", moins_de_mille(milliers, false))),
+    }
+
+    if !reste.is_multiple_of(1000) {
+        mots.push(moins_de_mille(reste % 1000, true));
+    }
+
+    mots.join(" ")
+}
+
+// Splits an amount into whole dirhams and centimes, rounded to the centime
+fn dirhams_centimes(montant: &BigDecimal) -> Result<(u64, u64), DocumentError> {
+    let absolu = montant.round(2).abs();
+    let hors_limite = || DocumentError::MontantHorsLimite(montant.clone());
+    let dirhams = absolu.with_scale(0).to_u64().ok_or_else(hors_limite)?;
+    let centimes = ((&absolu - BigDecimal::from(dirhams)) * BigDecimal::from(100))
+        .round(0)
+        .to_u64()
+        .ok_or_else(hors_limite)?;
+
+    Ok((dirhams, centimes))
+}
+
+// "cent vingt mille dirhams et cinquante centimes"
+pub fn montant_en_lettres(montant: &BigDecimal) -> Result<String, DocumentError> {
+    let (dirhams, centimes) = dirhams_centimes(montant)?;
+
+    let unite = match dirhams {
+        0 | 1 => "dirham",
+        // "un million de dirhams", but "un million cent dirhams"
+        _ if dirhams.is_multiple_of(1_000_000) => "de dirhams",
+        _ => "dirhams",
+    };
+
+    let mut lettres = if dirhams == 0 && centimes > 0 {
+        String::new()
+    } else {
+        format!("{} {}", nombre_en_lettres(dirhams), unite)
+    };
+
+    if centimes > 0 {
+        if !lettres.is_empty() {
+            lettres.push_str(" et ");
+        }
+        let pluriel = if centimes > 1 { "s" } else { "" };
+        lettres.push_str(&format!("{} centime{}", nombre_en_lettres(centimes), pluriel));
+    }
+
+    if montant.round(2).is_negative() {
+        Ok(format!("moins {}", lettres))
+    } else {
+        Ok(lettres)
+    }
+}
+
+const UNITES_AR: [&str; 11] = [
+    "صفر", "واحد", "اثنان", "ثلاثة", "أربعة", "خمسة", "ستة", "سبعة", "ثمانية", "تسعة", "عشرة",
+];
+const DIZAINES_AR: [&str; 10] = [
+    "", "عشرة", "عشرون", "ثلاثون", "أربعون", "خمسون", "ستون", "سبعون", "ثمانون", "تسعون",
+];
+const CENTAINES_AR: [&str; 10] = [
+    "", "مائة", "مائتان", "ثلاثمائة", "أربعمائة", "خمسمائة", "ستمائة", "سبعمائة", "ثمانمائة", "تسعمائة",
+];
+
+// Forms of a counted noun in Arabic: singular, dual, plural (3 to 10)
+// and accusative singular (11 to 99)
+struct Nom {
+    singulier: &'static str,
+    duel: &'static str,
+    pluriel: &'static str,
+    accusatif: &'static str,
+}
+
+const ALF: Nom = Nom { singulier: "ألف", duel: "ألفان", pluriel: "آلاف", accusatif: "ألفا" };
+const MILYOUN: Nom = Nom { singulier: "مليون", duel: "مليونان", pluriel: "ملايين", accusatif: "مليونا" };
+const MILYAR: Nom = Nom { singulier: "مليار", duel: "ملياران", pluriel: "مليارات", accusatif: "مليارا" };
+const DIRHAM: Nom = Nom { singulier: "درهم", duel: "درهمان", pluriel: "دراهم", accusatif: "درهما" };
+const CENTIME: Nom = Nom { singulier: "سنتيم", duel: "سنتيمان", pluriel: "سنتيمات", accusatif: "سنتيما" };
+
+impl Nom {
+    // The form taken after the number `n`, which is written before it
+    fn apres(&self, n: u64) -> &'static str {
+        match n % 100 {
+            3..=10 => self.pluriel,
+            11..=99 => self.accusatif,
+            _ => self.singulier,
+        }
+    }
+}
+
+// Masculine forms, the units before the tens: "خمسة وعشرون"
+fn moins_de_mille_ar(n: u64) -> String {
+    let (centaine, reste) = ((n / 100) as usize, n % 100);
+    let (dizaine, unite) = ((reste / 10) as usize, (reste % 10) as usize);
+
+    let dizaines = match reste {
+        0 => String::new(),
+        1..=10 => UNITES_AR[reste as usize].to_string(),
+        11 => "أحد عشر".to_string(),
+        12 => "اثنا عشر".to_string(),
+        13..=19 => format!("{} عشر", UNITES_AR[unite]),
+        _ if unite == 0 => DIZAINES_AR[dizaine].to_string(),
+        _ => format!("{} و{}", UNITES_AR[unite], DIZAINES_AR[dizaine]),
+    };
+
+    match (centaine, dizaines.is_empty()) {
+        (0, _) => dizaines,
+        (_, true) => CENTAINES_AR[centaine].to_string(),
+        _ => format!("{} و{}", CENTAINES_AR[centaine], dizaines),
+    }
+}
+
+// A count of thousands, millions or milliards: "ألف", "ألفان", "ثلاثة آلاف",
+// "أحد عشر ألفا", "مائة ألف", and "مائة ألف وألفان" for 102 000
+fn groupe_ar(n: u64, nom: &Nom) -> String {
+    match n {
+        1 => nom.singulier.to_string(),
+        2 => nom.duel.to_string(),
+        _ if n > 100 && matches!(n % 100, 1 | 2) => {
+            format!("{} {} و{}", moins_de_mille_ar(n - n % 100), nom.singulier, groupe_ar(n % 100, nom))
+        }
+        _ => format!("{} {}", moins_de_mille_ar(n), nom.apres(n)),
+    }
+}
+
+// Standard Arabic cardinal, groups joined with "و"
+pub fn nombre_en_lettres_arabe(n: u64) -> String {
+    if n == 0 {
+        return UNITES_AR[0].to_string();
+    }
+
+    let mut groupes = Vec::new();
+    let mut reste = n;
+
+    for (valeur, nom) in [(1_000_000_000, &MILYAR), (1_000_000, &MILYOUN), (1000, &ALF)] {
+        let nombre = reste / valeur;
+        if nombre > 0 {
+            groupes.push(if nombre < 1000 {
+                groupe_ar(nombre, nom)
+            } else {
+                format!("{} {}", nombre_en_lettres_arabe(nombre), nom.apres(nombre))
+            });
+            reste %= valeur;
+        }
+    }
+
+    if reste > 0 {
+        groupes.push(moins_de_mille_ar(reste));
+    }
+
+    groupes.join(" و")
+}
+
+// A quantity followed by its noun: "درهم واحد", "درهمان", "ثلاثة دراهم", "مائة درهم".
+// A scale directly followed by the noun loses its accusative ending: "عشرون ألف درهم"
+fn quantite_ar(n: u64, nom: &Nom) -> String {
+    match n {
+        1 => format!("{} {}", nom.singulier, UNITES_AR[1]),
+        2 => nom.duel.to_string(),
+        _ => {
+            let mut nombre = nombre_en_lettres_arabe(n);
+            if let Some(echelle) = [&ALF, &MILYOUN, &MILYAR].iter().find(|echelle| nombre.ends_with(echelle.accusatif)) {
+                nombre.truncate(nombre.len() - echelle.accusatif.len());
+                nombre.push_str(echelle.singulier);
+            }
+            format!("{} {}", nombre, nom.apres(n))
+        }
+    }
+}
+
+// "مائة وعشرون ألف درهم وخمسون سنتيما"
+pub fn montant_en_lettres_arabe(montant: &BigDecimal) -> Result<String, DocumentError> {
+    let (dirhams, centimes) = dirhams_centimes(montant)?;
+
+    let lettres = match (dirhams, centimes) {
+        (0, 0) => format!("{} {}", UNITES_AR[0], DIRHAM.singulier),
+        (0, _) => quantite_ar(centimes, &CENTIME),
+        (_, 0) => quantite_ar(dirhams, &DIRHAM),
+        _ => format!("{} و{}", quantite_ar(dirhams, &DIRHAM), quantite_ar(centimes, &CENTIME)),
+    };
+
+    if montant.round(2).is_negative() {
+        Ok(format!("ناقص {}", lettres))
+    } else {
+        Ok(lettres)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::str::FromStr;
+
+    fn montant(texte: &str) -> BigDecimal {
+        BigDecimal::from_str(texte).unwrap()
+    }
+
+    #[test]
+    fn french_numbers_follow_hyphenation_and_plural_rules() {
+        let cas = [
+            (0, "zéro"),
+            (1, "un"),
+            (16, "seize"),
+            (17, "dix-sept"),
+            (20, "vingt"),
+            (21, "vingt et un"),
+            (22, "vingt-deux"),
+            (61, "soixante et un"),
+            (70, "soixante-dix"),
+            (71, "soixante et onze"),
+            (72, "soixante-douze"),
+            (77, "soixante-dix-sept"),
+            (80, "quatre-vingts"),
+            (81, "quatre-vingt-un"),
+            (90, "quatre-vingt-dix"),
+            (91, "quatre-vingt-onze"),
+            (99, "quatre-vingt-dix-neuf"),
+            (100, "cent"),
+            (101, "cent un"),
+            (180, "cent quatre-vingts"),
+            (200, "deux cents"),
+            (201, "deux cent un"),
+            (999, "neuf cent quatre-vingt-dix-neuf"),
+            (1000, "mille"),
+            (1001, "mille un"),
+            (2000, "deux mille"),
+            (21_000, "vingt et un mille"),
+            (80_000, "quatre-vingt mille"),
+            (80_080, "quatre-vingt mille quatre-vingts"),
+            (200_000, "deux cent mille"),
+            (300_200, "trois cent mille deux cents"),
+            (1_000_000, "un million"),
+            (2_000_000, "deux millions"),
+            (80_000_000, "quatre-vingts millions"),
+            (200_000_000, "deux cents millions"),
+            (1_001_000, "un million mille"),
+            (1_000_000_000, "un milliard"),
+            (2_500_000_000, "deux milliards cinq cents millions"),
+        ];
+
+        for (nombre, attendu) in cas {
+            assert_eq!(nombre_en_lettres(nombre), attendu, "{}", nombre);
+        }
+    }
+
+    #[test]
+    fn french_numbers_are_distinct_and_well_formed_up_to_a_million() {
+        let mut vus = HashSet::new();
+
+        for n in 0..=1_000_000 {
+            let lettres = nombre_en_lettres(n);
+            let mots: Vec<&str> = lettres.split(' ').collect();
+
+            assert!(mots.iter().all(|mot| !mot.is_empty() && !mot.starts_with('-') && !mot.ends_with('-')), "{}: {}", n, lettres);
+
+            // "vingts" and "cents" only when nothing follows them but "millions"
+            for (position, mot) in mots.iter().enumerate() {
+                if mot.ends_with("vingts") || *mot == "cents" {
+                    assert!(mots.get(position + 1).is_none_or(|suivant| suivant.starts_with("million")), "{}: {}", n, lettres);
+                }
+            }
+
+            assert!(vus.insert(lettres), "{}", n);
+        }
+    }
+
+    #[test]
+    fn french_amounts_use_dirhams_and_centimes() {
+        let cas = [
+            ("0", "zéro dirham"),
+            ("1", "un dirham"),
+            ("2", "deux dirhams"),
+            ("0.01", "un centime"),
+            ("0.5", "cinquante centimes"),
+            ("1.01", "un dirham et un centime"),
+            ("120000.50", "cent vingt mille dirhams et cinquante centimes"),
+            ("1000000", "un million de dirhams"),
+            ("3000000.10", "trois millions de dirhams et dix centimes"),
+            ("1000100", "un million cent dirhams"),
+            ("8256.666", "huit mille deux cent cinquante-six dirhams et soixante-sept centimes"),
+            ("-5.50", "moins cinq dirhams et cinquante centimes"),
+        ];
+
+        for (texte, attendu) in cas {
+            assert_eq!(montant_en_lettres(&montant(texte)).unwrap(), attendu, "{}", texte);
+        }
+    }
+
+    #[test]
+    fn amounts_beyond_the_largest_number_are_refused() {
+        let trop = montant("18446744073709551616");
+
+        assert!(matches!(montant_en_lettres(&trop), Err(DocumentError::MontantHorsLimite(_))));
+        assert!(matches!(montant_en_lettres_arabe(&-trop), Err(DocumentError::MontantHorsLimite(_))));
+    }
+
+    #[test]
+    fn arabic_numbers() {
+        let cas = [
+            (0, "صفر"),
+            (1, "واحد"),
+            (11, "أحد عشر"),
+            (12, "اثنا عشر"),
+            (15, "خمسة عشر"),
+            (20, "عشرون"),
+            (25, "خمسة وعشرون"),
+            (100, "مائة"),
+            (125, "مائة وخمسة وعشرون"),
+            (200, "مائتان"),
+            (1000, "ألف"),
+            (2000, "ألفان"),
+            (3000, "ثلاثة آلاف"),
+            (11_000, "أحد عشر ألفا"),
+            (100_000, "مائة ألف"),
+            (102_000, "مائة ألف وألفان"),
+            (120_000, "مائة وعشرون ألفا"),
+            (1_000_001, "مليون وواحد"),
+            (5_000_000, "خمسة ملايين"),
+            (2_000_000_000, "ملياران"),
+        ];
+
+        for (nombre, attendu) in cas {
+            assert_eq!(nombre_en_lettres_arabe(nombre), attendu, "{}", nombre);
+        }
+    }
+
+    #[test]
+    fn arabic_amounts_agree_the_counted_noun() {
+        let cas = [
+            ("0", "صفر درهم"),
+            ("1", "درهم واحد"),
+            ("2", "درهمان"),
+            ("3", "ثلاثة دراهم"),
+            ("11", "أحد عشر درهما"),
+            ("100", "مائة درهم"),
+            ("0.50", "خمسون سنتيما"),
+            ("120000.50", "مائة وعشرون ألف درهم وخمسون سنتيما"),
+            ("11000000", "أحد عشر مليون درهم"),
+            ("-2.03", "ناقص درهمان وثلاثة سنتيمات"),
+        ];
+
+        for (texte, attendu) in cas {
+            assert_eq!(montant_en_lettres_arabe(&montant(texte)).unwrap(), attendu, "{}", texte);
+        }
+    }
+}
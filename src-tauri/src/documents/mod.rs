@@ -0,0 +1,87 @@
+pub mod attestation;
+pub mod bulletin;
+pub mod classeur;
+pub mod declaration;
+pub mod export;
+pub mod lettres;
+pub mod ov;
+pub mod virements;
+
+pub use attestation::{
+    remplir_modele, rendre_attestation, rendre_attestation_travail, verifier_modele, AttestationSalaire, AttestationTravail,
+    LigneAttestation, CHAMPS_MODELE,
+};
+pub use bulletin::{masquer_rib, rendre_bulletins, BulletinPaie, Employeur};
+pub use classeur::{rendre_classeur, Cellule, Feuille};
+pub use declaration::{feuilles_declaration, rendre_declaration_xml};
+pub use export::{rendre_liste, retenir, Exportable, FormatExport};
+pub use lettres::{montant_en_lettres, montant_en_lettres_arabe};
+pub use ov::{remplir_ov, LigneOv, NumeroOrdre, OrdreVirement, GABARIT_PAIE, GABARIT_RAPPEL};
+
+use bigdecimal::BigDecimal;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum DocumentError {
+    #[error("Erreur lors de la génération du PDF : {0}")]
+    Pdf(#[from] printpdf::Error),
+    #[error("Erreur lors de l'écriture du fichier : {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Erreur lors de la lecture du modèle : {0}")]
+    Zip(#[from] zip::result::ZipError),
+    #[error("Modèle invalide : élément {0} introuvable")]
+    Gabarit(String),
+    #[error("Colonne inconnue : {0}")]
+    ColonneInconnue(String),
+    #[error("Champ inconnu dans le modèle : {{{0}}}")]
+    ChampInconnu(String),
+    #[error("Accolade non fermée dans le modèle")]
+    AccoladeOuverte,
+    #[error("Le champ {{{0}}} n'est pas renseigné pour ce résident")]
+    ChampVide(String),
+    #[error("Aucune donnée à exporter")]
+    Empty,
+    #[error("Montant trop élevé pour être écrit en lettres : {0}")]
+    MontantHorsLimite(BigDecimal),
+}
+
+pub const MOIS: [&str; 12] = [
+    "Janvier", "Février", "Mars", "Avril", "Mai", "Juin", "Juillet", "Août", "Septembre", "Octobre", "Novembre", "Décembre",
+];
+
+// French amount format used on every document: "12 345,67"
+pub fn formater_montant(montant: &BigDecimal) -> String {
+    let texte = montant.round(2).with_scale(2).to_string();
+    let (signe, texte) = match texte.strip_prefix('-') {
+        Some(reste) => ("-", reste),
+        None => ("", texte.as_str()),
+    };
+    let (entier, decimales) = texte.split_once('.').unwrap_or((texte, "00"));
+
+    let mut groupes = Vec::new();
+    let chiffres: Vec<char> = entier.chars().collect();
+    for groupe in chiffres.rchunks(3).rev() {
+        groupes.push(groupe.iter().collect::<String>());
+    }
+
+    format!("{}{},{}", signe, groupes.join(" "), decimales)
+}
+
+// Escapes the five XML special characters for text and attribute values
+pub(crate) fn echapper_xml(texte: &str) -> String {
+    texte
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+// Makes a name safe to use as a file name on every platform
+pub fn nom_fichier(nom: &str) -> String {
+    nom.chars()
+        .map(|c| if c.is_alphanumeric() || c == '-' || c == '_' || c == ' ' { c } else { '_' })
+        .collect::<String>()
+        .trim()
+        .to_string()
+}
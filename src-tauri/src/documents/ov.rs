@@ -0,0 +1,428 @@
+use crate::documents::{echapper_xml, montant_en_lettres, DocumentError};
+use bigdecimal::{BigDecimal, Zero};
+use std::io::{Cursor, Read, Write};
+use zip::write::FileOptions;
+use zip::{CompressionMethod, ZipArchive, ZipWriter};
+
+const FEUILLE: &str = "xl/worksheets/sheet1.xml";
+const STYLES: &str = "xl/styles.xml";
+
+// One transfer of the order: a resident and the amount wired to their account
+#[derive(Debug, Clone)]
+pub struct LigneOv {
+    pub nom_prenom: String,
+    pub rib: String,
+    pub nom_banque: String,
+    pub montant: BigDecimal,
+}
+
+// Sequence number of an order within its year, printed as "N° 12/2024"
+#[derive(Debug, Clone, Copy)]
+pub struct NumeroOrdre {
+    pub numero: i32,
+    pub exercice: i32,
+}
+
+#[derive(Debug, Clone)]
+pub struct OrdreVirement {
+    pub lignes: Vec<LigneOv>,
+    // Sentence printed under the table, e.g. the month the order pays
+    pub objet: String,
+    // Left blank in the template, to be filled by hand, when None
+    pub numero: Option<NumeroOrdre>,
+}
+
+// Where a template expects its data. Columns are counted from A = 0: the four
+// data columns (name, RIB, bank, amount) start at `premiere_colonne`, and the row
+// `ligne_somme` receives the total in figures then in words.
+pub struct GabaritOv {
+    pub fichier: &'static [u8],
+    pub premiere_colonne: u8,
+    pub ligne_somme: u32,
+    pub premiere_ligne: u32,
+    pub hauteur_ligne: f32,
+    pub taille_police: u32,
+    // Rows on the first page and on the following ones, with carried-forward totals
+    pub lignes_par_page: Option<(usize, usize)>,
+    // Cell of the order number and its text, `{numero}` and `{exercice}` being replaced
+    pub cellule_numero: &'static str,
+    pub modele_numero: &'static str,
+}
+
+pub const GABARIT_PAIE: GabaritOv = GabaritOv {
+    fichier: include_bytes!("../../../public/templates/headerOV.xlsx"),
+    premiere_colonne: 0,
+    ligne_somme: 18,
+    premiere_ligne: 20,
+    hauteur_ligne: 24.0,
+    taille_police: 16,
+    lignes_par_page: Some((16, 28)),
+    cellule_numero: "A15",
+    modele_numero: "N° {numero}/{exercice}",
+};
+
+pub const GABARIT_RAPPEL: GabaritOv = GabaritOv {
+    fichier: include_bytes!("../../../public/templates/headerOV-RAP.xlsx"),
+    premiere_colonne: 1,
+    ligne_somme: 17,
+    premiere_ligne: 19,
+    hauteur_ligne: 60.75,
+    taille_police: 26,
+    lignes_par_page: None,
+    cellule_numero: "B13",
+    modele_numero: "N° {numero}/P/{exercice}",
+};
+
+struct Styles {
+    texte: usize,
+    centre: usize,
+    montant: usize,
+    total: usize,
+    total_montant: usize,
+    pied: usize,
+    signature: usize,
+}
+
+fn gabarit_invalide(element: &str) -> DocumentError {
+    DocumentError::Gabarit(element.to_string())
+}
+
+// Appends `elements` to a counted collection such as <fonts count="13">,
+// returning the index of the first one
+fn ajouter_elements(xml: &mut String, balise: &str, elements: &[String]) -> Result<usize, DocumentError> {
+    let ouverture = format!("<{} count=\"", balise);
+    let debut = xml.find(&ouverture).ok_or_else(|| gabarit_invalide(balise))? + ouverture.len();
+    let fin = debut + xml[debut..].find('"').ok_or_else(|| gabarit_invalide(balise))?;
+    let nombre: usize = xml[debut..fin].parse().map_err(|_| gabarit_invalide(balise))?;
+    xml.replace_range(debut..fin, &(nombre + elements.len()).to_string());
+
+    let fermeture = format!("</{}>", balise);
+    let position = xml.find(&fermeture).ok_or_else(|| gabarit_invalide(balise))?;
+    xml.insert_str(position, &elements.concat());
+
+    Ok(nombre)
+}
+
+fn ajouter_styles(styles: &mut String, taille: u32) -> Result<Styles, DocumentError> {
+    let police = ajouter_elements(
+        styles,
+        "fonts",
+        &[
+            format!("<font><b/><i/><sz val=\"{}\"/><name val=\"Times New Roman\"/><family val=\"1\"/></font>", taille),
+            format!("<font><b/><sz val=\"{}\"/><name val=\"Times New Roman\"/><family val=\"1\"/></font>", taille),
+            "<font><b/><sz val=\"16\"/><name val=\"Arial\"/><family val=\"2\"/></font>".to_string(),
+        ],
+    )?;
+    let fond = ajouter_elements(
+        styles,
+        "fills",
+        &["<fill><patternFill patternType=\"solid\"><fgColor rgb=\"FFBFBFBF\"/><bgColor indexed=\"64\"/></patternFill></fill>".to_string()],
+    )?;
+    let bordure = ajouter_elements(
+        styles,
+        "borders",
+        &["<border><left style=\"thin\"><color indexed=\"64\"/></left><right style=\"thin\"><color indexed=\"64\"/></right><top style=\"thin\"><color indexed=\"64\"/></top><bottom style=\"thin\"><color indexed=\"64\"/></bottom><diagonal/></border>".to_string()],
+    )?;
+
+    let xf = |format: u32, police: usize, fond: usize, bordure: usize, alignement: &str| {
+        format!(
+            "<xf numFmtId=\"{}\" fontId=\"{}\" fillId=\"{}\" borderId=\"{}\" xfId=\"0\" applyNumberFormat=\"1\" applyFont=\"1\" applyFill=\"1\" applyBorder=\"1\" applyAlignment=\"1\"><alignment horizontal=\"{}\" vertical=\"center\" wrapText=\"1\"/></xf>",
+            format, police, fond, bordure, alignement
+        )
+    };
+
+    // numFmtId 4 is the built-in "#,##0.00"
+    let premier = ajouter_elements(
+        styles,
+        "cellXfs",
+        &[
+            xf(0, police, 0, bordure, "left"),
+            xf(0, police, 0, bordure, "center"),
+            xf(4, police + 1, 0, bordure, "center"),
+            xf(0, police + 1, fond, bordure, "center"),
+            xf(4, police + 1, fond, bordure, "center"),
+            xf(0, police + 1, 0, 0, "center"),
+            xf(0, police + 2, 0, 0, "center"),
+        ],
+    )?;
+
+    Ok(Styles {
+        texte: premier,
+        centre: premier + 1,
+        montant: premier + 2,
+        total: premier + 3,
+        total_montant: premier + 4,
+        pied: premier + 5,
+        signature: premier + 6,
+    })
+}
+
+fn colonne(index: u8) -> char {
+    (b'A' + index) as char
+}
+
+fn cellule_texte(reference: &str, style: usize, texte: &str) -> String {
+    format!(
+        "<c r=\"{}\" s=\"{}\" t=\"inlineStr\"><is><t xml:space=\"preserve\">{}</t></is></c>",
+        reference,
+        style,
+        echapper_xml(texte)
+    )
+}
+
+fn cellule_montant(reference: &str, style: usize, montant: &BigDecimal) -> String {
+    format!("<c r=\"{}\" s=\"{}\"><v>{}</v></c>", reference, style, montant.round(2).with_scale(2))
+}
+
+// Replaces an existing cell of the template, keeping its style
+fn remplacer_cellule(feuille: &mut String, reference: &str, contenu: impl Fn(usize) -> String) -> Result<(), DocumentError> {
+    let motif = format!("<c r=\"{}\"", reference);
+    let debut = feuille.find(&motif).ok_or_else(|| gabarit_invalide(reference))?;
+    let reste = &feuille[debut..];
+    let fin_ouverture = reste.find('>').ok_or_else(|| gabarit_invalide(reference))?;
+    let fin = if reste[..fin_ouverture].ends_with('/') {
+        fin_ouverture + 1
+    } else {
+        reste.find("</c>").ok_or_else(|| gabarit_invalide(reference))? + 4
+    };
+
+    let ouverture = &reste[..fin_ouverture];
+    let style = ouverture
+        .find(" s=\"")
+        .and_then(|position| {
+            let valeur = &ouverture[position + 4..];
+            valeur[..valeur.find('"')?].parse().ok()
+        })
+        .unwrap_or(0);
+
+    feuille.replace_range(debut..debut + fin, &contenu(style));
+    Ok(())
+}
+
+fn remplir_feuille(feuille: &mut String, gabarit: &GabaritOv, styles: &Styles, ov: &OrdreVirement) -> Result<(), DocumentError> {
+    let col = |index: u8| colonne(gabarit.premiere_colonne + index);
+    let mut lignes = String::new();
+    let mut fusions = Vec::new();
+    let mut sauts = Vec::new();
+    let mut numero = gabarit.premiere_ligne;
+    let hauteur_total = gabarit.hauteur_ligne.max(33.75);
+
+    let mut ligne_total = |lignes: &mut String, numero: u32, libelle: &str, montant: &BigDecimal| {
+        lignes.push_str(&format!(
+            "<row r=\"{n}\" ht=\"{h}\" customHeight=\"1\">{}<c r=\"{b}{n}\" s=\"{s}\"/><c r=\"{c}{n}\" s=\"{s}\"/>{}</row>",
+            cellule_texte(&format!("{}{}", col(0), numero), styles.total, libelle),
+            cellule_montant(&format!("{}{}", col(3), numero), styles.total_montant, montant),
+            n = numero,
+            h = hauteur_total,
+            b = col(1),
+            c = col(2),
+            s = styles.total,
+        ));
+        fusions.push(format!("<mergeCell ref=\"{}{n}:{}{n}\"/>", col(0), col(2), n = numero));
+    };
+
+    let mut cumul = BigDecimal::zero();
+    let mut sur_page = 0;
+    let mut capacite = gabarit.lignes_par_page.map(|(premiere, _)| premiere);
+
+    for ligne in &ov.lignes {
+        if let (Some(places), Some((_, suivantes))) = (capacite, gabarit.lignes_par_page) {
+            if sur_page == places {
+                ligne_total(&mut lignes, numero, "TOTAL A REPORTER", &cumul);
+                sauts.push(numero);
+                numero += 1;
+                ligne_total(&mut lignes, numero, "TOTAL REPORTE", &cumul);
+                numero += 1;
+                sur_page = 0;
+                capacite = Some(suivantes);
+            }
+        }
+
+        lignes.push_str(&format!(
+            "<row r=\"{}\" ht=\"{}\" customHeight=\"1\">{}{}{}{}</row>",
+            numero,
+            gabarit.hauteur_ligne,
+            cellule_texte(&format!("{}{}", col(0), numero), styles.texte, &ligne.nom_prenom),
+            cellule_texte(&format!("{}{}", col(1), numero), styles.centre, &ligne.rib),
+            cellule_texte(&format!("{}{}", col(2), numero), styles.texte, &ligne.nom_banque),
+            cellule_montant(&format!("{}{}", col(3), numero), styles.montant, &ligne.montant),
+        ));
+        cumul += &ligne.montant;
+        sur_page += 1;
+        numero += 1;
+    }
+
+    ligne_total(&mut lignes, numero, "TOTAL GENERAL", &cumul);
+    numero += 1;
+
+    lignes.push_str(&format!(
+        "<row r=\"{}\" ht=\"45\" customHeight=\"1\">{}</row>",
+        numero,
+        cellule_texte(&format!("{}{}", col(0), numero), styles.pied, &ov.objet)
+    ));
+    fusions.push(format!("<mergeCell ref=\"{}{n}:{}{n}\"/>", col(0), col(3), n = numero));
+    numero += 1;
+
+    lignes.push_str(&format!(
+        "<row r=\"{}\" ht=\"60\" customHeight=\"1\">{}{}</row>",
+        numero,
+        cellule_texte(&format!("{}{}", col(0), numero), styles.signature, "L'ORDONNATEUR"),
+        cellule_texte(&format!("{}{}", col(2), numero), styles.signature, "LE FONDE DE POUVOIRS")
+    ));
+    fusions.push(format!("<mergeCell ref=\"{}{n}:{}{n}\"/>", col(2), col(3), n = numero));
+
+    let position = feuille.find("</sheetData>").ok_or_else(|| gabarit_invalide("sheetData"))?;
+    feuille.insert_str(position, &lignes);
+    ajouter_elements(feuille, "mergeCells", &fusions)?;
+
+    // Total in figures and in words above the table
+    remplacer_cellule(feuille, &format!("{}{}", col(0), gabarit.ligne_somme), |style| {
+        cellule_montant(&format!("{}{}", col(0), gabarit.ligne_somme), style, &cumul)
+    })?;
+    let en_lettres = montant_en_lettres(&cumul)?;
+    remplacer_cellule(feuille, &format!("{}{}", col(1), gabarit.ligne_somme), |style| {
+        cellule_texte(&format!("{}{}", col(1), gabarit.ligne_somme), style, &en_lettres)
+    })?;
+
+    if let Some(numero) = ov.numero {
+        let texte = gabarit
+            .modele_numero
+            .replace("{numero}", &numero.numero.to_string())
+            .replace("{exercice}", &numero.exercice.to_string());
+        remplacer_cellule(feuille, gabarit.cellule_numero, |style| cellule_texte(gabarit.cellule_numero, style, &texte))?;
+    }
+
+    if let Some(debut) = feuille.find("<dimension ref=\"") {
+        let debut = debut + "<dimension ref=\"".len();
+        if let Some(fin) = feuille[debut..].find('"') {
+            feuille.replace_range(debut..debut + fin, &format!("A1:{}{}", col(3), numero));
+        }
+    }
+
+    if !sauts.is_empty() {
+        let sauts_xml = format!(
+            "<rowBreaks count=\"{n}\" manualBreakCount=\"{n}\">{}</rowBreaks>",
+            sauts
+                .iter()
+                .map(|ligne| format!("<brk id=\"{}\" max=\"16383\" man=\"1\"/>", ligne))
+                .collect::<String>(),
+            n = sauts.len()
+        );
+        let position = feuille
+            .find("<drawing")
+            .or_else(|| feuille.find("</worksheet>"))
+            .ok_or_else(|| gabarit_invalide("worksheet"))?;
+        feuille.insert_str(position, &sauts_xml);
+    }
+
+    Ok(())
+}
+
+// Fills the OV template with the transfers of `ov` and returns the resulting workbook
+pub fn remplir_ov(gabarit: &GabaritOv, ov: &OrdreVirement) -> Result<Vec<u8>, DocumentError> {
+    if ov.lignes.is_empty() {
+        return Err(DocumentError::Empty);
+    }
+
+    let mut archive = ZipArchive::new(Cursor::new(gabarit.fichier))?;
+    let mut sortie = ZipWriter::new(Cursor::new(Vec::new()));
+    let options = FileOptions::default().compression_method(CompressionMethod::Deflated);
+
+    let mut styles = String::new();
+    archive.by_name(STYLES)?.read_to_string(&mut styles)?;
+    let ids = ajouter_styles(&mut styles, gabarit.taille_police)?;
+
+    let mut feuille = String::new();
+    archive.by_name(FEUILLE)?.read_to_string(&mut feuille)?;
+    remplir_feuille(&mut feuille, gabarit, &ids, ov)?;
+
+    for index in 0..archive.len() {
+        let mut fichier = archive.by_index(index)?;
+        let nom = fichier.name().to_string();
+        let mut contenu = Vec::new();
+        fichier.read_to_end(&mut contenu)?;
+
+        sortie.start_file(nom.as_str(), options)?;
+        match nom.as_str() {
+            FEUILLE => sortie.write_all(feuille.as_bytes())?,
+            STYLES => sortie.write_all(styles.as_bytes())?,
+            _ => sortie.write_all(&contenu)?,
+        }
+    }
+
+    Ok(sortie.finish()?.into_inner())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use calamine::{Data, Reader, Xlsx};
+    use std::str::FromStr;
+
+    fn ov(nombre: usize) -> OrdreVirement {
+        OrdreVirement {
+            lignes: (1..=nombre)
+                .map(|i| LigneOv {
+                    nom_prenom: format!("Résident {} & co", i),
+                    rib: format!("{:024}", i),
+                    nom_banque: "Banque".to_string(),
+                    montant: BigDecimal::from_str("1000.50").unwrap(),
+                })
+                .collect(),
+            objet: "Paie du mois de Juin 2024".to_string(),
+            numero: None,
+        }
+    }
+
+    fn lire(contenu: Vec<u8>) -> calamine::Range<Data> {
+        let mut classeur: Xlsx<_> = Xlsx::new(Cursor::new(contenu)).unwrap();
+        let feuille = classeur.sheet_names()[0].clone();
+        classeur.worksheet_range(&feuille).unwrap()
+    }
+
+    fn texte(feuille: &calamine::Range<Data>, ligne: u32, colonne: u32) -> String {
+        feuille.get_value((ligne - 1, colonne)).map(|v| v.to_string()).unwrap_or_default()
+    }
+
+    #[test]
+    fn monthly_order_has_rows_totals_and_amount_in_words() {
+        let feuille = lire(remplir_ov(&GABARIT_PAIE, &ov(3)).unwrap());
+
+        assert_eq!(texte(&feuille, 20, 0), "Résident 1 & co");
+        assert_eq!(texte(&feuille, 20, 1), "000000000000000000000001");
+        assert_eq!(feuille.get_value((21, 3)), Some(&Data::Float(1000.5)));
+        assert_eq!(texte(&feuille, 23, 0), "TOTAL GENERAL");
+        assert_eq!(feuille.get_value((22, 3)), Some(&Data::Float(3001.5)));
+        assert_eq!(feuille.get_value((17, 0)), Some(&Data::Float(3001.5)));
+        assert_eq!(texte(&feuille, 18, 1), "trois mille un dirhams et cinquante centimes");
+    }
+
+    #[test]
+    fn long_orders_carry_totals_over_pages() {
+        let feuille = lire(remplir_ov(&GABARIT_PAIE, &ov(20)).unwrap());
+
+        assert_eq!(texte(&feuille, 36, 0), "TOTAL A REPORTER");
+        assert_eq!(feuille.get_value((35, 3)), Some(&Data::Float(16008.0)));
+        assert_eq!(texte(&feuille, 37, 0), "TOTAL REPORTE");
+        assert_eq!(texte(&feuille, 38, 0), "Résident 17 & co");
+        assert_eq!(texte(&feuille, 42, 0), "TOTAL GENERAL");
+    }
+
+    #[test]
+    fn order_number_replaces_the_template_placeholder() {
+        let mut ordre = ov(1);
+        ordre.numero = Some(NumeroOrdre { numero: 12, exercice: 2024 });
+
+        assert_eq!(texte(&lire(remplir_ov(&GABARIT_PAIE, &ordre).unwrap()), 15, 0), "N° 12/2024");
+        assert_eq!(texte(&lire(remplir_ov(&GABARIT_RAPPEL, &ordre).unwrap()), 13, 1), "N° 12/P/2024");
+    }
+
+    #[test]
+    fn rappel_template_starts_in_column_b() {
+        let feuille = lire(remplir_ov(&GABARIT_RAPPEL, &ov(2)).unwrap());
+
+        assert_eq!(texte(&feuille, 19, 1), "Résident 1 & co");
+        assert_eq!(feuille.get_value((16, 1)), Some(&Data::Float(2001.0)));
+        assert_eq!(texte(&feuille, 21, 1), "TOTAL GENERAL");
+    }
+}
@@ -0,0 +1,141 @@
+use crate::documents::virements::{centimes, texte_bancaire, LotBanque, Remise, VirementError};
+use crate::documents::Employeur;
+
+// A fixed-width bulk-transfer layout. Every file has a header record (01), one
+// detail record per transfer (04) and a trailer record (09) carrying the count
+// and total in centimes; fields are padded with spaces (text) or zeros (numbers).
+//
+// 01 | date DDMMYYYY (8) | ordering RIB (24) | ordering name | reference (10) | bank code (3)
+// 04 | sequence (6) | RIB (24) | name | amount in centimes (15) | label
+// 09 | count (6) | total in centimes (18)
+#[derive(Debug)]
+pub struct FormatFixe {
+    pub code: &'static str,
+    pub longueur: usize,
+    pub longueur_nom: usize,
+    pub longueur_libelle: usize,
+}
+
+pub const FORMATS_FIXES: [FormatFixe; 2] = [
+    FormatFixe { code: "fixe160", longueur: 160, longueur_nom: 35, longueur_libelle: 35 },
+    FormatFixe { code: "fixe120", longueur: 120, longueur_nom: 30, longueur_libelle: 16 },
+];
+
+pub fn format_fixe(code: &str) -> Result<&'static FormatFixe, VirementError> {
+    FORMATS_FIXES
+        .iter()
+        .find(|format| format.code == code)
+        .ok_or_else(|| VirementError::FormatInconnu(code.to_string()))
+}
+
+fn alpha(texte: &str, longueur: usize) -> String {
+    format!("{:<largeur$}", texte_bancaire(texte, longueur), largeur = longueur)
+}
+
+fn numerique(valeur: u64, longueur: usize) -> String {
+    format!("{:0>largeur$}", valeur, largeur = longueur)
+}
+
+impl FormatFixe {
+    fn enregistrement(&self, champs: &[String]) -> String {
+        format!("{:<largeur$}\r\n", champs.concat(), largeur = self.longueur)
+    }
+}
+
+// The file of one bank, records separated by CRLF
+pub fn rendre_fichier_fixe(format: &FormatFixe, donneur: &Employeur, remise: &Remise, lot: &LotBanque) -> Result<String, VirementError> {
+    let rib_donneur: String = donneur.rib.chars().filter(|c| c.is_ascii_digit()).collect();
+    if rib_donneur.len() != 24 {
+        return Err(VirementError::DonneurSansRib);
+    }
+    if lot.virements.is_empty() {
+        return Err(VirementError::Vide);
+    }
+
+    let mut fichier = format.enregistrement(&[
+        "01".to_string(),
+        remise.date_execution.format("%d%m%Y").to_string(),
+        rib_donneur,
+        alpha(&donneur.nom, format.longueur_nom),
+        alpha(&remise.reference, 10),
+        alpha(lot.code_banque.as_deref().unwrap_or_default(), 3),
+    ]);
+
+    let mut total = 0;
+    for (rang, virement) in lot.virements.iter().enumerate() {
+        let montant = centimes(virement)?;
+        total += montant;
+        fichier.push_str(&format.enregistrement(&[
+            "04".to_string(),
+            numerique(rang as u64 + 1, 6),
+            virement.rib.clone(),
+            alpha(&virement.beneficiaire, format.longueur_nom),
+            numerique(montant, 15),
+            alpha(&remise.libelle, format.longueur_libelle),
+        ]));
+    }
+
+    fichier.push_str(&format.enregistrement(&[
+        "09".to_string(),
+        numerique(lot.nombre() as u64, 6),
+        numerique(total, 18),
+    ]));
+
+    Ok(fichier)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::documents::virements::grouper_par_banque;
+    use crate::documents::virements::tests::virement;
+    use chrono::NaiveDate;
+
+    fn employeur() -> Employeur {
+        Employeur {
+            nom: "Centre Hospitalier Universitaire".to_string(),
+            adresse: String::new(),
+            rib: "011 780 0000123456789012 34".to_string(),
+            bic: Some("BCPOMAMC".to_string()),
+            identifiant_fiscal: None,
+        }
+    }
+
+    fn remise() -> Remise {
+        Remise {
+            reference: "PAIE202406".to_string(),
+            date_execution: NaiveDate::from_ymd_opt(2024, 6, 28).unwrap(),
+            libelle: "Paie résidents 06/2024".to_string(),
+        }
+    }
+
+    #[test]
+    fn records_have_the_layout_width_and_control_totals() {
+        let lots = grouper_par_banque(&[virement(1, "Zahra Alaoui", "Banque A", "100.10"), virement(3, "Bilal", "Banque A", "2000")]).unwrap();
+
+        for format in &FORMATS_FIXES {
+            let fichier = rendre_fichier_fixe(format, &employeur(), &remise(), &lots[0]).unwrap();
+            let lignes: Vec<&str> = fichier.split_terminator("\r\n").collect();
+
+            assert_eq!(lignes.len(), 4);
+            assert!(lignes.iter().all(|ligne| ligne.len() == format.longueur), "{}", format.code);
+            assert!(lignes[0].starts_with("0128062024011780000012345678901234CENTRE HOSPITALIER"));
+            assert!(lignes[1].starts_with("04000001007780000000000000000312BILAL"));
+            assert_eq!(&lignes[2][32 + format.longueur_nom..47 + format.longueur_nom], "000000000010010");
+            assert_eq!(lignes[3].trim_end(), "09000002000000000000210010");
+        }
+    }
+
+    #[test]
+    fn ordering_account_is_required() {
+        let lots = grouper_par_banque(&[virement(1, "Amine", "Banque A", "10")]).unwrap();
+        let mut sans_rib = employeur();
+        sans_rib.rib = String::new();
+
+        assert!(matches!(
+            rendre_fichier_fixe(&FORMATS_FIXES[0], &sans_rib, &remise(), &lots[0]),
+            Err(VirementError::DonneurSansRib)
+        ));
+        assert!(matches!(format_fixe("cfonb"), Err(VirementError::FormatInconnu(_))));
+    }
+}
@@ -0,0 +1,197 @@
+pub mod fixe;
+pub mod pain001;
+pub mod retour;
+
+pub use fixe::{format_fixe, rendre_fichier_fixe, FormatFixe, FORMATS_FIXES};
+pub use pain001::rendre_pain001;
+pub use retour::{code_execute, lire_montant, lire_retour_fixe, motif_rejet, LigneRetour};
+
+use bigdecimal::{BigDecimal, Signed, ToPrimitive, Zero};
+use chrono::NaiveDate;
+use std::collections::BTreeMap;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum VirementError {
+    #[error("RIB invalide pour {0} : {1}")]
+    RibInvalide(String, String),
+    #[error("Montant invalide pour {0} : {1}")]
+    MontantInvalide(String, BigDecimal),
+    #[error("Le RIB du donneur d'ordre n'est pas configuré (EMPLOYEUR_RIB)")]
+    DonneurSansRib,
+    #[error("Format de fichier de virement inconnu : {0}")]
+    FormatInconnu(String),
+    #[error("Aucun virement à exporter")]
+    Vide,
+}
+
+// One transfer to a resident; `id_paiement` makes its end-to-end reference
+#[derive(Debug, Clone)]
+pub struct Virement {
+    pub id_paiement: i32,
+    pub beneficiaire: String,
+    pub rib: String,
+    pub nom_banque: String,
+    pub code_banque: Option<String>,
+    pub bic: Option<String>,
+    pub montant: BigDecimal,
+}
+
+// Identification of a bulk transfer: `reference` is the file/message id
+// the bank reports back, `libelle` the text shown on the statements
+#[derive(Debug, Clone)]
+pub struct Remise {
+    pub reference: String,
+    pub date_execution: NaiveDate,
+    pub libelle: String,
+}
+
+// The transfers sent to one bank, with the control totals of its file
+#[derive(Debug, Clone)]
+pub struct LotBanque {
+    pub nom_banque: String,
+    pub code_banque: Option<String>,
+    pub bic: Option<String>,
+    pub virements: Vec<Virement>,
+    pub total: BigDecimal,
+}
+
+impl LotBanque {
+    pub fn nombre(&self) -> usize {
+        self.virements.len()
+    }
+}
+
+// RIB without its separators, rejected unless it is 24 digits
+pub(crate) fn normaliser_rib(beneficiaire: &str, rib: &str) -> Result<String, VirementError> {
+    let chiffres: String = rib.chars().filter(|c| !c.is_whitespace() && *c != '-').collect();
+    if chiffres.len() != 24 || !chiffres.chars().all(|c| c.is_ascii_digit()) {
+        return Err(VirementError::RibInvalide(beneficiaire.to_string(), rib.to_string()));
+    }
+    Ok(chiffres)
+}
+
+pub(crate) fn centimes(virement: &Virement) -> Result<u64, VirementError> {
+    (virement.montant.round(2) * BigDecimal::from(100))
+        .round(0)
+        .to_u64()
+        .ok_or_else(|| VirementError::MontantInvalide(virement.beneficiaire.clone(), virement.montant.clone()))
+}
+
+// Banks only accept upper-case ASCII letters, digits and a few punctuation signs:
+// accents are dropped ("Fès" -> "FES") and anything else becomes a space
+pub(crate) fn texte_bancaire(texte: &str, longueur: usize) -> String {
+    texte
+        .chars()
+        .map(|c| match c {
+            'à' | 'â' | 'ä' | 'À' | 'Â' | 'Ä' => 'A',
+            'ç' | 'Ç' => 'C',
+            'é' | 'è' | 'ê' | 'ë' | 'É' | 'È' | 'Ê' | 'Ë' => 'E',
+            'î' | 'ï' | 'Î' | 'Ï' => 'I',
+            'ô' | 'ö' | 'Ô' | 'Ö' => 'O',
+            'ù' | 'û' | 'ü' | 'Ù' | 'Û' | 'Ü' => 'U',
+            'ÿ' => 'Y',
+            c if c.is_ascii_alphanumeric() => c.to_ascii_uppercase(),
+            '/' | '-' | '?' | ':' | '(' | ')' | '.' | ',' | '\'' | '+' => c,
+            _ => ' ',
+        })
+        .collect::<String>()
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .chars()
+        .take(longueur)
+        .collect()
+}
+
+// Checks every transfer and groups them per bank, banks and beneficiaries in
+// alphabetical order
+pub fn grouper_par_banque(virements: &[Virement]) -> Result<Vec<LotBanque>, VirementError> {
+    if virements.is_empty() {
+        return Err(VirementError::Vide);
+    }
+
+    let mut lots: BTreeMap<String, LotBanque> = BTreeMap::new();
+
+    for virement in virements {
+        if !virement.montant.is_positive() {
+            return Err(VirementError::MontantInvalide(virement.beneficiaire.clone(), virement.montant.clone()));
+        }
+        let rib = normaliser_rib(&virement.beneficiaire, &virement.rib)?;
+
+        let lot = lots.entry(virement.nom_banque.clone()).or_insert_with(|| LotBanque {
+            nom_banque: virement.nom_banque.clone(),
+            code_banque: virement.code_banque.clone(),
+            bic: virement.bic.clone(),
+            virements: Vec::new(),
+            total: BigDecimal::zero(),
+        });
+        lot.total += virement.montant.round(2);
+        lot.virements.push(Virement { rib, ..virement.clone() });
+    }
+
+    let mut lots: Vec<LotBanque> = lots.into_values().collect();
+    for lot in &mut lots {
+        lot.virements.sort_by(|a, b| a.beneficiaire.cmp(&b.beneficiaire));
+    }
+
+    Ok(lots)
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    pub(crate) fn virement(id: i32, beneficiaire: &str, banque: &str, montant: &str) -> Virement {
+        Virement {
+            id_paiement: id,
+            beneficiaire: beneficiaire.to_string(),
+            rib: format!("{} 780 0000000000{:06} 12", if banque == "Banque B" { "190" } else { "007" }, id),
+            nom_banque: banque.to_string(),
+            code_banque: Some(if banque == "Banque B" { "190" } else { "007" }.to_string()),
+            bic: if banque == "Banque B" { None } else { Some("BCMAMAMC".to_string()) },
+            montant: BigDecimal::from_str(montant).unwrap(),
+        }
+    }
+
+    #[test]
+    fn transfers_are_grouped_per_bank_with_totals() {
+        let lots = grouper_par_banque(&[
+            virement(1, "Zahra", "Banque A", "100.10"),
+            virement(2, "Amine", "Banque B", "50"),
+            virement(3, "Bilal", "Banque A", "200.05"),
+        ])
+        .unwrap();
+
+        assert_eq!(lots.len(), 2);
+        assert_eq!(lots[0].nom_banque, "Banque A");
+        assert_eq!(lots[0].nombre(), 2);
+        assert_eq!(lots[0].total, BigDecimal::from_str("300.15").unwrap());
+        assert_eq!(lots[0].virements[0].beneficiaire, "Bilal");
+        assert_eq!(lots[0].virements[0].rib, "007780000000000000000312");
+        assert_eq!(lots[1].total, BigDecimal::from(50));
+    }
+
+    #[test]
+    fn invalid_transfers_are_rejected() {
+        let mut rib_court = virement(1, "Amine", "Banque A", "10");
+        rib_court.rib = "0077800000".to_string();
+        assert!(matches!(grouper_par_banque(&[rib_court]), Err(VirementError::RibInvalide(..))));
+
+        let nul = virement(1, "Amine", "Banque A", "0");
+        assert!(matches!(grouper_par_banque(&[nul]), Err(VirementError::MontantInvalide(..))));
+
+        assert!(matches!(grouper_par_banque(&[]), Err(VirementError::Vide)));
+
+        assert_eq!(centimes(&virement(1, "Amine", "Banque A", "1234.565")).unwrap(), 123457);
+        let hors_limite = virement(1, "Amine", "Banque A", "184467440737095516.16");
+        assert!(matches!(centimes(&hors_limite), Err(VirementError::MontantInvalide(..))));
+    }
+
+    #[test]
+    fn bank_text_is_upper_case_ascii() {
+        assert_eq!(texte_bancaire("Benjelloun  Hélène & Loïc", 35), "BENJELLOUN HELENE LOIC");
+        assert_eq!(texte_bancaire("Paie Juin 2024", 6), "PAIE J");
+    }
+}
@@ -0,0 +1,154 @@
+use crate::documents::virements::{normaliser_rib, texte_bancaire, LotBanque, Remise, VirementError};
+use crate::documents::{echapper_xml, Employeur};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDateTime;
+use std::fmt::Write;
+
+const ESPACE_NOMS: &str = "urn:iso:std:iso:20022:tech:xsd:pain.001.001.03";
+const DEVISE: &str = "MAD";
+
+fn montant(valeur: &BigDecimal) -> String {
+    valeur.round(2).with_scale(2).to_string()
+}
+
+// A financial institution by BIC, or by its bank code when it has none
+fn institution(bic: Option<&str>, code_banque: Option<&str>) -> String {
+    match (bic, code_banque) {
+        (Some(bic), _) => format!("<FinInstnId><BIC>{}</BIC></FinInstnId>", echapper_xml(&bic.to_uppercase())),
+        (None, Some(code)) => format!("<FinInstnId><Othr><Id>{}</Id></Othr></FinInstnId>", echapper_xml(code)),
+        (None, None) => "<FinInstnId><Othr><Id>NOTPROVIDED</Id></Othr></FinInstnId>".to_string(),
+    }
+}
+
+// ISO 20022 customer credit transfer initiation (pain.001.001.03): one payment
+// information block per bank, each with its own count and control sum, and the
+// grand totals in the group header. The RIB is sent as a non-IBAN account id.
+pub fn rendre_pain001(donneur: &Employeur, remise: &Remise, lots: &[LotBanque], cree_le: NaiveDateTime) -> Result<String, VirementError> {
+    let rib_donneur =
+        normaliser_rib(&donneur.nom, &donneur.rib).map_err(|_| VirementError::DonneurSansRib)?;
+    if lots.iter().all(|lot| lot.virements.is_empty()) {
+        return Err(VirementError::Vide);
+    }
+
+    let nombre: usize = lots.iter().map(LotBanque::nombre).sum();
+    let total = lots.iter().fold(BigDecimal::zero(), |total, lot| total + &lot.total);
+    let nom_donneur = echapper_xml(&texte_bancaire(&donneur.nom, 140));
+    let reference = texte_bancaire(&remise.reference, 30);
+    let libelle = echapper_xml(&texte_bancaire(&remise.libelle, 140));
+
+    let mut xml = String::new();
+    // Writing to a String cannot fail
+    let _ = write!(
+        xml,
+        "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<Document xmlns=\"{}\"><CstmrCdtTrfInitn>\
+         <GrpHdr><MsgId>{}</MsgId><CreDtTm>{}</CreDtTm><NbOfTxs>{}</NbOfTxs><CtrlSum>{}</CtrlSum>\
+         <InitgPty><Nm>{}</Nm></InitgPty></GrpHdr>",
+        ESPACE_NOMS,
+        echapper_xml(&reference),
+        cree_le.format("%Y-%m-%dT%H:%M:%S"),
+        nombre,
+        montant(&total),
+        nom_donneur
+    );
+
+    for (rang, lot) in lots.iter().filter(|lot| !lot.virements.is_empty()).enumerate() {
+        let _ = write!(
+            xml,
+            "<PmtInf><PmtInfId>{}-{:03}</PmtInfId><PmtMtd>TRF</PmtMtd><BtchBookg>true</BtchBookg>\
+             <NbOfTxs>{}</NbOfTxs><CtrlSum>{}</CtrlSum><PmtTpInf><CtgyPurp><Cd>SALA</Cd></CtgyPurp></PmtTpInf>\
+             <ReqdExctnDt>{}</ReqdExctnDt><Dbtr><Nm>{}</Nm></Dbtr><DbtrAcct><Id><Othr><Id>{}</Id></Othr></Id><Ccy>{}</Ccy></DbtrAcct>\
+             <DbtrAgt>{}</DbtrAgt><ChrgBr>SLEV</ChrgBr>",
+            echapper_xml(&reference),
+            rang + 1,
+            lot.nombre(),
+            montant(&lot.total),
+            remise.date_execution.format("%Y-%m-%d"),
+            nom_donneur,
+            rib_donneur,
+            DEVISE,
+            institution(donneur.bic.as_deref(), rib_donneur.get(..3))
+        );
+
+        for virement in &lot.virements {
+            let _ = write!(
+                xml,
+                "<CdtTrfTxInf><PmtId><EndToEndId>{}-{}</EndToEndId></PmtId>\
+                 <Amt><InstdAmt Ccy=\"{}\">{}</InstdAmt></Amt><CdtrAgt>{}</CdtrAgt>\
+                 <Cdtr><Nm>{}</Nm></Cdtr><CdtrAcct><Id><Othr><Id>{}</Id></Othr></Id></CdtrAcct>\
+                 <RmtInf><Ustrd>{}</Ustrd></RmtInf></CdtTrfTxInf>",
+                echapper_xml(&reference),
+                virement.id_paiement,
+                DEVISE,
+                montant(&virement.montant),
+                institution(lot.bic.as_deref(), lot.code_banque.as_deref()),
+                echapper_xml(&texte_bancaire(&virement.beneficiaire, 140)),
+                virement.rib,
+                libelle
+            );
+        }
+
+        xml.push_str("</PmtInf>");
+    }
+
+    xml.push_str("</CstmrCdtTrfInitn></Document>\n");
+    Ok(xml)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::documents::virements::grouper_par_banque;
+    use crate::documents::virements::tests::virement;
+    use chrono::NaiveDate;
+    use std::process::Command;
+
+    const SCHEMA: &str = concat!(env!("CARGO_MANIFEST_DIR"), "/schemas/pain.001.001.03.xsd");
+
+    fn rendre() -> String {
+        let lots = grouper_par_banque(&[
+            virement(1, "Zahra Alaoui", "Banque A", "100.10"),
+            virement(2, "Amine O'Neil & fils", "Banque B", "50"),
+            virement(3, "Bilal", "Banque A", "2000"),
+        ])
+        .unwrap();
+        let donneur = Employeur {
+            nom: "Centre Hospitalier Universitaire".to_string(),
+            adresse: String::new(),
+            rib: "011780000012345678901234".to_string(),
+            bic: None,
+            identifiant_fiscal: None,
+        };
+        let remise = Remise {
+            reference: "PAIE202406".to_string(),
+            date_execution: NaiveDate::from_ymd_opt(2024, 6, 28).unwrap(),
+            libelle: "Paie résidents 06/2024".to_string(),
+        };
+        let cree_le = NaiveDate::from_ymd_opt(2024, 6, 27).unwrap().and_hms_opt(10, 30, 0).unwrap();
+
+        rendre_pain001(&donneur, &remise, &lots, cree_le).unwrap()
+    }
+
+    #[test]
+    fn control_totals_are_given_per_bank_and_overall() {
+        let xml = rendre();
+
+        assert!(xml.contains("<GrpHdr><MsgId>PAIE202406</MsgId><CreDtTm>2024-06-27T10:30:00</CreDtTm><NbOfTxs>3</NbOfTxs><CtrlSum>2150.10</CtrlSum>"));
+        assert!(xml.contains("<PmtInfId>PAIE202406-001</PmtInfId><PmtMtd>TRF</PmtMtd><BtchBookg>true</BtchBookg><NbOfTxs>2</NbOfTxs><CtrlSum>2100.10</CtrlSum>"));
+        assert!(xml.contains("<PmtInfId>PAIE202406-002</PmtInfId><PmtMtd>TRF</PmtMtd><BtchBookg>true</BtchBookg><NbOfTxs>1</NbOfTxs><CtrlSum>50.00</CtrlSum>"));
+        assert!(xml.contains("<EndToEndId>PAIE202406-3</EndToEndId>"));
+        assert!(xml.contains("<Cdtr><Nm>AMINE O&apos;NEIL FILS</Nm></Cdtr>"));
+    }
+
+    // Needs xmllint (libxml2) on the PATH
+    #[test]
+    fn output_is_valid_against_the_schema() {
+        let fichier = std::env::temp_dir().join(format!("pain001-{}.xml", std::process::id()));
+        std::fs::write(&fichier, rendre()).unwrap();
+
+        let resultat = Command::new("xmllint").arg("--noout").arg("--schema").arg(SCHEMA).arg(&fichier).output();
+        let _ = std::fs::remove_file(&fichier);
+
+        let sortie = resultat.expect("xmllint (libxml2) is required to validate the pain.001 schema");
+        assert!(sortie.status.success(), "{}", String::from_utf8_lossy(&sortie.stderr));
+    }
+}
@@ -0,0 +1,187 @@
+use crate::documents::virements::{normaliser_rib, FormatFixe, VirementError, FORMATS_FIXES};
+use crate::models::ImportRowError;
+use bigdecimal::BigDecimal;
+use std::str::FromStr;
+
+// ISO 20022 reason codes banks use for rejected credit transfers
+pub const MOTIFS_REJET: [(&str, &str); 7] = [
+    ("AC01", "Numéro de compte incorrect"),
+    ("AC04", "Compte clôturé"),
+    ("AC06", "Compte bloqué"),
+    ("AG01", "Opération interdite sur ce compte"),
+    ("BE01", "Bénéficiaire différent du titulaire du compte"),
+    ("MD07", "Bénéficiaire décédé"),
+    ("MS03", "Motif non précisé"),
+];
+
+// One line of a bank return file; `rejet` holds the reason when the transfer was rejected
+#[derive(Debug, Clone)]
+pub struct LigneRetour {
+    pub ligne: usize,
+    pub rib: String,
+    pub montant: BigDecimal,
+    pub beneficiaire: Option<String>,
+    pub rejet: Option<String>,
+}
+
+// Label of a reject code, the code itself when unknown
+pub fn motif_rejet(code: &str, libelle: &str) -> String {
+    let code = code.trim();
+    let libelle = libelle.trim();
+    let connu = MOTIFS_REJET.iter().find(|(c, _)| c.eq_ignore_ascii_case(code)).map(|(_, l)| *l);
+
+    match (connu, libelle.is_empty()) {
+        (_, false) if code.is_empty() => libelle.to_string(),
+        (_, false) => format!("{} - {}", code, libelle),
+        (Some(connu), true) => format!("{} - {}", code, connu),
+        (None, true) if code.is_empty() => MOTIFS_REJET[6].1.to_string(),
+        (None, true) => format!("Rejet {}", code),
+    }
+}
+
+// A code made of zeros, or none at all, means the transfer was executed
+pub fn code_execute(code: &str) -> bool {
+    code.trim().chars().all(|c| c == '0')
+}
+
+// "1 234,50", "1234.50" and "1.234,50" all read as 1234.50
+pub fn lire_montant(texte: &str) -> Option<BigDecimal> {
+    let texte: String = texte.chars().filter(|c| !c.is_whitespace() && *c != '\u{a0}').collect();
+    let texte = match (texte.rfind(','), texte.rfind('.')) {
+        (Some(virgule), Some(point)) if virgule > point => texte.replace('.', "").replace(',', "."),
+        (Some(_), Some(_)) => texte.replace(',', ""),
+        (Some(_), None) => texte.replace(',', "."),
+        _ => texte,
+    };
+    BigDecimal::from_str(&texte).ok()
+}
+
+fn format_retour(longueur: usize) -> Option<&'static FormatFixe> {
+    FORMATS_FIXES.iter().find(|format| format.longueur == longueur)
+}
+
+// Return file in the layout of the transfer files: the bank sends back the 04
+// records with, right after the label, a 4-character status ("0000" or blank when
+// executed, a reject code otherwise) followed by the reject reason
+pub fn lire_retour_fixe(contenu: &str) -> Result<(Vec<LigneRetour>, Vec<ImportRowError>), VirementError> {
+    let mut lignes = Vec::new();
+    let mut erreurs = Vec::new();
+
+    let premiere = contenu.lines().next().unwrap_or_default().trim_end_matches('\r');
+    let format = format_retour(premiere.chars().count())
+        .ok_or_else(|| VirementError::FormatInconnu(format!("{} caractères par ligne", premiere.chars().count())))?;
+
+    let debut_montant = 32 + format.longueur_nom;
+    let debut_statut = debut_montant + 15 + format.longueur_libelle;
+
+    for (index, enregistrement) in contenu.lines().enumerate() {
+        let enregistrement: Vec<char> = enregistrement.trim_end_matches('\r').chars().collect();
+        if !enregistrement.starts_with(&['0', '4']) {
+            continue;
+        }
+
+        let champ = |debut: usize, fin: usize| -> String {
+            enregistrement
+                .get(debut..fin.min(enregistrement.len()))
+                .map(|c| c.iter().collect::<String>())
+                .unwrap_or_default()
+        };
+        let ligne = index + 1;
+        let beneficiaire = champ(32, debut_montant).trim().to_string();
+
+        let rib = match normaliser_rib(&beneficiaire, &champ(8, 32)) {
+            Ok(rib) => rib,
+            Err(e) => {
+                erreurs.push(ImportRowError { ligne, nom_prenom: Some(beneficiaire), message: e.to_string() });
+                continue;
+            }
+        };
+        let montant = match champ(debut_montant, debut_montant + 15).parse::<u64>() {
+            Ok(centimes) => BigDecimal::from(centimes) / BigDecimal::from(100),
+            Err(_) => {
+                erreurs.push(ImportRowError { ligne, nom_prenom: Some(beneficiaire), message: "Montant illisible".to_string() });
+                continue;
+            }
+        };
+
+        let code = champ(debut_statut, debut_statut + 4);
+        let rejet = (!code_execute(&code)).then(|| motif_rejet(&code, &champ(debut_statut + 4, format.longueur)));
+
+        lignes.push(LigneRetour {
+            ligne,
+            rib,
+            montant,
+            beneficiaire: Some(beneficiaire).filter(|nom| !nom.is_empty()),
+            rejet,
+        });
+    }
+
+    Ok((lignes, erreurs))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::documents::virements::tests::virement;
+    use crate::documents::virements::{grouper_par_banque, rendre_fichier_fixe, Remise};
+    use crate::documents::Employeur;
+    use chrono::NaiveDate;
+
+    // The transfer file we send, with the statuses the bank appends
+    fn retour(format: &FormatFixe, statuts: &[&str]) -> String {
+        let lots = grouper_par_banque(&[virement(1, "Amine", "Banque A", "100.10"), virement(2, "Bilal", "Banque A", "50")]).unwrap();
+        let employeur = Employeur { nom: "CHU".to_string(), adresse: String::new(), rib: "011780000012345678901234".to_string(), bic: None, identifiant_fiscal: None };
+        let remise = Remise { reference: "PAIE202406".to_string(), date_execution: NaiveDate::from_ymd_opt(2024, 6, 28).unwrap(), libelle: "Paie".to_string() };
+        let fichier = rendre_fichier_fixe(format, &employeur, &remise, &lots[0]).unwrap();
+        let debut_statut = 32 + format.longueur_nom + 15 + format.longueur_libelle;
+
+        let mut details = statuts.iter();
+        fichier
+            .split_terminator("\r\n")
+            .map(|enregistrement| match enregistrement.starts_with("04") {
+                true => {
+                    let statut = details.next().unwrap();
+                    format!("{}{:<largeur$}", &enregistrement[..debut_statut], statut, largeur = format.longueur - debut_statut)
+                }
+                false => enregistrement.to_string(),
+            })
+            .collect::<Vec<_>>()
+            .join("\r\n")
+    }
+
+    #[test]
+    fn fixed_width_returns_carry_status_and_reason() {
+        for format in &FORMATS_FIXES {
+            let (lignes, erreurs) = lire_retour_fixe(&retour(format, &["0000", "AC04"])).unwrap();
+
+            assert!(erreurs.is_empty());
+            assert_eq!(lignes.len(), 2);
+            assert_eq!(lignes[0].rib, "007780000000000000000112");
+            assert_eq!(lignes[0].montant, BigDecimal::from_str("100.10").unwrap());
+            assert_eq!(lignes[0].rejet, None);
+            assert_eq!(lignes[1].beneficiaire.as_deref(), Some("BILAL"));
+            assert_eq!(lignes[1].rejet.as_deref(), Some("AC04 - Compte clôturé"));
+        }
+    }
+
+    #[test]
+    fn unknown_line_lengths_are_refused() {
+        assert!(matches!(lire_retour_fixe("04 trop court"), Err(VirementError::FormatInconnu(_))));
+    }
+
+    #[test]
+    fn reject_reasons_and_amounts() {
+        assert!(code_execute("0000"));
+        assert!(code_execute("  "));
+        assert!(!code_execute("AC01"));
+        assert_eq!(motif_rejet("ac01", ""), "ac01 - Numéro de compte incorrect");
+        assert_eq!(motif_rejet("", "Compte soldé"), "Compte soldé");
+        assert_eq!(motif_rejet("", ""), "Motif non précisé");
+        assert_eq!(motif_rejet("X9", ""), "Rejet X9");
+
+        for (texte, attendu) in [("1 234,50", "1234.50"), ("1234.5", "1234.5"), ("1.234,50", "1234.50"), ("1,234.50", "1234.50")] {
+            assert_eq!(lire_montant(texte), Some(BigDecimal::from_str(attendu).unwrap()), "{}", texte);
+        }
+        assert_eq!(lire_montant("abc"), None);
+    }
+}
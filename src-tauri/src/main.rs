@@ -10,16 +10,22 @@ use std::env;
 
 mod commands;
 mod models;
+mod payroll;
+mod documents;
 
-use commands::login;
+use commands::{login, get_session};
 
 use commands::{
     connect_db,
     get_banques, 
+    add_banque,
+    modify_banque,
+    delete_banque,
     get_specialites, 
     add_specialite,
     get_residents,
     delete_specialite,
+    merge_specialites,
     modify_specialite, 
     add_resident,
     delete_resident,
@@ -28,7 +34,58 @@ use commands::{
     generate_payments,
     get_rappels,
     generate_rappel,
-    get_resident_id
+    get_resident_id,
+    import_residents,
+    get_baremes,
+    add_bareme,
+    modify_bareme,
+    delete_bareme,
+    get_bareme_applicable,
+    get_payroll_runs,
+    get_payroll_run_audit,
+    preview_payments,
+    change_payroll_run_status,
+    cancel_payroll_run,
+    get_absences,
+    add_absence,
+    modify_absence,
+    delete_absence,
+    get_payment_detail,
+    get_taux_cotisations,
+    add_taux_cotisation,
+    delete_taux_cotisation,
+    get_tranches_ir,
+    add_tranche_ir,
+    delete_tranche_ir,
+    get_parametres_ir,
+    add_parametre_ir,
+    delete_parametre_ir,
+    get_ajustements,
+    add_ajustement,
+    modify_ajustement,
+    delete_ajustement,
+    detect_overpayments,
+    get_recovery_schedules,
+    create_recovery_schedule,
+    generate_payslip,
+    generate_payslips,
+    generate_ov,
+    generate_ov_rappel,
+    generate_ov_par_banque,
+    generate_ov_reemission,
+    amount_in_words,
+    export_bank_files,
+    export_pain001,
+    import_bank_return,
+    export_list,
+    generate_salary_attestation,
+    get_modeles_attestation,
+    add_modele_attestation,
+    modify_modele_attestation,
+    delete_modele_attestation,
+    generate_work_certificate,
+    get_attestations_travail,
+    export_tax_declaration
 
 };
     
@@ -59,13 +116,19 @@ async fn main() {
     tauri::Builder::default()
         .manage(AppState {
             pool: Mutex::new(db_connection), // Pass the db connection to the AppState
+            session: Mutex::new(None), // Set by login, used to attribute and authorize payroll actions
         }) 
         .invoke_handler(tauri::generate_handler![
             login,
+            get_session,
             get_banques,
+            add_banque,
+            modify_banque,
+            delete_banque,
             get_specialites,
             add_specialite,
             delete_specialite,
+            merge_specialites,
             modify_specialite,
             get_residents,
             add_resident,
@@ -75,7 +138,58 @@ async fn main() {
             generate_payments,
             get_rappels,
             generate_rappel,
-            get_resident_id
+            get_resident_id,
+            import_residents,
+            get_baremes,
+            add_bareme,
+            modify_bareme,
+            delete_bareme,
+            get_bareme_applicable,
+            get_payroll_runs,
+            get_payroll_run_audit,
+            preview_payments,
+            change_payroll_run_status,
+            cancel_payroll_run,
+            get_absences,
+            add_absence,
+            modify_absence,
+            delete_absence,
+            get_payment_detail,
+            get_taux_cotisations,
+            add_taux_cotisation,
+            delete_taux_cotisation,
+            get_tranches_ir,
+            add_tranche_ir,
+            delete_tranche_ir,
+            get_parametres_ir,
+            add_parametre_ir,
+            delete_parametre_ir,
+            get_ajustements,
+            add_ajustement,
+            modify_ajustement,
+            delete_ajustement,
+            detect_overpayments,
+            get_recovery_schedules,
+            create_recovery_schedule,
+            generate_payslip,
+            generate_payslips,
+            generate_ov,
+            generate_ov_rappel,
+            generate_ov_par_banque,
+            generate_ov_reemission,
+            amount_in_words,
+            export_bank_files,
+            export_pain001,
+            import_bank_return,
+            export_list,
+            generate_salary_attestation,
+            get_modeles_attestation,
+            add_modele_attestation,
+            modify_modele_attestation,
+            delete_modele_attestation,
+            generate_work_certificate,
+            get_attestations_travail,
+            export_tax_declaration
         ])
         .run(tauri::generate_context!())
         .expect("error while running tauri application");
@@ -0,0 +1,23 @@
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Absence {
+    pub id_absence: i32,
+    pub id_resident: i32,
+    pub nom_resident: Option<String>,
+    pub type_absence: String,
+    pub date_debut: NaiveDate,
+    pub date_fin: NaiveDate,
+    pub motif: Option<String>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewAbsence {
+    pub id_resident: i32,
+    pub type_absence: String,
+    pub date_debut: NaiveDate,
+    pub date_fin: NaiveDate,
+    pub motif: Option<String>,
+}
@@ -0,0 +1,29 @@
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+// `periode` is the first day of the month whose payroll applies the adjustment,
+// `id_paiement` the payment it ended up on
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Ajustement {
+    pub id_ajustement: i32,
+    pub id_resident: i32,
+    pub nom_resident: Option<String>,
+    pub periode: NaiveDate,
+    pub type_ajustement: String,
+    pub montant: BigDecimal,
+    pub motif: String,
+    pub created_by: Option<String>,
+    pub created_at: Option<NaiveDateTime>,
+    pub id_paiement: Option<i32>,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewAjustement {
+    pub id_resident: i32,
+    pub periode: NaiveDate,
+    pub type_ajustement: String,
+    pub montant: BigDecimal,
+    pub motif: String,
+}
@@ -0,0 +1,34 @@
+use chrono::NaiveDateTime;
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+// Work certificate template; `corps` holds one paragraph per line and `{champ}` placeholders
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ModeleAttestation {
+    pub id_modele: i32,
+    pub nom: String,
+    pub titre: String,
+    pub corps: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewModeleAttestation {
+    pub nom: String,
+    pub titre: String,
+    pub corps: String,
+}
+
+// Entry of the register of issued work certificates
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct AttestationTravailEmise {
+    pub id_attestation: i32,
+    pub exercice: i32,
+    pub numero: i32,
+    pub id_resident: Option<i32>,
+    pub nom_prenom: String,
+    pub id_modele: Option<i32>,
+    pub nom_modele: Option<String>,
+    pub en_fonction: bool,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+}
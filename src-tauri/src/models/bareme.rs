@@ -0,0 +1,28 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct Bareme {
+    pub id_bareme: i32,
+    pub annee_residanat: i32,
+    pub id_specialite: Option<i32>,
+    pub nom_specialite: Option<String>,
+    pub salaire_base: BigDecimal,
+    pub indemnite_fonction: BigDecimal,
+    pub indemnite_logement: BigDecimal,
+    pub indemnite_transport: BigDecimal,
+    pub date_effet: NaiveDate,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewBareme {
+    pub annee_residanat: i32,
+    pub id_specialite: Option<i32>,
+    pub salaire_base: BigDecimal,
+    pub indemnite_fonction: BigDecimal,
+    pub indemnite_logement: BigDecimal,
+    pub indemnite_transport: BigDecimal,
+    pub date_effet: NaiveDate,
+}
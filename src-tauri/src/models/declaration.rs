@@ -0,0 +1,26 @@
+use bigdecimal::BigDecimal;
+use serde::Serialize;
+
+// A problem found on the declaration; blocking ones would be rejected by the tax office
+#[derive(Debug, Clone, Serialize)]
+pub struct AnomalieDeclaration {
+    pub id_resident: Option<i32>,
+    pub nom_prenom: String,
+    pub champ: String,
+    pub message: String,
+    pub bloquante: bool,
+}
+
+// Totals of the annual salary declaration and its validation report. `fichier` is
+// empty when only the check was asked for, or when blocking problems kept the XML
+// from being written.
+#[derive(Debug, Serialize)]
+pub struct RapportDeclaration {
+    pub annee: i32,
+    pub nombre_salaries: usize,
+    pub brut_imposable: BigDecimal,
+    pub net_imposable: BigDecimal,
+    pub ir: BigDecimal,
+    pub fichier: Option<String>,
+    pub anomalies: Vec<AnomalieDeclaration>,
+}
@@ -0,0 +1,13 @@
+use serde::Deserialize;
+
+// The filters of the list pages: name search, then year and month of the
+// payment (or generation) date
+#[derive(Debug, Default, Deserialize)]
+pub struct FiltreListe {
+    #[serde(default)]
+    pub recherche: Option<String>,
+    #[serde(default)]
+    pub annee: Option<i32>,
+    #[serde(default)]
+    pub mois: Option<u32>,
+}
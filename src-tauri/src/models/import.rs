@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+#[derive(Debug, Serialize)]
+pub struct ImportRowError {
+    pub ligne: usize,
+    pub nom_prenom: Option<String>,
+    pub message: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct ImportReport {
+    pub total_lignes: usize,
+    pub lignes_valides: usize,
+    pub lignes_importees: usize,
+    pub dry_run: bool,
+    pub erreurs: Vec<ImportRowError>,
+}
@@ -2,6 +2,18 @@ pub mod login_payload;
 pub mod specialty;
 pub mod resident;
 pub mod payments;
+pub mod import;
+pub mod bareme;
+pub mod payroll_run;
+pub mod session;
+pub mod absence;
+pub mod retenue;
+pub mod ajustement;
+pub mod recouvrement;
+pub mod virement;
+pub mod export;
+pub mod attestation;
+pub mod declaration;
 
 pub use login_payload::LoginPayload;
 pub use specialty::Specialite;
@@ -9,8 +21,26 @@ pub use resident::Resident;
 pub use specialty:: NewSpecialite;
 pub use resident:: NewResident;
 pub use specialty::Banque;
+pub use specialty::NewBanque;
 pub use payments::PaiementMensuel;
+pub use payments::NewPaiementMensuel;
+pub use payments::{LignePaiement, PaiementDetail};
 pub use payments::RappelAnnuel;
+pub use import::{ImportReport, ImportRowError};
+pub use export::FiltreListe;
+pub use attestation::{AttestationTravailEmise, ModeleAttestation, NewModeleAttestation};
+pub use declaration::{AnomalieDeclaration, RapportDeclaration};
+pub use bareme::{Bareme, NewBareme};
+pub use payroll_run::{PayrollRun, PayrollRunAudit};
+pub use session::{Role, Session};
+pub use absence::{Absence, NewAbsence};
+pub use ajustement::{Ajustement, NewAjustement};
+pub use recouvrement::{EcheanceRecouvrement, NewRecouvrement, Recouvrement};
+pub use virement::{
+    DemandeReemission, ExportOrdres, ExportVirements, LigneNonRapprochee, OrdreBanque, OrdreReemission, RapportRetour,
+    TotalBanque,
+};
+pub use retenue::{NewParametreIr, NewTauxCotisation, NewTrancheIr, ParametreIr, TauxCotisation, TrancheIr};
 
 use serde::Serialize;
 use tokio::sync::Mutex;
@@ -30,4 +60,5 @@ impl From<sqlx::Error> for MyError {
 
 pub struct AppState {
     pub pool: Mutex<Option<sqlx::PgPool>>,
+    pub session: Mutex<Option<Session>>,
 }
\ No newline at end of file
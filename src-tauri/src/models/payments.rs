@@ -15,6 +15,54 @@ pub struct PaiementMensuel {
     pub nom_resident: Option<String>,
     pub rib: String,
     pub nom_banque: Option<String>,
+    pub id_bareme: Option<i32>,
+    pub salaire_base: Option<BigDecimal>,
+    pub indemnite_fonction: Option<BigDecimal>,
+    pub indemnite_logement: Option<BigDecimal>,
+    pub indemnite_transport: Option<BigDecimal>,
+    pub salaire_brut: Option<BigDecimal>,
+    pub total_retenues: Option<BigDecimal>,
+    // en_attente, paye or rejete from the bank's return files, reemis once reissued
+    pub statut_virement: String,
+    pub motif_rejet: Option<String>,
+}
+
+// One earning or deduction of a payment, `categorie` is "gain" or "retenue"
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct LignePaiement {
+    pub code: String,
+    pub libelle: String,
+    pub categorie: String,
+    pub base: Option<BigDecimal>,
+    pub taux: Option<BigDecimal>,
+    pub montant: BigDecimal,
+}
+
+// A payment with its gross-to-net breakdown
+#[derive(Debug, Serialize)]
+pub struct PaiementDetail {
+    pub paiement: PaiementMensuel,
+    pub lignes: Vec<LignePaiement>,
+}
+
+// A payment computed by the payroll engine, before it is persisted
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct NewPaiementMensuel {
+    pub id_resident: i32,
+    pub nom_resident: String,
+    pub id_bareme: Option<i32>,
+    pub jours_travail: i32,
+    pub salaire_base: BigDecimal,
+    pub indemnite_fonction: BigDecimal,
+    pub indemnite_logement: BigDecimal,
+    pub indemnite_transport: BigDecimal,
+    pub allocations_familiales: BigDecimal,
+    pub salaire_brut: BigDecimal,
+    pub total_retenues: BigDecimal,
+    pub montant: BigDecimal,
+    pub date_paiement: NaiveDate,
+    pub lignes: Vec<LignePaiement>,
+    pub id_ajustements: Vec<i32>,
 }
 
 
@@ -29,5 +77,8 @@ pub struct RappelAnnuel{
     pub rib: String,
     pub nom_banque: Option<String>,
     pub date_generation: Option<NaiveDate>,
+    pub retenue_recouvrement: BigDecimal,
+    pub statut_virement: String,
+    pub motif_rejet: Option<String>,
 }
 
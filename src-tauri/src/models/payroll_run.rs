@@ -0,0 +1,28 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDateTime;
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PayrollRun {
+    pub id_run: i32,
+    pub annee: i32,
+    pub mois: i32,
+    pub statut: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub nombre_paiements: i32,
+    pub total_montant: BigDecimal,
+    pub total_allocations: BigDecimal,
+}
+
+#[derive(Debug, Serialize, Deserialize, FromRow)]
+pub struct PayrollRunAudit {
+    pub id_audit: i32,
+    pub id_run: i32,
+    pub action: String,
+    pub auteur: String,
+    pub motif: Option<String>,
+    pub details: Option<String>,
+    pub date_action: NaiveDateTime,
+}
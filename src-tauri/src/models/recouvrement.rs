@@ -0,0 +1,45 @@
+use bigdecimal::BigDecimal;
+use chrono::{NaiveDate, NaiveDateTime};
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+// `periode` is set for installments deducted from a monthly payroll,
+// `exercice` for those deducted from the annual rappel; `recouvre` once the
+// payroll run holding it is locked or the rappel has been generated
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct EcheanceRecouvrement {
+    pub id_echeance: i32,
+    pub id_recouvrement: i32,
+    pub rang: i32,
+    pub montant: BigDecimal,
+    pub periode: Option<NaiveDate>,
+    pub exercice: Option<i32>,
+    pub id_ajustement: Option<i32>,
+    pub id_rappel: Option<i32>,
+    pub recouvre: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct Recouvrement {
+    pub id_recouvrement: i32,
+    pub id_resident: i32,
+    pub nom_resident: Option<String>,
+    pub montant_total: BigDecimal,
+    pub imputation: String,
+    pub motif: String,
+    pub created_by: String,
+    pub created_at: NaiveDateTime,
+    pub echeances: Vec<EcheanceRecouvrement>,
+}
+
+// `premiere_periode` is the first month (imputation "paie") or any date of the
+// first exercice (imputation "rappel") to deduct from
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewRecouvrement {
+    pub id_resident: i32,
+    pub id_paiements: Vec<i32>,
+    pub nombre_echeances: i32,
+    pub premiere_periode: NaiveDate,
+    pub imputation: String,
+    pub motif: String,
+}
@@ -14,6 +14,11 @@ pub struct Resident {
     pub nombre_enfants: i32,
     pub nom_specialite: Option<String>,
     pub nom_banque: Option<String>,
+    // National identity card number and home address, for the annual tax declaration
+    #[serde(default)]
+    pub cin: Option<String>,
+    #[serde(default)]
+    pub adresse: Option<String>,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -24,4 +29,8 @@ pub struct NewResident {
     pub rib: String,
     pub id_banque: Option<i32>,
     pub nombre_enfants: Option<i32>,
+    #[serde(default)]
+    pub cin: Option<String>,
+    #[serde(default)]
+    pub adresse: Option<String>,
 }
@@ -0,0 +1,66 @@
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde::{Serialize, Deserialize};
+use sqlx::FromRow;
+
+// Contribution rate (CMR, CNSS, AMO, mutuelle) in percent of `assiette`
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TauxCotisation {
+    pub id_taux: i32,
+    pub code: String,
+    pub libelle: String,
+    pub taux: BigDecimal,
+    pub assiette: String,
+    pub plafond: Option<BigDecimal>,
+    pub date_effet: NaiveDate,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewTauxCotisation {
+    pub code: String,
+    pub libelle: String,
+    pub taux: BigDecimal,
+    pub assiette: String,
+    pub plafond: Option<BigDecimal>,
+    pub date_effet: NaiveDate,
+}
+
+// Annual income tax bracket, `revenu_max` is empty for the last one
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct TrancheIr {
+    pub id_tranche: i32,
+    pub revenu_min: BigDecimal,
+    pub revenu_max: Option<BigDecimal>,
+    pub taux: BigDecimal,
+    pub somme_a_deduire: BigDecimal,
+    pub date_effet: NaiveDate,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewTrancheIr {
+    pub revenu_min: BigDecimal,
+    pub revenu_max: Option<BigDecimal>,
+    pub taux: BigDecimal,
+    pub somme_a_deduire: BigDecimal,
+    pub date_effet: NaiveDate,
+}
+
+// Annual family deduction per dependent and monthly professional expenses allowance
+#[derive(Debug, Clone, Serialize, Deserialize, FromRow)]
+pub struct ParametreIr {
+    pub id_parametre: i32,
+    pub deduction_par_charge: BigDecimal,
+    pub max_charges: i32,
+    pub taux_frais_pro: BigDecimal,
+    pub plafond_frais_pro: BigDecimal,
+    pub date_effet: NaiveDate,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct NewParametreIr {
+    pub deduction_par_charge: BigDecimal,
+    pub max_charges: i32,
+    pub taux_frais_pro: BigDecimal,
+    pub plafond_frais_pro: BigDecimal,
+    pub date_effet: NaiveDate,
+}
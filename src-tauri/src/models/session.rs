@@ -0,0 +1,28 @@
+use serde::{Serialize, Deserialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Role {
+    Admin,
+    Gestionnaire,
+    Validateur,
+    Tresorier,
+}
+
+impl Role {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "admin" => Some(Role::Admin),
+            "gestionnaire" => Some(Role::Gestionnaire),
+            "validateur" => Some(Role::Validateur),
+            "tresorier" => Some(Role::Tresorier),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct Session {
+    pub username: String,
+    pub role: Role,
+}
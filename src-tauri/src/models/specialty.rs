@@ -30,6 +30,12 @@ pub struct NewSpecialite {
 pub struct Banque {
     pub id_banque: i32,
     pub nom: String,
+    pub code_banque: Option<String>,
+    pub swift_bic: Option<String>,
+    pub agence: Option<String>,
+    // Layout of its bulk-transfer files, see documents::virements::FORMATS_FIXES
+    #[serde(default)]
+    pub format_virement: Option<String>,
 }
 
 impl FromRow<'_, sqlx::postgres::PgRow> for Banque {
@@ -37,7 +43,21 @@ impl FromRow<'_, sqlx::postgres::PgRow> for Banque {
         Ok(Self {
             id_banque: row.try_get("id_banque")?,
             nom: row.try_get("nom")?,
+            code_banque: row.try_get("code_banque")?,
+            swift_bic: row.try_get("swift_bic")?,
+            agence: row.try_get("agence")?,
+            format_virement: row.try_get("format_virement")?,
         })
     }
 }
 
+#[derive(Serialize, Deserialize)]
+pub struct NewBanque {
+    pub nom: String,
+    pub code_banque: Option<String>,
+    pub swift_bic: Option<String>,
+    pub agence: Option<String>,
+    #[serde(default)]
+    pub format_virement: Option<String>,
+}
+
@@ -0,0 +1,82 @@
+use crate::models::ImportRowError;
+use bigdecimal::BigDecimal;
+use serde::{Deserialize, Serialize};
+
+// Control totals of the transfers sent to one bank
+#[derive(Debug, Serialize)]
+pub struct TotalBanque {
+    pub nom_banque: String,
+    pub nombre_virements: i32,
+    pub total: BigDecimal,
+}
+
+// Result of a bulk-transfer export: what was written and the totals to check
+// against the bank's acknowledgement
+#[derive(Debug, Serialize)]
+pub struct ExportVirements {
+    pub reference: String,
+    pub fichiers: Vec<String>,
+    pub banques: Vec<TotalBanque>,
+    pub nombre_virements: i32,
+    pub total: BigDecimal,
+}
+
+// A transfer order exported for one bank
+#[derive(Debug, Serialize)]
+pub struct OrdreBanque {
+    pub numero: i32,
+    pub exercice: i32,
+    pub nom_banque: String,
+    pub nombre_virements: i32,
+    pub total: BigDecimal,
+    pub fichier: String,
+}
+
+// Result of the per-bank OV export, with the treasury summary workbook
+#[derive(Debug, Serialize)]
+pub struct ExportOrdres {
+    pub ordres: Vec<OrdreBanque>,
+    pub recapitulatif: String,
+    pub nombre_virements: i32,
+    pub total: BigDecimal,
+}
+
+// A line of a bank return file that matched no pending transfer
+#[derive(Debug, Serialize)]
+pub struct LigneNonRapprochee {
+    pub ligne: usize,
+    pub rib: String,
+    pub montant: BigDecimal,
+    pub beneficiaire: Option<String>,
+    pub motif_rejet: Option<String>,
+}
+
+// Result of a bank return import; nothing is written on a dry run
+#[derive(Debug, Serialize)]
+pub struct RapportRetour {
+    pub total_lignes: usize,
+    pub payes: usize,
+    pub rejetes: usize,
+    pub dry_run: bool,
+    pub non_rapprochees: Vec<LigneNonRapprochee>,
+    pub erreurs: Vec<ImportRowError>,
+}
+
+// A rejected payment or rappel to reissue, with the corrected RIB when it changed
+#[derive(Debug, Deserialize)]
+pub struct DemandeReemission {
+    pub id: i32,
+    #[serde(default)]
+    pub rib: Option<String>,
+}
+
+// Transfer order written for reissued transfers
+#[derive(Debug, Serialize)]
+pub struct OrdreReemission {
+    pub numero: i32,
+    pub exercice: i32,
+    pub fichier: String,
+    pub nombre_virements: i32,
+    pub total: BigDecimal,
+    pub ribs_corriges: i32,
+}
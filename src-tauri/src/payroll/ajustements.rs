@@ -0,0 +1,37 @@
+use crate::models::{Ajustement, LignePaiement};
+use crate::payroll::Periode;
+
+pub const TYPES_AJUSTEMENT: [&str; 3] = ["prime", "garde", "retenue"];
+
+pub fn est_retenue(ajustement: &Ajustement) -> bool {
+    ajustement.type_ajustement == "retenue"
+}
+
+// Adjustments a resident receives in the payroll of `periode`
+pub fn ajustements_du_mois(
+    ajustements: &[Ajustement],
+    id_resident: i32,
+    periode: Periode,
+) -> impl Iterator<Item = &Ajustement> {
+    ajustements
+        .iter()
+        .filter(move |a| a.id_resident == id_resident && a.periode == periode.debut())
+}
+
+// Adjustments are shown on their own payslip line, labelled with their reason
+pub fn ligne_ajustement(ajustement: &Ajustement) -> LignePaiement {
+    let (code, libelle, categorie) = match ajustement.type_ajustement.as_str() {
+        "garde" => ("GARDE", "Indemnité de garde", "gain"),
+        "retenue" => ("RETENUE", "Retenue", "retenue"),
+        _ => ("PRIME", "Prime exceptionnelle", "gain"),
+    };
+
+    LignePaiement {
+        code: code.to_string(),
+        libelle: format!("{} : {}", libelle, ajustement.motif),
+        categorie: categorie.to_string(),
+        base: None,
+        taux: None,
+        montant: ajustement.montant.clone(),
+    }
+}
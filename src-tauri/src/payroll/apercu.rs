@@ -0,0 +1,274 @@
+use crate::models::NewPaiementMensuel;
+use crate::payroll::engine::{AnomaliePaie, ResidentPaie, ResultatPaie};
+use crate::payroll::Periode;
+use bigdecimal::{BigDecimal, Zero};
+use serde::Serialize;
+use std::collections::{BTreeMap, HashMap};
+
+// Amount actually paid to a resident for the previous month
+#[derive(Debug, Clone)]
+pub struct PaiementPrecedent {
+    pub id_resident: i32,
+    pub nom_prenom: String,
+    pub montant: BigDecimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TotalGroupe {
+    pub libelle: String,
+    pub nombre: usize,
+    pub total: BigDecimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct MouvementResident {
+    pub id_resident: i32,
+    pub nom_prenom: String,
+    pub montant: BigDecimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct EcartMontant {
+    pub id_resident: i32,
+    pub nom_prenom: String,
+    pub montant_precedent: BigDecimal,
+    pub montant: BigDecimal,
+    pub ecart: BigDecimal,
+}
+
+// What a payroll run would create, compared with the month before
+#[derive(Debug, Serialize)]
+pub struct ApercuPaie {
+    pub periode: Periode,
+    pub lignes: Vec<NewPaiementMensuel>,
+    pub anomalies: Vec<AnomaliePaie>,
+    pub nombre_paiements: usize,
+    pub total: BigDecimal,
+    pub totaux_par_banque: Vec<TotalGroupe>,
+    pub totaux_par_specialite: Vec<TotalGroupe>,
+    pub nouveaux: Vec<MouvementResident>,
+    pub sortants: Vec<MouvementResident>,
+    pub montants_modifies: Vec<EcartMontant>,
+}
+
+fn totaux_par<'a>(lignes: impl Iterator<Item = (String, &'a BigDecimal)>) -> Vec<TotalGroupe> {
+    let mut groupes: BTreeMap<String, (usize, BigDecimal)> = BTreeMap::new();
+
+    for (libelle, montant) in lignes {
+        let groupe = groupes.entry(libelle).or_insert_with(|| (0, BigDecimal::zero()));
+        groupe.0 += 1;
+        groupe.1 += montant;
+    }
+
+    groupes
+        .into_iter()
+        .map(|(libelle, (nombre, total))| TotalGroupe { libelle, nombre, total })
+        .collect()
+}
+
+pub fn construire_apercu(
+    periode: Periode,
+    resultat: ResultatPaie,
+    residents: &[ResidentPaie],
+    precedents: &[PaiementPrecedent],
+) -> ApercuPaie {
+    let residents: HashMap<i32, &ResidentPaie> = residents.iter().map(|r| (r.id_resident, r)).collect();
+
+    // Legacy months may hold several rows per resident, compare what was paid in total
+    let mut paye_precedent: HashMap<i32, (String, BigDecimal)> = HashMap::new();
+    for paiement in precedents {
+        let entry = paye_precedent
+            .entry(paiement.id_resident)
+            .or_insert_with(|| (paiement.nom_prenom.clone(), BigDecimal::zero()));
+        entry.1 += &paiement.montant;
+    }
+
+    let totaux_par_banque = totaux_par(resultat.lignes.iter().map(|ligne| {
+        let banque = residents
+            .get(&ligne.id_resident)
+            .and_then(|r| r.nom_banque.clone())
+            .unwrap_or_else(|| "Sans banque".to_string());
+        (banque, &ligne.montant)
+    }));
+
+    let totaux_par_specialite = totaux_par(resultat.lignes.iter().map(|ligne| {
+        let specialite = residents
+            .get(&ligne.id_resident)
+            .and_then(|r| r.nom_specialite.clone())
+            .unwrap_or_else(|| "Sans spécialité".to_string());
+        (specialite, &ligne.montant)
+    }));
+
+    let mut nouveaux = Vec::new();
+    let mut montants_modifies = Vec::new();
+
+    for ligne in &resultat.lignes {
+        match paye_precedent.get(&ligne.id_resident) {
+            None => nouveaux.push(MouvementResident {
+                id_resident: ligne.id_resident,
+                nom_prenom: ligne.nom_resident.clone(),
+                montant: ligne.montant.clone(),
+            }),
+            Some((_, montant_precedent)) if montant_precedent != &ligne.montant => {
+                montants_modifies.push(EcartMontant {
+                    id_resident: ligne.id_resident,
+                    nom_prenom: ligne.nom_resident.clone(),
+                    montant_precedent: montant_precedent.clone(),
+                    montant: ligne.montant.clone(),
+                    ecart: &ligne.montant - montant_precedent,
+                })
+            }
+            Some(_) => {}
+        }
+    }
+
+    let mut sortants: Vec<MouvementResident> = paye_precedent
+        .into_iter()
+        .filter(|(id_resident, _)| !resultat.lignes.iter().any(|ligne| ligne.id_resident == *id_resident))
+        .map(|(id_resident, (nom_prenom, montant))| MouvementResident {
+            id_resident,
+            nom_prenom,
+            montant,
+        })
+        .collect();
+    sortants.sort_by(|a, b| a.nom_prenom.cmp(&b.nom_prenom));
+
+    let total = resultat
+        .lignes
+        .iter()
+        .fold(BigDecimal::zero(), |total, ligne| total + &ligne.montant);
+
+    ApercuPaie {
+        periode,
+        nombre_paiements: resultat.lignes.len(),
+        total,
+        totaux_par_banque,
+        totaux_par_specialite,
+        nouveaux,
+        sortants,
+        montants_modifies,
+        lignes: resultat.lignes,
+        anomalies: resultat.anomalies,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::NaiveDate;
+    use std::str::FromStr;
+
+    fn dec(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    fn resident(id_resident: i32, specialite: Option<&str>, banque: Option<&str>) -> ResidentPaie {
+        ResidentPaie {
+            id_resident,
+            nom_prenom: format!("Résident {}", id_resident),
+            date_debut: NaiveDate::from_ymd_opt(2023, 11, 1).unwrap(),
+            date_fin: None,
+            id_specialite: Some(1),
+            nombre_annees: 4,
+            nombre_enfants: 0,
+            nom_specialite: specialite.map(str::to_string),
+            nom_banque: banque.map(str::to_string),
+        }
+    }
+
+    fn ligne(id_resident: i32, montant: &str) -> NewPaiementMensuel {
+        NewPaiementMensuel {
+            id_resident,
+            nom_resident: format!("Résident {}", id_resident),
+            id_bareme: None,
+            jours_travail: 30,
+            salaire_base: dec(montant),
+            indemnite_fonction: BigDecimal::zero(),
+            indemnite_logement: BigDecimal::zero(),
+            indemnite_transport: BigDecimal::zero(),
+            allocations_familiales: BigDecimal::zero(),
+            salaire_brut: dec(montant),
+            total_retenues: BigDecimal::zero(),
+            montant: dec(montant),
+            date_paiement: NaiveDate::from_ymd_opt(2024, 6, 30).unwrap(),
+            lignes: Vec::new(),
+            id_ajustements: Vec::new(),
+        }
+    }
+
+    fn precedent(id_resident: i32, montant: &str) -> PaiementPrecedent {
+        PaiementPrecedent {
+            id_resident,
+            nom_prenom: format!("Résident {}", id_resident),
+            montant: dec(montant),
+        }
+    }
+
+    fn apercu(lignes: Vec<NewPaiementMensuel>, residents: &[ResidentPaie], precedents: &[PaiementPrecedent]) -> ApercuPaie {
+        let resultat = ResultatPaie {
+            lignes,
+            anomalies: Vec::new(),
+        };
+        construire_apercu(Periode::new(2024, 6).unwrap(), resultat, residents, precedents)
+    }
+
+    fn groupes(totaux: &[TotalGroupe]) -> Vec<(&str, usize, BigDecimal)> {
+        totaux
+            .iter()
+            .map(|groupe| (groupe.libelle.as_str(), groupe.nombre, groupe.total.clone()))
+            .collect()
+    }
+
+    #[test]
+    fn totals_are_grouped_by_bank_and_specialty() {
+        let residents = vec![
+            resident(1, Some("Pédiatrie"), Some("CIH")),
+            resident(2, Some("Chirurgie"), Some("Attijariwafa")),
+            resident(3, Some("Pédiatrie"), Some("CIH")),
+            resident(4, None, None),
+        ];
+        let lignes = vec![ligne(1, "9000.50"), ligne(2, "8000"), ligne(3, "7000"), ligne(4, "100")];
+
+        let apercu = apercu(lignes, &residents, &[]);
+
+        assert_eq!(apercu.nombre_paiements, 4);
+        assert_eq!(apercu.total, dec("24100.50"));
+        assert_eq!(
+            groupes(&apercu.totaux_par_banque),
+            vec![("Attijariwafa", 1, dec("8000")), ("CIH", 2, dec("16000.50")), ("Sans banque", 1, dec("100"))]
+        );
+        assert_eq!(
+            groupes(&apercu.totaux_par_specialite),
+            vec![("Chirurgie", 1, dec("8000")), ("Pédiatrie", 2, dec("16000.50")), ("Sans spécialité", 1, dec("100"))]
+        );
+    }
+
+    #[test]
+    fn residents_are_compared_with_the_previous_month() {
+        let residents: Vec<ResidentPaie> = (1..=4).map(|id| resident(id, None, None)).collect();
+        let lignes = vec![ligne(1, "9000"), ligne(2, "8500"), ligne(4, "7000")];
+        // Resident 2 was paid in two legacy rows totalling 8000
+        let precedents = vec![
+            precedent(1, "9000"),
+            precedent(2, "5000"),
+            precedent(2, "3000"),
+            precedent(3, "6000"),
+            precedent(5, "4000"),
+        ];
+
+        let apercu = apercu(lignes, &residents, &precedents);
+
+        let nouveaux: Vec<(i32, BigDecimal)> = apercu.nouveaux.iter().map(|m| (m.id_resident, m.montant.clone())).collect();
+        assert_eq!(nouveaux, vec![(4, dec("7000"))]);
+
+        let sortants: Vec<(i32, BigDecimal)> = apercu.sortants.iter().map(|m| (m.id_resident, m.montant.clone())).collect();
+        assert_eq!(sortants, vec![(3, dec("6000")), (5, dec("4000"))]);
+
+        assert_eq!(apercu.montants_modifies.len(), 1);
+        let ecart = &apercu.montants_modifies[0];
+        assert_eq!(ecart.id_resident, 2);
+        assert_eq!(ecart.montant_precedent, dec("8000"));
+        assert_eq!(ecart.montant, dec("8500"));
+        assert_eq!(ecart.ecart, dec("500"));
+    }
+}
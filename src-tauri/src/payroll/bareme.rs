@@ -0,0 +1,39 @@
+use crate::models::Bareme;
+use chrono::{Datelike, NaiveDate};
+
+// Residency year reached during the month of `periode`, counted in whole months since `date_debut`
+// and capped at the specialty's duration
+pub fn annee_residanat(date_debut: NaiveDate, periode: NaiveDate, nombre_annees: i32) -> i32 {
+    let mois_ecoules = (periode.year() - date_debut.year()) * 12
+        + periode.month() as i32
+        - date_debut.month() as i32;
+
+    (mois_ecoules.max(0) / 12 + 1).min(nombre_annees.max(1))
+}
+
+// Grid row in force for a payment month: the most recent row effective on or before the month.
+// A row specific to the resident's specialty always wins over the generic grid for the same year.
+pub fn bareme_applicable(
+    grille: &[Bareme],
+    annee: i32,
+    id_specialite: Option<i32>,
+    periode: NaiveDate,
+) -> Option<&Bareme> {
+    let debut_mois = periode.with_day(1)?;
+
+    let plus_recent = |specifique: bool| {
+        grille
+            .iter()
+            .filter(|row| row.annee_residanat == annee && row.date_effet <= debut_mois)
+            .filter(|row| {
+                if specifique {
+                    row.id_specialite.is_some() && row.id_specialite == id_specialite
+                } else {
+                    row.id_specialite.is_none()
+                }
+            })
+            .max_by_key(|row| row.date_effet)
+    };
+
+    plus_recent(true).or_else(|| plus_recent(false))
+}
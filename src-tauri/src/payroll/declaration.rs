@@ -0,0 +1,283 @@
+use crate::models::AnomalieDeclaration;
+use bigdecimal::{BigDecimal, Zero};
+use std::collections::HashMap;
+
+// A payment or a rappel of the year, in the terms of the declaration
+#[derive(Debug, Clone, Default)]
+pub struct ElementDeclare {
+    pub salaire_base: BigDecimal,
+    pub brut_imposable: BigDecimal,
+    // Family allowances, paid free of tax
+    pub exonere: BigDecimal,
+    pub frais_professionnels: BigDecimal,
+    pub cotisations: BigDecimal,
+    pub ir: BigDecimal,
+    // Adjustments and recovered overpayments, which do not reduce the taxable income
+    pub autres_retenues: BigDecimal,
+    pub jours: i32,
+    // Paid before deductions were itemised: all of them count as other deductions
+    pub sans_detail: bool,
+    // Paid before payroll runs existed, so never validated through one
+    pub hors_run: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct SalarieDeclare {
+    pub id_resident: i32,
+    pub nom_prenom: String,
+    pub cin: Option<String>,
+    pub adresse: Option<String>,
+    pub nombre_charges: i32,
+}
+
+// Yearly totals of one resident, one row of the declaration
+#[derive(Debug, Clone)]
+pub struct LigneDeclaration {
+    pub salarie: SalarieDeclare,
+    pub nom: String,
+    pub prenom: String,
+    pub jours: i32,
+    pub salaire_base: BigDecimal,
+    pub brut: BigDecimal,
+    pub exonere: BigDecimal,
+    pub brut_imposable: BigDecimal,
+    pub frais_professionnels: BigDecimal,
+    pub cotisations: BigDecimal,
+    pub net_imposable: BigDecimal,
+    pub ir: BigDecimal,
+    pub autres_retenues: BigDecimal,
+    pub sans_detail: usize,
+    pub hors_run: usize,
+}
+
+impl LigneDeclaration {
+    // Deductions from the taxable gross: professional expenses and contributions
+    pub fn total_deductions(&self) -> BigDecimal {
+        &self.frais_professionnels + &self.cotisations
+    }
+}
+
+// Names are entered family name first. Leading words in capitals are the family
+// name ("EL ALAMI Sara"); otherwise the first word is.
+pub fn separer_nom(nom_prenom: &str) -> (String, String) {
+    let mots: Vec<&str> = nom_prenom.split_whitespace().collect();
+    let majuscules = mots
+        .iter()
+        .take_while(|mot| mot.chars().any(char::is_alphabetic) && !mot.chars().any(char::is_lowercase))
+        .count();
+    let coupure = if majuscules == 0 || majuscules == mots.len() { 1.min(mots.len()) } else { majuscules };
+
+    (mots[..coupure].join(" "), mots[coupure..].join(" "))
+}
+
+// National identity card number: one or two letters then one to six digits
+pub fn cin_valide(cin: &str) -> bool {
+    let lettres = cin.chars().take_while(|c| c.is_ascii_uppercase()).count();
+    let chiffres = &cin[lettres..];
+
+    (1..=2).contains(&lettres) && (1..=6).contains(&chiffres.len()) && chiffres.chars().all(|c| c.is_ascii_digit())
+}
+
+pub fn declarer(salarie: SalarieDeclare, elements: &[ElementDeclare]) -> LigneDeclaration {
+    let somme = |montant: fn(&ElementDeclare) -> &BigDecimal| {
+        elements.iter().fold(BigDecimal::zero(), |total, element| total + montant(element))
+    };
+
+    let brut_imposable = somme(|e| &e.brut_imposable);
+    let exonere = somme(|e| &e.exonere);
+    let frais_professionnels = somme(|e| &e.frais_professionnels);
+    let cotisations = somme(|e| &e.cotisations);
+    let net_imposable = &brut_imposable - &frais_professionnels - &cotisations;
+    let (nom, prenom) = separer_nom(&salarie.nom_prenom);
+
+    LigneDeclaration {
+        nom,
+        prenom,
+        jours: elements.iter().map(|e| e.jours).sum(),
+        salaire_base: somme(|e| &e.salaire_base),
+        brut: &brut_imposable + &exonere,
+        exonere,
+        net_imposable: if net_imposable < BigDecimal::zero() { BigDecimal::zero() } else { net_imposable },
+        brut_imposable,
+        frais_professionnels,
+        cotisations,
+        ir: somme(|e| &e.ir),
+        autres_retenues: somme(|e| &e.autres_retenues),
+        sans_detail: elements.iter().filter(|e| e.sans_detail).count(),
+        hors_run: elements.iter().filter(|e| e.hors_run).count(),
+        salarie,
+    }
+}
+
+// Everything the tax office would reject, and what deserves a second look
+pub fn controler(identifiant_fiscal: Option<&str>, lignes: &[LigneDeclaration]) -> Vec<AnomalieDeclaration> {
+    let mut anomalies = Vec::new();
+    let mut anomalie = |ligne: Option<&LigneDeclaration>, champ: &str, message: String, bloquante: bool| {
+        anomalies.push(AnomalieDeclaration {
+            id_resident: ligne.map(|l| l.salarie.id_resident),
+            nom_prenom: ligne.map_or_else(|| "Employeur".to_string(), |l| l.salarie.nom_prenom.clone()),
+            champ: champ.to_string(),
+            message,
+            bloquante,
+        })
+    };
+
+    if identifiant_fiscal.unwrap_or_default().trim().is_empty() {
+        anomalie(None, "identifiant_fiscal", "Identifiant fiscal de l'employeur non renseigné (EMPLOYEUR_IF)".to_string(), true);
+    }
+
+    let mut cins: HashMap<&str, usize> = HashMap::new();
+    for ligne in lignes {
+        if let Some(cin) = ligne.salarie.cin.as_deref() {
+            *cins.entry(cin).or_default() += 1;
+        }
+    }
+
+    for ligne in lignes {
+        match ligne.salarie.cin.as_deref() {
+            None => anomalie(Some(ligne), "cin", "CIN non renseignée".to_string(), true),
+            Some(cin) if !cin_valide(cin) => anomalie(Some(ligne), "cin", format!("CIN invalide : {}", cin), true),
+            Some(cin) if cins[cin] > 1 => anomalie(Some(ligne), "cin", format!("CIN {} partagée par plusieurs résidents", cin), true),
+            Some(_) => {}
+        }
+
+        if ligne.salarie.adresse.as_deref().unwrap_or_default().trim().is_empty() {
+            anomalie(Some(ligne), "adresse", "Adresse personnelle non renseignée".to_string(), true);
+        }
+
+        if ligne.prenom.is_empty() {
+            anomalie(Some(ligne), "nom_prenom", "Nom et prénom indissociables : saisir « NOM Prénom »".to_string(), true);
+        }
+
+        if ligne.sans_detail > 0 {
+            anomalie(
+                Some(ligne),
+                "retenues",
+                format!("{} paiement(s) sans détail des retenues, déclarées en autres retenues", ligne.sans_detail),
+                false,
+            );
+        }
+
+        if ligne.hors_run > 0 {
+            anomalie(
+                Some(ligne),
+                "paiements",
+                format!("{} paiement(s) antérieur(s) aux runs de paie, déclarés sans validation", ligne.hors_run),
+                false,
+            );
+        }
+    }
+
+    anomalies
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn montant(valeur: &str) -> BigDecimal {
+        BigDecimal::from_str(valeur).unwrap()
+    }
+
+    fn salarie(nom_prenom: &str, cin: Option<&str>) -> SalarieDeclare {
+        SalarieDeclare {
+            id_resident: 1,
+            nom_prenom: nom_prenom.to_string(),
+            cin: cin.map(str::to_string),
+            adresse: Some("12 rue des Orangers, Rabat".to_string()),
+            nombre_charges: 2,
+        }
+    }
+
+    fn mois(brut: &str, allocations: &str, frais: &str, cotisations: &str, ir: &str) -> ElementDeclare {
+        ElementDeclare {
+            salaire_base: montant("7000"),
+            brut_imposable: montant(brut),
+            exonere: montant(allocations),
+            frais_professionnels: montant(frais),
+            cotisations: montant(cotisations),
+            ir: montant(ir),
+            jours: 30,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn yearly_totals_separate_exempt_and_taxable_income() {
+        let rappel = ElementDeclare {
+            brut_imposable: montant("1200"),
+            autres_retenues: montant("200"),
+            ..Default::default()
+        };
+        let ligne = declarer(
+            salarie("ALAMI Sara", Some("AB123456")),
+            &[mois("10000", "300", "2000", "600", "850.25"), mois("10000", "300", "2000", "600", "850.25"), rappel],
+        );
+
+        assert_eq!((ligne.nom.as_str(), ligne.prenom.as_str()), ("ALAMI", "Sara"));
+        assert_eq!(ligne.jours, 60);
+        assert_eq!(ligne.salaire_base, montant("14000"));
+        assert_eq!(ligne.brut_imposable, montant("21200"));
+        assert_eq!(ligne.exonere, montant("600"));
+        assert_eq!(ligne.brut, montant("21800"));
+        assert_eq!(ligne.total_deductions(), montant("5200"));
+        assert_eq!(ligne.net_imposable, montant("16000"));
+        assert_eq!(ligne.ir, montant("1700.50"));
+        assert_eq!(ligne.autres_retenues, montant("200"));
+    }
+
+    #[test]
+    fn family_names_are_the_leading_capitalised_words() {
+        let nom = |texte: &str| {
+            let (nom, prenom) = separer_nom(texte);
+            format!("{}|{}", nom, prenom)
+        };
+        assert_eq!(nom("EL ALAMI Sara"), "EL ALAMI|Sara");
+        assert_eq!(nom("Alami Sara Nour"), "Alami|Sara Nour");
+        assert_eq!(nom("ALAMI SARA"), "ALAMI|SARA");
+        assert_eq!(nom("Alami"), "Alami|");
+    }
+
+    #[test]
+    fn identity_card_numbers_are_letters_then_digits() {
+        assert!(cin_valide("AB123456"));
+        assert!(cin_valide("J1234"));
+        assert!(!cin_valide("123456"));
+        assert!(!cin_valide("ABC12345"));
+        assert!(!cin_valide("AB1234567"));
+        assert!(!cin_valide("ab123456"));
+    }
+
+    #[test]
+    fn missing_or_shared_identity_blocks_the_declaration() {
+        let mut sans_detail = mois("9000", "0", "0", "0", "0");
+        sans_detail.sans_detail = true;
+        sans_detail.hors_run = true;
+        let lignes = vec![
+            declarer(salarie("ALAMI Sara", Some("AB123456")), &[mois("9000", "0", "0", "0", "0")]),
+            declarer(salarie("BENNANI Omar", Some("AB123456")), &[sans_detail]),
+            declarer(salarie("Idrissi", None), &[]),
+        ];
+
+        let anomalies = controler(Some(" "), &lignes);
+        let champs: Vec<(&str, &str, bool)> = anomalies
+            .iter()
+            .map(|a| (a.nom_prenom.as_str(), a.champ.as_str(), a.bloquante))
+            .collect();
+        assert_eq!(
+            champs,
+            vec![
+                ("Employeur", "identifiant_fiscal", true),
+                ("ALAMI Sara", "cin", true),
+                ("BENNANI Omar", "cin", true),
+                ("BENNANI Omar", "retenues", false),
+                ("BENNANI Omar", "paiements", false),
+                ("Idrissi", "cin", true),
+                ("Idrissi", "nom_prenom", true),
+            ]
+        );
+
+        assert!(controler(Some("1234567"), &lignes[..1]).is_empty());
+    }
+}
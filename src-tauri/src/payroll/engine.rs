@@ -0,0 +1,527 @@
+use crate::models::{Absence, Ajustement, Bareme, LignePaiement, NewPaiementMensuel};
+use crate::payroll::{
+    ajustements_du_mois, annee_residanat, bareme_applicable, calculer_retenues, est_retenue, jours_travailles,
+    ligne_ajustement, Periode, RegimeRetenues,
+};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+// Public sector payroll counts every month as 30 days
+pub const JOURS_MOIS: i32 = 30;
+
+// Family allowance per child per month: the first 3 children, then the 4th to 6th
+const ALLOCATION_ENFANTS_1_3: i64 = 300;
+const ALLOCATION_ENFANTS_4_6: i64 = 36;
+const MAX_ENFANTS_ALLOCATION: i32 = 6;
+
+// What the engine needs to know about a resident, independent of how it is loaded
+#[derive(Debug, Clone)]
+pub struct ResidentPaie {
+    pub id_resident: i32,
+    pub nom_prenom: String,
+    pub date_debut: NaiveDate,
+    pub date_fin: Option<NaiveDate>,
+    pub id_specialite: Option<i32>,
+    pub nombre_annees: i32,
+    pub nombre_enfants: i32,
+    pub nom_specialite: Option<String>,
+    pub nom_banque: Option<String>,
+}
+
+#[derive(Debug, Serialize)]
+pub struct AnomaliePaie {
+    pub id_resident: i32,
+    pub nom_prenom: String,
+    pub message: String,
+}
+
+#[derive(Debug, Default, Serialize)]
+pub struct ResultatPaie {
+    pub lignes: Vec<NewPaiementMensuel>,
+    pub anomalies: Vec<AnomaliePaie>,
+}
+
+pub(crate) fn arrondir(montant: BigDecimal) -> BigDecimal {
+    montant.round(2).with_scale(2)
+}
+
+// Whether the resident is in post at any point of the month of `periode`
+pub fn est_actif(resident: &ResidentPaie, periode: Periode) -> bool {
+    resident.date_debut <= periode.fin()
+        && resident.date_fin.is_none_or(|date_fin| date_fin >= periode.debut())
+}
+
+pub fn allocations_familiales(nombre_enfants: i32) -> BigDecimal {
+    let enfants = nombre_enfants.clamp(0, MAX_ENFANTS_ALLOCATION) as i64;
+    let premiers = enfants.min(3);
+    let suivants = enfants - premiers;
+
+    BigDecimal::from(premiers * ALLOCATION_ENFANTS_1_3 + suivants * ALLOCATION_ENFANTS_4_6)
+}
+
+// Scales a monthly amount to the days actually worked
+fn prorata(montant: &BigDecimal, jours_travail: i32) -> BigDecimal {
+    arrondir(montant * BigDecimal::from(jours_travail) / BigDecimal::from(JOURS_MOIS))
+}
+
+fn gain(code: &str, libelle: &str, base: &BigDecimal, montant: &BigDecimal) -> LignePaiement {
+    LignePaiement {
+        code: code.to_string(),
+        libelle: libelle.to_string(),
+        categorie: "gain".to_string(),
+        base: Some(base.clone()),
+        taux: None,
+        montant: montant.clone(),
+    }
+}
+
+// Computes one payment per active resident for the month of `date_paiement`,
+// prorated to the days worked (partial month, absences), then goes from gross to
+// net with the deductions in force that month. Manual adjustments of the month are
+// added as their own lines: bonuses are taxable, withholdings come off the net.
+// Residents without an applicable grid row, a negative net or an adjustment that
+// cannot be applied are reported as anomalies instead of being paid.
+pub fn calculer_paie(
+    residents: &[ResidentPaie],
+    grille: &[Bareme],
+    retenues: &RegimeRetenues,
+    absences: &[Absence],
+    ajustements: &[Ajustement],
+    date_paiement: NaiveDate,
+) -> ResultatPaie {
+    let mut resultat = ResultatPaie::default();
+    let periode = Periode::contenant(date_paiement);
+
+    for resident in residents.iter().filter(|r| est_actif(r, periode)) {
+        let annee = annee_residanat(resident.date_debut, date_paiement, resident.nombre_annees);
+
+        let bareme = match bareme_applicable(grille, annee, resident.id_specialite, date_paiement) {
+            Some(bareme) => bareme,
+            None => {
+                resultat.anomalies.push(AnomaliePaie {
+                    id_resident: resident.id_resident,
+                    nom_prenom: resident.nom_prenom.clone(),
+                    message: format!("Aucun barème applicable pour la {}e année de résidanat", annee),
+                });
+                continue;
+            }
+        };
+
+        let jours = jours_travailles(resident, absences, periode);
+
+        let salaire_base = prorata(&bareme.salaire_base, jours);
+        let indemnite_fonction = prorata(&bareme.indemnite_fonction, jours);
+        let indemnite_logement = prorata(&bareme.indemnite_logement, jours);
+        let indemnite_transport = prorata(&bareme.indemnite_transport, jours);
+        let allocations = allocations_familiales(resident.nombre_enfants);
+        let allocations_familiales = prorata(&allocations, jours);
+
+        let mut lignes: Vec<LignePaiement> = [
+            ("SALAIRE_BASE", "Salaire de base", &bareme.salaire_base, &salaire_base),
+            ("IND_FONCTION", "Indemnité de fonction", &bareme.indemnite_fonction, &indemnite_fonction),
+            ("IND_LOGEMENT", "Indemnité de logement", &bareme.indemnite_logement, &indemnite_logement),
+            ("IND_TRANSPORT", "Indemnité de transport", &bareme.indemnite_transport, &indemnite_transport),
+        ]
+        .iter()
+        .filter(|(_, _, _, montant)| !montant.is_zero())
+        .map(|(code, libelle, base, montant)| gain(code, libelle, base, montant))
+        .collect();
+
+        let (retenues_ajustement, primes): (Vec<&Ajustement>, Vec<&Ajustement>) =
+            ajustements_du_mois(ajustements, resident.id_resident, periode).partition(|a| est_retenue(a));
+        lignes.extend(primes.iter().map(|a| ligne_ajustement(a)));
+
+        let salaire_brut = lignes
+            .iter()
+            .fold(BigDecimal::zero(), |total, ligne| total + &ligne.montant);
+
+        let deductions = calculer_retenues(retenues, periode, &salaire_base, &salaire_brut, resident.nombre_enfants);
+
+        // Family allowances are paid on top of the net salary, free of deductions
+        if !allocations_familiales.is_zero() {
+            lignes.push(gain("ALLOC_FAM", "Allocations familiales", &allocations, &allocations_familiales));
+        }
+        lignes.extend(deductions);
+        lignes.extend(retenues_ajustement.iter().map(|a| ligne_ajustement(a)));
+
+        let total_retenues = lignes
+            .iter()
+            .filter(|ligne| ligne.categorie == "retenue")
+            .fold(BigDecimal::zero(), |total, ligne| total + &ligne.montant);
+
+        let montant = &salaire_brut - &total_retenues + &allocations_familiales;
+        if montant < BigDecimal::zero() {
+            resultat.anomalies.push(AnomaliePaie {
+                id_resident: resident.id_resident,
+                nom_prenom: resident.nom_prenom.clone(),
+                message: format!("Le net à payer est négatif ({})", montant),
+            });
+            continue;
+        }
+
+        resultat.lignes.push(NewPaiementMensuel {
+            id_resident: resident.id_resident,
+            nom_resident: resident.nom_prenom.clone(),
+            id_bareme: Some(bareme.id_bareme),
+            jours_travail: jours,
+            salaire_base,
+            indemnite_fonction,
+            indemnite_logement,
+            indemnite_transport,
+            allocations_familiales,
+            salaire_brut,
+            total_retenues,
+            montant,
+            date_paiement,
+            lignes,
+            id_ajustements: primes.iter().chain(&retenues_ajustement).map(|a| a.id_ajustement).collect(),
+        });
+    }
+
+    // An adjustment is never dropped silently, e.g. for a resident who left before its month
+    for ajustement in ajustements.iter().filter(|a| a.periode == periode.debut()) {
+        let traite = resultat.lignes.iter().any(|l| l.id_resident == ajustement.id_resident)
+            || resultat.anomalies.iter().any(|a| a.id_resident == ajustement.id_resident);
+        if !traite {
+            resultat.anomalies.push(AnomaliePaie {
+                id_resident: ajustement.id_resident,
+                nom_prenom: ajustement.nom_resident.clone().unwrap_or_default(),
+                message: format!("L'ajustement \"{}\" ne peut pas être appliqué : le résident n'est pas actif sur la période", ajustement.motif),
+            });
+        }
+    }
+
+    resultat
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::{ParametreIr, TauxCotisation, TrancheIr};
+    use std::str::FromStr;
+
+    fn date(annee: i32, mois: u32, jour: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(annee, mois, jour).unwrap()
+    }
+
+    fn dec(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    fn bareme(id_bareme: i32, annee: i32, id_specialite: Option<i32>, salaire_base: &str, date_effet: NaiveDate) -> Bareme {
+        Bareme {
+            id_bareme,
+            annee_residanat: annee,
+            id_specialite,
+            nom_specialite: None,
+            salaire_base: dec(salaire_base),
+            indemnite_fonction: dec("1000"),
+            indemnite_logement: BigDecimal::zero(),
+            indemnite_transport: BigDecimal::zero(),
+            date_effet,
+        }
+    }
+
+    fn resident(id_resident: i32, date_debut: NaiveDate, nombre_enfants: i32) -> ResidentPaie {
+        ResidentPaie {
+            id_resident,
+            nom_prenom: format!("Résident {}", id_resident),
+            date_debut,
+            date_fin: None,
+            id_specialite: Some(1),
+            nombre_annees: 4,
+            nombre_enfants,
+            nom_specialite: None,
+            nom_banque: None,
+        }
+    }
+
+    #[test]
+    fn family_allowance_follows_child_ranks() {
+        assert_eq!(allocations_familiales(0), BigDecimal::zero());
+        assert_eq!(allocations_familiales(3), BigDecimal::from(900));
+        assert_eq!(allocations_familiales(5), BigDecimal::from(972));
+        assert_eq!(allocations_familiales(9), BigDecimal::from(1008));
+        assert_eq!(allocations_familiales(-1), BigDecimal::zero());
+    }
+
+    #[test]
+    fn full_month_pays_grid_amounts_plus_allowance() {
+        let grille = vec![bareme(1, 1, None, "8000", date(2024, 1, 1))];
+        let residents = vec![resident(1, date(2024, 3, 10), 2)];
+
+        let resultat = calculer_paie(&residents, &grille, &RegimeRetenues::default(), &[], &[], date(2024, 6, 1));
+
+        assert!(resultat.anomalies.is_empty());
+        let ligne = &resultat.lignes[0];
+        assert_eq!(ligne.jours_travail, JOURS_MOIS);
+        assert_eq!(ligne.salaire_base, dec("8000.00"));
+        assert_eq!(ligne.allocations_familiales, dec("600.00"));
+        assert_eq!(ligne.montant, dec("9600.00"));
+    }
+
+    fn absence(id_resident: i32, date_debut: NaiveDate, date_fin: NaiveDate) -> Absence {
+        Absence {
+            id_absence: 0,
+            id_resident,
+            nom_resident: None,
+            type_absence: "absence".to_string(),
+            date_debut,
+            date_fin,
+            motif: None,
+        }
+    }
+
+    #[test]
+    fn days_worked_prorate_every_component() {
+        let grille = vec![bareme(1, 1, None, "8000", date(2024, 1, 1))];
+        let residents = vec![resident(1, date(2024, 3, 10), 1)];
+        let absences = vec![absence(1, date(2024, 6, 11), date(2024, 6, 30))];
+
+        let resultat = calculer_paie(&residents, &grille, &RegimeRetenues::default(), &absences, &[], date(2024, 6, 1));
+
+        let ligne = &resultat.lignes[0];
+        assert_eq!(ligne.jours_travail, 10);
+        assert_eq!(ligne.salaire_base, dec("2666.67"));
+        assert_eq!(ligne.indemnite_fonction, dec("333.33"));
+        assert_eq!(ligne.allocations_familiales, dec("100.00"));
+        assert_eq!(ligne.montant, dec("3100.00"));
+    }
+
+    #[test]
+    fn grid_row_depends_on_residency_year_and_effective_date() {
+        let grille = vec![
+            bareme(1, 1, None, "8000", date(2023, 1, 1)),
+            bareme(2, 2, None, "9000", date(2023, 1, 1)),
+            bareme(3, 2, None, "9500", date(2025, 1, 1)),
+        ];
+        let residents = vec![resident(1, date(2023, 9, 1), 0)];
+
+        let premiere_annee = calculer_paie(&residents, &grille, &RegimeRetenues::default(), &[], &[], date(2024, 8, 1));
+        let deuxieme_annee = calculer_paie(&residents, &grille, &RegimeRetenues::default(), &[], &[], date(2024, 9, 1));
+        let apres_revision = calculer_paie(&residents, &grille, &RegimeRetenues::default(), &[], &[], date(2025, 1, 1));
+
+        assert_eq!(premiere_annee.lignes[0].id_bareme, Some(1));
+        assert_eq!(deuxieme_annee.lignes[0].id_bareme, Some(2));
+        assert_eq!(apres_revision.lignes[0].id_bareme, Some(3));
+    }
+
+    #[test]
+    fn specialty_row_wins_over_generic_grid() {
+        let grille = vec![
+            bareme(1, 1, None, "8000", date(2024, 6, 1)),
+            bareme(2, 1, Some(1), "8500", date(2024, 1, 1)),
+        ];
+        let residents = vec![resident(1, date(2024, 3, 1), 0)];
+
+        let resultat = calculer_paie(&residents, &grille, &RegimeRetenues::default(), &[], &[], date(2024, 7, 1));
+
+        assert_eq!(resultat.lignes[0].id_bareme, Some(2));
+    }
+
+    #[test]
+    fn arrival_and_departure_mid_month_count_on_a_30_day_basis() {
+        let grille = vec![bareme(1, 1, None, "8000", date(2024, 1, 1))];
+        let arrivee = resident(1, date(2024, 7, 16), 0);
+        let mut depart = resident(2, date(2024, 1, 1), 0);
+        depart.date_fin = Some(date(2024, 7, 10));
+        let mut fin_fevrier = resident(3, date(2024, 1, 1), 0);
+        fin_fevrier.date_fin = Some(date(2024, 2, 29));
+
+        let juillet = calculer_paie(&[arrivee, depart], &grille, &RegimeRetenues::default(), &[], &[], date(2024, 7, 1));
+        let fevrier = calculer_paie(&[fin_fevrier], &grille, &RegimeRetenues::default(), &[], &[], date(2024, 2, 1));
+
+        assert_eq!(juillet.lignes[0].jours_travail, 15);
+        assert_eq!(juillet.lignes[1].jours_travail, 10);
+        assert_eq!(fevrier.lignes[0].jours_travail, JOURS_MOIS);
+    }
+
+    #[test]
+    fn overlapping_absences_are_counted_once() {
+        let grille = vec![bareme(1, 1, None, "8000", date(2024, 1, 1))];
+        let residents = vec![resident(1, date(2024, 1, 1), 0)];
+        let absences = vec![
+            absence(1, date(2024, 5, 25), date(2024, 6, 5)),
+            absence(1, date(2024, 6, 3), date(2024, 6, 7)),
+            absence(2, date(2024, 6, 1), date(2024, 6, 30)),
+        ];
+
+        let resultat = calculer_paie(&residents, &grille, &RegimeRetenues::default(), &absences, &[], date(2024, 6, 1));
+
+        assert_eq!(resultat.lignes[0].jours_travail, 23);
+    }
+
+    #[test]
+    fn residents_outside_the_month_are_skipped() {
+        let grille = vec![bareme(1, 1, None, "8000", date(2024, 1, 1))];
+        let mut parti = resident(1, date(2024, 1, 1), 0);
+        parti.date_fin = Some(date(2024, 5, 31));
+        let futur = resident(2, date(2024, 7, 1), 0);
+
+        let resultat = calculer_paie(&[parti, futur], &grille, &RegimeRetenues::default(), &[], &[], date(2024, 6, 1));
+
+        assert!(resultat.lignes.is_empty());
+        assert!(resultat.anomalies.is_empty());
+    }
+
+    fn cotisation(code: &str, taux: &str, assiette: &str, date_effet: NaiveDate) -> TauxCotisation {
+        TauxCotisation {
+            id_taux: 0,
+            code: code.to_string(),
+            libelle: code.to_string(),
+            taux: dec(taux),
+            assiette: assiette.to_string(),
+            plafond: None,
+            date_effet,
+        }
+    }
+
+    fn bareme_ir(date_effet: NaiveDate) -> Vec<TrancheIr> {
+        [
+            ("0", Some("40000"), "0", "0"),
+            ("40000.01", Some("60000"), "10", "4000"),
+            ("60000.01", Some("80000"), "20", "10000"),
+            ("80000.01", Some("100000"), "30", "18000"),
+            ("100000.01", Some("180000"), "34", "22000"),
+            ("180000.01", None, "37", "25600"),
+        ]
+        .iter()
+        .map(|(min, max, taux, somme)| TrancheIr {
+            id_tranche: 0,
+            revenu_min: dec(min),
+            revenu_max: max.map(dec),
+            taux: dec(taux),
+            somme_a_deduire: dec(somme),
+            date_effet,
+        })
+        .collect()
+    }
+
+    fn regime(date_effet: NaiveDate) -> RegimeRetenues {
+        RegimeRetenues {
+            cotisations: vec![
+                cotisation("CMR", "10", "salaire_base", date_effet),
+                cotisation("AMO", "2.5", "brut", date_effet),
+            ],
+            tranches_ir: bareme_ir(date_effet),
+            parametres_ir: vec![ParametreIr {
+                id_parametre: 0,
+                deduction_par_charge: dec("500"),
+                max_charges: 6,
+                taux_frais_pro: dec("20"),
+                plafond_frais_pro: dec("2500"),
+                date_effet,
+            }],
+        }
+    }
+
+    #[test]
+    fn net_pay_deducts_contributions_and_income_tax() {
+        let grille = vec![bareme(1, 1, None, "8000", date(2024, 1, 1))];
+        let residents = vec![resident(1, date(2024, 3, 10), 2)];
+
+        let resultat = calculer_paie(&residents, &grille, &regime(date(2024, 1, 1)), &[], &[], date(2024, 6, 1));
+
+        let ligne = &resultat.lignes[0];
+        let retenue = |code: &str| ligne.lignes.iter().find(|l| l.code == code).map(|l| l.montant.clone());
+        assert_eq!(ligne.salaire_brut, dec("9000.00"));
+        assert_eq!(retenue("CMR"), Some(dec("800.00")));
+        assert_eq!(retenue("AMO"), Some(dec("225.00")));
+        // (9000 - 800 - 225 - 1800) * 12 = 74100, taxed at 20% minus 10000 and 2 dependents
+        assert_eq!(retenue("IR"), Some(dec("318.33")));
+        assert_eq!(ligne.total_retenues, dec("1343.33"));
+        assert_eq!(ligne.montant, dec("8256.67"));
+    }
+
+    #[test]
+    fn deductions_use_the_rates_in_force_for_the_month() {
+        let grille = vec![bareme(1, 1, None, "8000", date(2024, 1, 1))];
+        let residents = vec![resident(1, date(2024, 3, 10), 0)];
+        let mut retenues = regime(date(2024, 1, 1));
+        retenues.cotisations.push(cotisation("CMR", "12", "salaire_base", date(2025, 1, 1)));
+        retenues.tranches_ir = vec![TrancheIr {
+            id_tranche: 0,
+            revenu_min: BigDecimal::zero(),
+            revenu_max: None,
+            taux: BigDecimal::zero(),
+            somme_a_deduire: BigDecimal::zero(),
+            date_effet: date(2024, 1, 1),
+        }];
+        retenues.tranches_ir.extend(bareme_ir(date(2025, 1, 1)));
+
+        let avant = calculer_paie(&residents, &grille, &retenues, &[], &[], date(2024, 12, 1));
+        let apres = calculer_paie(&residents, &grille, &retenues, &[], &[], date(2025, 1, 1));
+
+        let retenue = |ligne: &NewPaiementMensuel, code: &str| ligne.lignes.iter().find(|l| l.code == code).map(|l| l.montant.clone());
+        assert_eq!(retenue(&avant.lignes[0], "CMR"), Some(dec("800.00")));
+        assert_eq!(retenue(&avant.lignes[0], "IR"), None);
+        assert_eq!(retenue(&apres.lignes[0], "CMR"), Some(dec("960.00")));
+        assert!(retenue(&apres.lignes[0], "IR").is_some());
+    }
+
+    fn ajustement(id_ajustement: i32, id_resident: i32, type_ajustement: &str, montant: &str, periode: NaiveDate) -> Ajustement {
+        Ajustement {
+            id_ajustement,
+            id_resident,
+            nom_resident: None,
+            periode,
+            type_ajustement: type_ajustement.to_string(),
+            montant: dec(montant),
+            motif: "test".to_string(),
+            created_by: None,
+            created_at: None,
+            id_paiement: None,
+        }
+    }
+
+    #[test]
+    fn adjustments_of_the_month_are_separate_lines() {
+        let grille = vec![bareme(1, 1, None, "8000", date(2024, 1, 1))];
+        let residents = vec![resident(1, date(2024, 1, 1), 0)];
+        let ajustements = vec![
+            ajustement(1, 1, "garde", "1200", date(2024, 6, 1)),
+            ajustement(2, 1, "retenue", "500", date(2024, 6, 1)),
+            ajustement(3, 1, "prime", "3000", date(2024, 7, 1)),
+        ];
+
+        let resultat = calculer_paie(&residents, &grille, &RegimeRetenues::default(), &[], &ajustements, date(2024, 6, 1));
+
+        let ligne = &resultat.lignes[0];
+        assert_eq!(ligne.salaire_brut, dec("10200.00"));
+        assert_eq!(ligne.total_retenues, dec("500"));
+        assert_eq!(ligne.montant, dec("9700.00"));
+        assert_eq!(ligne.id_ajustements, vec![1, 2]);
+        assert!(ligne.lignes.iter().any(|l| l.code == "GARDE" && l.categorie == "gain"));
+        assert!(ligne.lignes.iter().any(|l| l.code == "RETENUE" && l.categorie == "retenue"));
+    }
+
+    #[test]
+    fn adjustments_that_cannot_be_applied_are_reported() {
+        let grille = vec![bareme(1, 1, None, "8000", date(2024, 1, 1))];
+        let mut parti = resident(1, date(2024, 1, 1), 0);
+        parti.date_fin = Some(date(2024, 5, 31));
+        let present = resident(2, date(2024, 1, 1), 0);
+        let ajustements = vec![
+            ajustement(1, 1, "prime", "1000", date(2024, 6, 1)),
+            ajustement(2, 2, "retenue", "20000", date(2024, 6, 1)),
+        ];
+
+        let resultat = calculer_paie(&[parti, present], &grille, &RegimeRetenues::default(), &[], &ajustements, date(2024, 6, 1));
+
+        assert!(resultat.lignes.is_empty());
+        assert_eq!(resultat.anomalies.len(), 2);
+    }
+
+    #[test]
+    fn missing_grid_row_is_reported() {
+        let grille = vec![bareme(1, 1, None, "8000", date(2025, 1, 1))];
+        let residents = vec![resident(1, date(2024, 3, 1), 0)];
+
+        let resultat = calculer_paie(&residents, &grille, &RegimeRetenues::default(), &[], &[], date(2024, 6, 1));
+
+        assert!(resultat.lignes.is_empty());
+        assert_eq!(resultat.anomalies.len(), 1);
+        assert_eq!(resultat.anomalies[0].id_resident, 1);
+    }
+}
@@ -0,0 +1,23 @@
+pub mod ajustements;
+pub mod apercu;
+pub mod bareme;
+pub mod declaration;
+pub mod engine;
+pub mod periode;
+pub mod presence;
+pub mod rapprochement;
+pub mod retenues;
+pub mod trop_percu;
+pub mod workflow;
+
+pub use ajustements::{ajustements_du_mois, est_retenue, ligne_ajustement, TYPES_AJUSTEMENT};
+pub use apercu::{construire_apercu, ApercuPaie, PaiementPrecedent};
+pub use bareme::{annee_residanat, bareme_applicable};
+pub use declaration::{cin_valide, controler, declarer, ElementDeclare, LigneDeclaration, SalarieDeclare};
+pub use engine::{calculer_paie, ResidentPaie, ResultatPaie};
+pub use periode::{valider_exercice, Periode, PeriodeError};
+pub use presence::{jours_travailles, TYPES_ABSENCE};
+pub use rapprochement::{peut_reemettre, rapprocher, Origine, VirementEmis};
+pub use retenues::{calculer_retenues, frais_professionnels, RegimeRetenues, ASSIETTES};
+pub use trop_percu::{detecter_trop_percus, echeancier, imputer_sur_rappel, PaiementVerse, TropPercu, IMPUTATIONS};
+pub use workflow::{peut_preparer, peut_rapprocher, StatutRun};
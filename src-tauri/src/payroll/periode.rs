@@ -0,0 +1,150 @@
+use chrono::{Datelike, NaiveDate};
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+
+// Payroll can still be generated for the previous exercice (late December runs, missed months),
+// anything older is considered closed
+const EXERCICES_OUVERTS: i32 = 2;
+
+#[derive(Debug, Error)]
+pub enum PeriodeError {
+    #[error("Le mois doit être compris entre 1 et 12")]
+    InvalidMonth,
+    #[error("Impossible de générer la paie d'une période future ({0})")]
+    Future(String),
+    #[error("La période {0} est clôturée")]
+    Closed(String),
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Serialize, Deserialize)]
+pub struct Periode {
+    pub annee: i32,
+    pub mois: u32,
+}
+
+impl Periode {
+    pub fn new(annee: i32, mois: u32) -> Result<Self, PeriodeError> {
+        if !(1..=12).contains(&mois) {
+            return Err(PeriodeError::InvalidMonth);
+        }
+
+        Ok(Self { annee, mois })
+    }
+
+    pub fn contenant(date: NaiveDate) -> Self {
+        Self {
+            annee: date.year(),
+            mois: date.month(),
+        }
+    }
+
+    pub fn debut(&self) -> NaiveDate {
+        NaiveDate::from_ymd_opt(self.annee, self.mois, 1).expect("valid month")
+    }
+
+    pub fn fin(&self) -> NaiveDate {
+        self.suivante().debut().pred_opt().expect("valid month")
+    }
+
+    pub fn suivante(&self) -> Self {
+        if self.mois == 12 {
+            Self { annee: self.annee + 1, mois: 1 }
+        } else {
+            Self { annee: self.annee, mois: self.mois + 1 }
+        }
+    }
+
+    pub fn precedente(&self) -> Self {
+        if self.mois == 1 {
+            Self { annee: self.annee - 1, mois: 12 }
+        } else {
+            Self { annee: self.annee, mois: self.mois - 1 }
+        }
+    }
+
+    // Date stored on the payments of this period: today for the current month,
+    // the last day of the month for a late run
+    pub fn date_paiement(&self, aujourd_hui: NaiveDate) -> NaiveDate {
+        self.fin().min(aujourd_hui)
+    }
+
+    // A period can be generated if it has started and belongs to an open exercice
+    pub fn valider(&self, aujourd_hui: NaiveDate) -> Result<(), PeriodeError> {
+        if *self > Periode::contenant(aujourd_hui) {
+            return Err(PeriodeError::Future(self.to_string()));
+        }
+
+        valider_exercice(self.annee, aujourd_hui).map_err(|_| PeriodeError::Closed(self.to_string()))
+    }
+}
+
+impl std::fmt::Display for Periode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        write!(f, "{:02}/{}", self.mois, self.annee)
+    }
+}
+
+pub fn valider_exercice(exercice: i32, aujourd_hui: NaiveDate) -> Result<(), PeriodeError> {
+    if exercice > aujourd_hui.year() {
+        return Err(PeriodeError::Future(exercice.to_string()));
+    }
+
+    if exercice <= aujourd_hui.year() - EXERCICES_OUVERTS {
+        return Err(PeriodeError::Closed(exercice.to_string()));
+    }
+
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(annee: i32, mois: u32, jour: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(annee, mois, jour).unwrap()
+    }
+
+    fn periode(annee: i32, mois: u32) -> Periode {
+        Periode::new(annee, mois).unwrap()
+    }
+
+    #[test]
+    fn months_wrap_around_the_year() {
+        assert!(matches!(Periode::new(2025, 13), Err(PeriodeError::InvalidMonth)));
+        assert!(matches!(Periode::new(2025, 0), Err(PeriodeError::InvalidMonth)));
+        assert_eq!(periode(2024, 12).suivante(), periode(2025, 1));
+        assert_eq!(periode(2025, 1).precedente(), periode(2024, 12));
+        assert_eq!(periode(2024, 2).fin(), date(2024, 2, 29));
+        assert_eq!(periode(2025, 3).to_string(), "03/2025");
+    }
+
+    #[test]
+    fn payments_are_dated_today_or_at_the_end_of_a_past_month() {
+        let aujourd_hui = date(2025, 3, 14);
+        assert_eq!(periode(2025, 3).date_paiement(aujourd_hui), aujourd_hui);
+        assert_eq!(periode(2025, 1).date_paiement(aujourd_hui), date(2025, 1, 31));
+    }
+
+    #[test]
+    fn future_periods_are_refused() {
+        let aujourd_hui = date(2025, 3, 14);
+        assert!(periode(2025, 3).valider(aujourd_hui).is_ok());
+        assert!(matches!(periode(2025, 4).valider(aujourd_hui), Err(PeriodeError::Future(p)) if p == "04/2025"));
+        assert!(matches!(periode(2026, 1).valider(aujourd_hui), Err(PeriodeError::Future(_))));
+        assert!(matches!(valider_exercice(2026, aujourd_hui), Err(PeriodeError::Future(e)) if e == "2026"));
+    }
+
+    #[test]
+    fn only_the_current_and_previous_exercices_are_open() {
+        let aujourd_hui = date(2025, 1, 5);
+
+        assert!(valider_exercice(2025, aujourd_hui).is_ok());
+        assert!(valider_exercice(2024, aujourd_hui).is_ok());
+        assert!(matches!(valider_exercice(2023, aujourd_hui), Err(PeriodeError::Closed(e)) if e == "2023"));
+
+        // A late December run is still possible in January
+        assert!(periode(2024, 12).valider(aujourd_hui).is_ok());
+        assert!(periode(2024, 1).valider(aujourd_hui).is_ok());
+        assert!(matches!(periode(2023, 12).valider(aujourd_hui), Err(PeriodeError::Closed(p)) if p == "12/2023"));
+    }
+}
@@ -0,0 +1,49 @@
+use crate::models::Absence;
+use crate::payroll::engine::{ResidentPaie, JOURS_MOIS};
+use crate::payroll::Periode;
+use chrono::{Datelike, NaiveDate};
+
+pub const TYPES_ABSENCE: [&str; 3] = ["absence", "conge_sans_solde", "suspension"];
+
+// Day of `date` on the 30-day payroll calendar: the 31st counts as the 30th
+// and the last day of February closes the month
+fn jour_paie(date: NaiveDate, periode: Periode) -> usize {
+    if date >= periode.fin() {
+        JOURS_MOIS as usize
+    } else {
+        (date.day() as usize).min(JOURS_MOIS as usize)
+    }
+}
+
+// Range of payroll days [debut, fin] covered by a date interval within the month
+fn jours_couverts(debut: NaiveDate, fin: Option<NaiveDate>, periode: Periode) -> Option<(usize, usize)> {
+    if debut > periode.fin() || fin.is_some_and(|fin| fin < periode.debut()) {
+        return None;
+    }
+
+    let premier = if debut <= periode.debut() { 1 } else { jour_paie(debut, periode) };
+    let dernier = match fin {
+        Some(fin) if fin < periode.fin() => jour_paie(fin, periode),
+        _ => JOURS_MOIS as usize,
+    };
+
+    (premier <= dernier).then_some((premier, dernier))
+}
+
+// Days paid for the month: the days in post (arrival or departure mid-month)
+// minus the days covered by the resident's absences, counted once each
+pub fn jours_travailles(resident: &ResidentPaie, absences: &[Absence], periode: Periode) -> i32 {
+    let mut travailles = [false; JOURS_MOIS as usize];
+
+    if let Some((premier, dernier)) = jours_couverts(resident.date_debut, resident.date_fin, periode) {
+        travailles[premier - 1..dernier].iter_mut().for_each(|jour| *jour = true);
+    }
+
+    for absence in absences.iter().filter(|a| a.id_resident == resident.id_resident) {
+        if let Some((premier, dernier)) = jours_couverts(absence.date_debut, Some(absence.date_fin), periode) {
+            travailles[premier - 1..dernier].iter_mut().for_each(|jour| *jour = false);
+        }
+    }
+
+    travailles.iter().filter(|jour| **jour).count() as i32
+}
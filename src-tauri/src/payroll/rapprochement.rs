@@ -0,0 +1,110 @@
+use crate::payroll::engine::arrondir;
+use bigdecimal::BigDecimal;
+use chrono::NaiveDate;
+use serde::{Deserialize, Serialize};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Origine {
+    Paiement,
+    Rappel,
+    Reemission,
+}
+
+// A transfer still waiting for the bank's answer, from paiement_mensuel, rappels_annuels or reemissions
+#[derive(Debug, Clone)]
+pub struct VirementEmis {
+    pub origine: Origine,
+    pub id: i32,
+    pub rib: String,
+    pub montant: BigDecimal,
+    pub date: NaiveDate,
+}
+
+// Matches each return line (RIB, amount) to a pending transfer with the same RIB
+// and the same amount to the centime. A transfer answers at most one line; when a
+// resident has several pending transfers of the same amount, the most recent one
+// is taken first since banks return the latest batch.
+pub fn rapprocher(retours: &[(&str, &BigDecimal)], virements: &[VirementEmis]) -> Vec<Option<usize>> {
+    let mut ordre: Vec<usize> = (0..virements.len()).collect();
+    ordre.sort_by(|a, b| virements[*b].date.cmp(&virements[*a].date));
+
+    let mut utilises = vec![false; virements.len()];
+
+    retours
+        .iter()
+        .map(|(rib, montant)| {
+            let montant = arrondir((*montant).clone());
+            let trouve = ordre.iter().copied().find(|index| {
+                !utilises[*index] && virements[*index].rib == *rib && arrondir(virements[*index].montant.clone()) == montant
+            })?;
+            utilises[trouve] = true;
+            Some(trouve)
+        })
+        .collect()
+}
+
+// A payment or rappel can be reissued when its transfer was rejected, or when
+// the last reissue of it was rejected in turn
+pub fn peut_reemettre(statut_virement: &str, derniere_reemission: Option<&str>) -> bool {
+    match statut_virement {
+        "rejete" => true,
+        "reemis" => derniere_reemission == Some("rejete"),
+        _ => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn emis(origine: Origine, id: i32, rib: &str, montant: &str, mois: u32) -> VirementEmis {
+        VirementEmis {
+            origine,
+            id,
+            rib: rib.to_string(),
+            montant: BigDecimal::from_str(montant).unwrap(),
+            date: NaiveDate::from_ymd_opt(2024, mois, 28).unwrap(),
+        }
+    }
+
+    #[test]
+    fn matches_on_rib_and_amount_once() {
+        let virements = [
+            emis(Origine::Paiement, 1, "A", "9000", 5),
+            emis(Origine::Paiement, 2, "A", "9000", 6),
+            emis(Origine::Rappel, 3, "A", "1200.50", 6),
+            emis(Origine::Paiement, 4, "B", "9000", 6),
+        ];
+        let neuf_mille = BigDecimal::from(9000);
+        let rappel = BigDecimal::from_str("1200.5").unwrap();
+
+        let resultat = rapprocher(
+            &[("A", &neuf_mille), ("A", &rappel), ("A", &neuf_mille), ("A", &neuf_mille), ("C", &neuf_mille)],
+            &virements,
+        );
+
+        assert_eq!(resultat, vec![Some(1), Some(2), Some(0), None, None]);
+    }
+
+    #[test]
+    fn amounts_must_agree_to_the_centime() {
+        let virements = [emis(Origine::Paiement, 1, "A", "9000.00", 6)];
+        let proche = BigDecimal::from_str("9000.01").unwrap();
+        let egal = BigDecimal::from_str("9000.004").unwrap();
+
+        assert_eq!(rapprocher(&[("A", &proche)], &virements), vec![None]);
+        assert_eq!(rapprocher(&[("A", &egal)], &virements), vec![Some(0)]);
+    }
+
+    #[test]
+    fn only_rejected_transfers_are_reissued() {
+        assert!(peut_reemettre("rejete", None));
+        assert!(peut_reemettre("reemis", Some("rejete")));
+        assert!(!peut_reemettre("reemis", Some("en_attente")));
+        assert!(!peut_reemettre("reemis", Some("paye")));
+        assert!(!peut_reemettre("paye", None));
+        assert!(!peut_reemettre("en_attente", None));
+    }
+}
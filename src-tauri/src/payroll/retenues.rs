@@ -0,0 +1,148 @@
+use crate::models::{LignePaiement, ParametreIr, TauxCotisation, TrancheIr};
+use crate::payroll::engine::arrondir;
+use crate::payroll::Periode;
+use bigdecimal::{BigDecimal, Zero};
+use std::collections::BTreeMap;
+
+pub const ASSIETTES: [&str; 2] = ["salaire_base", "brut"];
+
+// Every version of the deduction rates, as stored; the rows in force for a
+// month are picked when the payroll of that month is computed
+#[derive(Debug, Clone, Default)]
+pub struct RegimeRetenues {
+    pub cotisations: Vec<TauxCotisation>,
+    pub tranches_ir: Vec<TrancheIr>,
+    pub parametres_ir: Vec<ParametreIr>,
+}
+
+struct ReglesApplicables<'a> {
+    cotisations: Vec<&'a TauxCotisation>,
+    tranches_ir: Vec<&'a TrancheIr>,
+    parametre_ir: Option<&'a ParametreIr>,
+}
+
+fn pourcentage(montant: &BigDecimal, taux: &BigDecimal) -> BigDecimal {
+    montant * taux / BigDecimal::from(100)
+}
+
+impl RegimeRetenues {
+    fn applicables(&self, periode: Periode) -> ReglesApplicables<'_> {
+        let debut = periode.debut();
+
+        // Latest version of each contribution code
+        let mut cotisations: BTreeMap<&str, &TauxCotisation> = BTreeMap::new();
+        for taux in self.cotisations.iter().filter(|t| t.date_effet <= debut) {
+            let courant = cotisations.entry(taux.code.as_str()).or_insert(taux);
+            if taux.date_effet > courant.date_effet {
+                *courant = taux;
+            }
+        }
+
+        // The tax scale is versioned as a whole: keep only the brackets of the latest one
+        let date_bareme = self
+            .tranches_ir
+            .iter()
+            .map(|t| t.date_effet)
+            .filter(|date| *date <= debut)
+            .max();
+        let mut tranches_ir: Vec<&TrancheIr> = self
+            .tranches_ir
+            .iter()
+            .filter(|t| Some(t.date_effet) == date_bareme)
+            .collect();
+        tranches_ir.sort_by(|a, b| a.revenu_min.cmp(&b.revenu_min));
+
+        let parametre_ir = self
+            .parametres_ir
+            .iter()
+            .filter(|p| p.date_effet <= debut)
+            .max_by_key(|p| p.date_effet);
+
+        ReglesApplicables {
+            cotisations: cotisations.into_values().collect(),
+            tranches_ir,
+            parametre_ir,
+        }
+    }
+}
+
+// Monthly income tax: the taxable income is annualised, taxed on the scale,
+// reduced by the family deductions and brought back to one month
+fn impot_mensuel(regles: &ReglesApplicables, net_imposable: &BigDecimal, nombre_charges: i32) -> Option<(BigDecimal, BigDecimal)> {
+    let annuel = net_imposable * BigDecimal::from(12);
+
+    let tranche = regles.tranches_ir.iter().find(|t| {
+        t.revenu_min <= annuel && t.revenu_max.as_ref().is_none_or(|max| annuel <= *max)
+    })?;
+
+    let deductions_famille = regles.parametre_ir.map_or_else(BigDecimal::zero, |p| {
+        BigDecimal::from(nombre_charges.clamp(0, p.max_charges)) * &p.deduction_par_charge
+    });
+
+    let impot_annuel = pourcentage(&annuel, &tranche.taux) - &tranche.somme_a_deduire - deductions_famille;
+    let impot_annuel = if impot_annuel < BigDecimal::zero() { BigDecimal::zero() } else { impot_annuel };
+
+    Some((arrondir(impot_annuel / BigDecimal::from(12)), tranche.taux.clone()))
+}
+
+fn deduction_frais(parametre_ir: Option<&ParametreIr>, brut: &BigDecimal) -> BigDecimal {
+    parametre_ir.map_or_else(BigDecimal::zero, |p| {
+        arrondir(pourcentage(brut, &p.taux_frais_pro)).min(p.plafond_frais_pro.clone())
+    })
+}
+
+// Professional expenses allowance deducted from the taxable income of the month
+pub fn frais_professionnels(regime: &RegimeRetenues, periode: Periode, brut: &BigDecimal) -> BigDecimal {
+    deduction_frais(regime.applicables(periode).parametre_ir, brut)
+}
+
+// Statutory deductions of one month, in payslip order: contributions, then income tax.
+// Family allowances are exempt and never part of `brut`.
+pub fn calculer_retenues(
+    regime: &RegimeRetenues,
+    periode: Periode,
+    salaire_base: &BigDecimal,
+    brut: &BigDecimal,
+    nombre_charges: i32,
+) -> Vec<LignePaiement> {
+    let regles = regime.applicables(periode);
+    let mut lignes = Vec::new();
+    let mut total_cotisations = BigDecimal::zero();
+
+    for taux in regles.cotisations.iter().filter(|t| !t.taux.is_zero()) {
+        let assiette = if taux.assiette == "salaire_base" { salaire_base } else { brut };
+        let base = match &taux.plafond {
+            Some(plafond) if plafond < assiette => plafond.clone(),
+            _ => assiette.clone(),
+        };
+        let montant = arrondir(pourcentage(&base, &taux.taux));
+        total_cotisations += &montant;
+
+        lignes.push(LignePaiement {
+            code: taux.code.clone(),
+            libelle: taux.libelle.clone(),
+            categorie: "retenue".to_string(),
+            base: Some(base),
+            taux: Some(taux.taux.clone()),
+            montant,
+        });
+    }
+
+    let net_imposable = brut - total_cotisations - deduction_frais(regles.parametre_ir, brut);
+    let net_imposable = if net_imposable < BigDecimal::zero() { BigDecimal::zero() } else { net_imposable };
+
+    if let Some((impot, taux)) = impot_mensuel(&regles, &net_imposable, nombre_charges) {
+        if !impot.is_zero() {
+            lignes.push(LignePaiement {
+                code: "IR".to_string(),
+                libelle: "Impôt sur le revenu".to_string(),
+                categorie: "retenue".to_string(),
+                base: Some(net_imposable),
+                taux: Some(taux),
+                montant: impot,
+            });
+        }
+    }
+
+    lignes
+}
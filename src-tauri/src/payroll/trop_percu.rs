@@ -0,0 +1,282 @@
+use crate::models::{Absence, Ajustement, Bareme};
+use crate::payroll::engine::{arrondir, est_actif};
+use crate::payroll::{calculer_paie, Periode, RegimeRetenues, ResidentPaie};
+use bigdecimal::{BigDecimal, Zero};
+use chrono::NaiveDate;
+use serde::Serialize;
+
+pub const IMPUTATIONS: [&str; 2] = ["paie", "rappel"];
+
+// A payment already sent to the bank, as stored
+#[derive(Debug, Clone)]
+pub struct PaiementVerse {
+    pub id_paiement: i32,
+    pub id_resident: i32,
+    pub periode: Periode,
+    pub date_paiement: NaiveDate,
+    pub jours_travail: i32,
+    pub montant: BigDecimal,
+}
+
+#[derive(Debug, Serialize)]
+pub struct TropPercu {
+    pub id_paiement: i32,
+    pub id_resident: i32,
+    pub nom_prenom: String,
+    pub periode: Periode,
+    pub jours_payes: i32,
+    pub jours_dus: i32,
+    pub montant_verse: BigDecimal,
+    pub montant_du: BigDecimal,
+    pub trop_percu: BigDecimal,
+}
+
+// What the resident should have received for the month of `paiement`, given
+// what is known today (departure date, suspensions, absences recorded late).
+// Only a drop in days worked is considered: the amount paid is scaled to the
+// days still due, so that grid, rate or family changes made since are not
+// overpayments. Returns None when the days due cannot be recomputed.
+fn montant_du(
+    paiement: &PaiementVerse,
+    resident: &ResidentPaie,
+    grille: &[Bareme],
+    retenues: &RegimeRetenues,
+    absences: &[Absence],
+    ajustements: &[Ajustement],
+) -> Option<(i32, BigDecimal)> {
+    if !est_actif(resident, paiement.periode) {
+        return Some((0, BigDecimal::zero()));
+    }
+
+    let resultat = calculer_paie(
+        std::slice::from_ref(resident),
+        grille,
+        retenues,
+        absences,
+        ajustements,
+        paiement.date_paiement,
+    );
+    let ligne = resultat.lignes.first()?;
+
+    if ligne.jours_travail >= paiement.jours_travail {
+        return Some((paiement.jours_travail, paiement.montant.clone()));
+    }
+
+    let montant = arrondir(
+        &paiement.montant * BigDecimal::from(ligne.jours_travail) / BigDecimal::from(paiement.jours_travail),
+    );
+
+    Some((ligne.jours_travail, montant))
+}
+
+pub fn detecter_trop_percus(
+    paiements: &[PaiementVerse],
+    residents: &[ResidentPaie],
+    grille: &[Bareme],
+    retenues: &RegimeRetenues,
+    absences: &[Absence],
+    ajustements: &[Ajustement],
+) -> Vec<TropPercu> {
+    let mut trop_percus = Vec::new();
+
+    for paiement in paiements {
+        let Some(resident) = residents.iter().find(|r| r.id_resident == paiement.id_resident) else {
+            continue;
+        };
+        let Some((jours_dus, du)) = montant_du(paiement, resident, grille, retenues, absences, ajustements) else {
+            continue;
+        };
+
+        let trop_percu = &paiement.montant - &du;
+        if trop_percu > BigDecimal::zero() {
+            trop_percus.push(TropPercu {
+                id_paiement: paiement.id_paiement,
+                id_resident: paiement.id_resident,
+                nom_prenom: resident.nom_prenom.clone(),
+                periode: paiement.periode,
+                jours_payes: paiement.jours_travail,
+                jours_dus,
+                montant_verse: paiement.montant.clone(),
+                montant_du: du,
+                trop_percu,
+            });
+        }
+    }
+
+    trop_percus
+}
+
+// Splits `total` into `nombre` installments of whole cents, the last one absorbing the remainder
+pub fn echeancier(total: &BigDecimal, nombre: i32) -> Vec<BigDecimal> {
+    if nombre <= 0 {
+        return Vec::new();
+    }
+
+    let echeance = (total / BigDecimal::from(nombre)).with_scale(2);
+    let mut echeances = vec![echeance.clone(); nombre as usize - 1];
+    echeances.push(arrondir(total - echeance * BigDecimal::from(nombre - 1)));
+    echeances
+}
+
+// Deducts the pending installments from a rappel of `disponible`, in order: each
+// one is split between the part the rappel covers and the remainder, carried over
+pub fn imputer_sur_rappel(disponible: &BigDecimal, echeances: &[BigDecimal]) -> Vec<(BigDecimal, BigDecimal)> {
+    let mut disponible = disponible.max(&BigDecimal::zero()).clone();
+
+    echeances
+        .iter()
+        .map(|echeance| {
+            let impute = echeance.min(&disponible).clone();
+            disponible -= &impute;
+            (impute.clone(), echeance - impute)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn date(annee: i32, mois: u32, jour: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(annee, mois, jour).unwrap()
+    }
+
+    fn dec(value: &str) -> BigDecimal {
+        BigDecimal::from_str(value).unwrap()
+    }
+
+    fn resident(id_resident: i32) -> ResidentPaie {
+        ResidentPaie {
+            id_resident,
+            nom_prenom: format!("Résident {}", id_resident),
+            date_debut: date(2024, 1, 1),
+            date_fin: None,
+            id_specialite: Some(1),
+            nombre_annees: 4,
+            nombre_enfants: 0,
+            nom_specialite: None,
+            nom_banque: None,
+        }
+    }
+
+    fn grille() -> Vec<Bareme> {
+        vec![Bareme {
+            id_bareme: 1,
+            annee_residanat: 1,
+            id_specialite: None,
+            nom_specialite: None,
+            salaire_base: dec("9000"),
+            indemnite_fonction: BigDecimal::zero(),
+            indemnite_logement: BigDecimal::zero(),
+            indemnite_transport: BigDecimal::zero(),
+            date_effet: date(2024, 1, 1),
+        }]
+    }
+
+    fn paiement(id_paiement: i32, id_resident: i32, montant: &str) -> PaiementVerse {
+        PaiementVerse {
+            id_paiement,
+            id_resident,
+            periode: Periode::new(2024, 6).unwrap(),
+            date_paiement: date(2024, 6, 30),
+            jours_travail: 30,
+            montant: dec(montant),
+        }
+    }
+
+    fn absence(id_resident: i32, date_debut: NaiveDate, date_fin: NaiveDate) -> Absence {
+        Absence {
+            id_absence: 0,
+            id_resident,
+            nom_resident: None,
+            type_absence: "absence".to_string(),
+            date_debut,
+            date_fin,
+            motif: None,
+        }
+    }
+
+    fn detecter(paiements: &[PaiementVerse], residents: &[ResidentPaie], absences: &[Absence]) -> Vec<TropPercu> {
+        detecter_trop_percus(paiements, residents, &grille(), &RegimeRetenues::default(), absences, &[])
+    }
+
+    #[test]
+    fn days_no_longer_due_are_overpaid() {
+        let mut parti = resident(3);
+        parti.date_fin = Some(date(2024, 5, 31));
+        let residents = vec![resident(1), resident(2), parti, resident(4)];
+        let absences = vec![absence(1, date(2024, 6, 11), date(2024, 6, 30)), absence(4, date(2024, 6, 21), date(2024, 6, 30))];
+        let paiements = vec![
+            paiement(10, 1, "9000"),
+            paiement(20, 2, "9000"),
+            paiement(30, 3, "9000"),
+            // Paid before payroll runs, under an older grid: the amount paid is scaled
+            paiement(40, 4, "8100"),
+            paiement(50, 99, "9000"),
+        ];
+
+        let trop_percus = detecter(&paiements, &residents, &absences);
+
+        let resume: Vec<(i32, i32, BigDecimal, BigDecimal)> = trop_percus
+            .iter()
+            .map(|t| (t.id_paiement, t.jours_dus, t.montant_du.clone(), t.trop_percu.clone()))
+            .collect();
+        assert_eq!(
+            resume,
+            vec![
+                (10, 10, dec("3000"), dec("6000")),
+                (30, 0, BigDecimal::zero(), dec("9000")),
+                (40, 20, dec("5400"), dec("2700")),
+            ]
+        );
+        assert_eq!(trop_percus[0].jours_payes, 30);
+        assert_eq!(trop_percus[0].montant_verse, dec("9000"));
+    }
+
+    #[test]
+    fn nothing_to_recover_when_the_days_paid_are_still_due() {
+        let residents = vec![resident(1), resident(2)];
+        // Resident 2 was paid for 20 days and is now due more: underpayments are not recovered
+        let mut sous_paye = paiement(20, 2, "6000");
+        sous_paye.jours_travail = 20;
+        let paiements = vec![paiement(10, 1, "9000"), sous_paye];
+
+        assert!(detecter(&paiements, &residents, &[]).is_empty());
+        assert!(detecter(&[], &residents, &[]).is_empty());
+    }
+
+    #[test]
+    fn installments_are_whole_cents_and_add_up_to_the_total() {
+        assert_eq!(echeancier(&dec("900"), 3), vec![dec("300"), dec("300"), dec("300")]);
+        assert_eq!(echeancier(&dec("1000"), 3), vec![dec("333.33"), dec("333.33"), dec("333.34")]);
+        assert_eq!(echeancier(&dec("0.05"), 2), vec![dec("0.02"), dec("0.03")]);
+        assert_eq!(echeancier(&dec("1234.56"), 1), vec![dec("1234.56")]);
+
+        let echeances = echeancier(&dec("6000.01"), 7);
+        assert_eq!(echeances.len(), 7);
+        assert!(echeances.iter().all(|echeance| *echeance == echeance.round(2)));
+        assert_eq!(echeances.iter().fold(BigDecimal::zero(), |total, e| total + e), dec("6000.01"));
+
+        assert!(echeancier(&dec("100"), 0).is_empty());
+        assert!(echeancier(&dec("100"), -2).is_empty());
+    }
+
+    #[test]
+    fn a_rappel_covers_installments_in_order_and_the_rest_is_carried_over() {
+        let echeances = vec![dec("300"), dec("300"), dec("300")];
+
+        assert_eq!(
+            imputer_sur_rappel(&dec("1000"), &echeances),
+            vec![(dec("300"), dec("0")), (dec("300"), dec("0")), (dec("300"), dec("0"))]
+        );
+        assert_eq!(
+            imputer_sur_rappel(&dec("450.50"), &echeances),
+            vec![(dec("300"), dec("0")), (dec("150.50"), dec("149.50")), (dec("0"), dec("300"))]
+        );
+        assert_eq!(
+            imputer_sur_rappel(&dec("-20"), &echeances[..1]),
+            vec![(dec("0"), dec("300"))]
+        );
+    }
+}
@@ -0,0 +1,158 @@
+use crate::models::Role;
+use serde::{Serialize, Deserialize};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum WorkflowError {
+    #[error("Statut de run de paie inconnu : {0}")]
+    UnknownStatus(String),
+    #[error("Passage de « {from} » à « {to} » impossible")]
+    InvalidTransition { from: &'static str, to: &'static str },
+    #[error("Votre rôle ne permet pas cette action")]
+    Forbidden,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum StatutRun {
+    Brouillon,
+    Valide,
+    Envoye,
+    Paye,
+}
+
+impl StatutRun {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            StatutRun::Brouillon => "brouillon",
+            StatutRun::Valide => "valide",
+            StatutRun::Envoye => "envoye",
+            StatutRun::Paye => "paye",
+        }
+    }
+
+    pub fn parse(value: &str) -> Result<Self, WorkflowError> {
+        match value {
+            "brouillon" => Ok(StatutRun::Brouillon),
+            "valide" => Ok(StatutRun::Valide),
+            "envoye" => Ok(StatutRun::Envoye),
+            "paye" => Ok(StatutRun::Paye),
+            other => Err(WorkflowError::UnknownStatus(other.to_string())),
+        }
+    }
+
+    // Payments of a run are frozen as soon as it is validated
+    pub fn est_verrouille(&self) -> bool {
+        *self != StatutRun::Brouillon
+    }
+
+    // Only the next step of the lifecycle is allowed, by the role responsible for it
+    pub fn transition(&self, cible: StatutRun, role: Role) -> Result<StatutRun, WorkflowError> {
+        let roles: &[Role] = match (self, cible) {
+            (StatutRun::Brouillon, StatutRun::Valide) => &[Role::Admin, Role::Validateur],
+            (StatutRun::Valide, StatutRun::Envoye) | (StatutRun::Envoye, StatutRun::Paye) => {
+                &[Role::Admin, Role::Tresorier]
+            }
+            _ => {
+                return Err(WorkflowError::InvalidTransition {
+                    from: self.as_str(),
+                    to: cible.as_str(),
+                })
+            }
+        };
+
+        if !roles.contains(&role) {
+            return Err(WorkflowError::Forbidden);
+        }
+
+        Ok(cible)
+    }
+}
+
+// Generating, regenerating and cancelling a draft run
+pub fn peut_preparer(role: Role) -> Result<(), WorkflowError> {
+    match role {
+        Role::Admin | Role::Gestionnaire => Ok(()),
+        _ => Err(WorkflowError::Forbidden),
+    }
+}
+
+// Ordering the transfers of a validated run, importing the bank's return files,
+// which settles each transfer, and reissuing the rejected ones
+pub fn peut_rapprocher(role: Role) -> Result<(), WorkflowError> {
+    match role {
+        Role::Admin | Role::Tresorier => Ok(()),
+        _ => Err(WorkflowError::Forbidden),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const STATUTS: [StatutRun; 4] = [StatutRun::Brouillon, StatutRun::Valide, StatutRun::Envoye, StatutRun::Paye];
+    const ROLES: [Role; 4] = [Role::Admin, Role::Gestionnaire, Role::Validateur, Role::Tresorier];
+
+    #[derive(Debug, PartialEq)]
+    enum Attendu {
+        Autorise,
+        Interdit,
+        Impossible,
+    }
+
+    #[test]
+    fn each_transition_is_reserved_to_its_roles() {
+        use Attendu::*;
+        use Role::*;
+        use StatutRun::*;
+
+        let attendu = |de: StatutRun, vers: StatutRun, role: Role| match (de, vers) {
+            (Brouillon, Valide) if matches!(role, Admin | Validateur) => Autorise,
+            (Valide, Envoye) | (Envoye, Paye) if matches!(role, Admin | Tresorier) => Autorise,
+            (Brouillon, Valide) | (Valide, Envoye) | (Envoye, Paye) => Interdit,
+            _ => Impossible,
+        };
+
+        for de in STATUTS {
+            for vers in STATUTS {
+                for role in ROLES {
+                    let obtenu = match de.transition(vers, role) {
+                        Ok(statut) => {
+                            assert_eq!(statut, vers);
+                            Autorise
+                        }
+                        Err(WorkflowError::Forbidden) => Interdit,
+                        Err(WorkflowError::InvalidTransition { from, to }) => {
+                            assert_eq!((from, to), (de.as_str(), vers.as_str()));
+                            Impossible
+                        }
+                        Err(e) => panic!("{:?}", e),
+                    };
+                    assert_eq!(obtenu, attendu(de, vers, role), "{:?} -> {:?} par {:?}", de, vers, role);
+                }
+            }
+        }
+
+        // No step can be skipped or undone, whatever the role
+        assert!(matches!(Brouillon.transition(Paye, Admin), Err(WorkflowError::InvalidTransition { .. })));
+        assert!(matches!(Paye.transition(Brouillon, Admin), Err(WorkflowError::InvalidTransition { .. })));
+        assert!(matches!(Valide.transition(Valide, Admin), Err(WorkflowError::InvalidTransition { .. })));
+    }
+
+    #[test]
+    fn preparing_and_reconciling_are_separate_duties() {
+        for role in ROLES {
+            assert_eq!(peut_preparer(role).is_ok(), matches!(role, Role::Admin | Role::Gestionnaire), "{:?}", role);
+            assert_eq!(peut_rapprocher(role).is_ok(), matches!(role, Role::Admin | Role::Tresorier), "{:?}", role);
+        }
+    }
+
+    #[test]
+    fn statuses_round_trip_and_lock_once_validated() {
+        for statut in STATUTS {
+            assert_eq!(StatutRun::parse(statut.as_str()).unwrap(), statut);
+            assert_eq!(statut.est_verrouille(), statut != StatutRun::Brouillon);
+        }
+        assert!(matches!(StatutRun::parse("annule"), Err(WorkflowError::UnknownStatus(s)) if s == "annule"));
+    }
+}
@@ -0,0 +1,112 @@
+import React, { useState } from "react";
+import {
+  Button, Dialog, DialogTitle, DialogContent, DialogActions, FormControlLabel, Checkbox,
+  RadioGroup, Radio, FormLabel, Snackbar, Alert, Box,
+} from "@mui/material";
+import { invoke } from "@tauri-apps/api/tauri";
+import { save } from "@tauri-apps/api/dialog";
+
+// Exports a list page ("residents", "specialites", "paiements" or "rappels") with
+// the page's current filters, letting the user pick the format and the columns
+const ExportButton = ({ liste, filtre, colonnes, nomFichier }) => {
+  const [dialogOpen, setDialogOpen] = useState(false);
+  const [format, setFormat] = useState("xlsx");
+  const [virgule, setVirgule] = useState(true);
+  const [choisies, setChoisies] = useState(colonnes.map((colonne) => colonne.field));
+  const [snackbarOpen, setSnackbarOpen] = useState(false);
+  const [snackbarMessage, setSnackbarMessage] = useState("");
+  const [snackbarType, setSnackbarType] = useState("success");
+
+  const toggleColonne = (field) => {
+    setChoisies((actuelles) =>
+      actuelles.includes(field) ? actuelles.filter((f) => f !== field) : [...actuelles, field]
+    );
+  };
+
+  const handleExport = async () => {
+    try {
+      const chemin = await save({
+        defaultPath: `${nomFichier}.${format}`,
+        filters: [format === "csv" ? { name: "CSV", extensions: ["csv"] } : { name: "Excel", extensions: ["xlsx"] }],
+      });
+      if (!chemin) {
+        return;
+      }
+      const nombre = await invoke("export_list", {
+        liste,
+        filtre,
+        // Keep the order of the page's columns
+        colonnes: colonnes.map((colonne) => colonne.field).filter((field) => choisies.includes(field)),
+        format,
+        virgule,
+        chemin,
+      });
+      setDialogOpen(false);
+      setSnackbarType("success");
+      setSnackbarMessage(`${nombre} ligne(s) exportée(s) dans ${chemin}`);
+    } catch (error) {
+      setSnackbarType("error");
+      setSnackbarMessage(`Échec de l'export : ${error}`);
+    }
+    setSnackbarOpen(true);
+  };
+
+  return (
+    <>
+      <Button variant="contained" color="secondary" size="medium" onClick={() => setDialogOpen(true)} sx={{ ml: 2 }}>
+        Exporter
+      </Button>
+
+      <Dialog open={dialogOpen} onClose={() => setDialogOpen(false)}>
+        <DialogTitle>Exporter la liste</DialogTitle>
+        <DialogContent>
+          <FormLabel>Format</FormLabel>
+          <RadioGroup row value={format} onChange={(e) => setFormat(e.target.value)}>
+            <FormControlLabel value="xlsx" control={<Radio />} label="Excel (.xlsx)" />
+            <FormControlLabel value="csv" control={<Radio />} label="CSV" />
+          </RadioGroup>
+          {format === "csv" && (
+            <>
+              <FormLabel>Séparateur décimal</FormLabel>
+              <RadioGroup row value={virgule ? "virgule" : "point"} onChange={(e) => setVirgule(e.target.value === "virgule")}>
+                <FormControlLabel value="virgule" control={<Radio />} label="Virgule (1234,50)" />
+                <FormControlLabel value="point" control={<Radio />} label="Point (1234.50)" />
+              </RadioGroup>
+            </>
+          )}
+          <FormLabel>Colonnes</FormLabel>
+          <Box display="flex" flexDirection="column">
+            {colonnes.map((colonne) => (
+              <FormControlLabel
+                key={colonne.field}
+                control={<Checkbox checked={choisies.includes(colonne.field)} onChange={() => toggleColonne(colonne.field)} />}
+                label={colonne.label}
+              />
+            ))}
+          </Box>
+        </DialogContent>
+        <DialogActions>
+          <Button onClick={() => setDialogOpen(false)} color="primary">
+            Annuler
+          </Button>
+          <Button onClick={handleExport} color="secondary" variant="contained" disabled={choisies.length === 0}>
+            Exporter
+          </Button>
+        </DialogActions>
+      </Dialog>
+
+      <Snackbar
+        open={snackbarOpen}
+        autoHideDuration={6000}
+        onClose={() => setSnackbarOpen(false)}
+        anchorOrigin={{ vertical: "bottom", horizontal: "center" }}
+      >
+        <Alert onClose={() => setSnackbarOpen(false)} severity={snackbarType}>
+          {snackbarMessage}
+        </Alert>
+      </Snackbar>
+    </>
+  );
+};
+
+export default ExportButton;
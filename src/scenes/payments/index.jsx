@@ -17,12 +17,11 @@ import {
 } from "@mui/material";
 import { DataGrid } from "@mui/x-data-grid";
 import { invoke } from "@tauri-apps/api/tauri";
+import { open, save } from "@tauri-apps/api/dialog";
 import { tokens } from "../../theme";
 import Header from "../../components/Header";
+import ExportButton from "../../components/ExportButton";
 import SearchIcon from "@mui/icons-material/Search";
-import { saveAs } from 'file-saver';
-import ExcelJS from 'exceljs';
-import n2words from 'n2words';
 
 
 const Payments = () => {
@@ -56,220 +55,182 @@ const Payments = () => {
 
   const handleGenerateClick = async () => {
     try {
-      // Load header template
-      const headerResponse = await fetch('/templates/headerOV.xlsx');
-      if (!headerResponse.ok) {
-        throw new Error('Failed to fetch template file');
+      const chemin = await save({
+        defaultPath: `OV ${selectedMonth} ${selectedYear}.xlsx`,
+        filters: [{ name: "Excel", extensions: ["xlsx"] }],
+      });
+      if (!chemin) {
+        return;
       }
-      const headerArrayBuffer = await headerResponse.arrayBuffer();
-  
-      // Load workbook and modify existing worksheet
-      const workbook = new ExcelJS.Workbook();
-      await workbook.xlsx.load(headerArrayBuffer);
-      const worksheet = workbook.getWorksheet(1); // Assuming data starts on the first sheet
-  
-  
-      // Start inserting data from row 20
-      let startDataRow = 20;
-      let currentRow = startDataRow;
-      let currentPageHeight = 385; // Height of header on the first page
-      const maxPageHeight = 11 * 72; // Maximum page height for letter size (11 inches) in points
-      const rowHeightPoints = 24; // Standard row height in points
-      let endDataRow = 0;
-  
-      let previousTotalARow = startDataRow - 1; // Track the previous TOTAL A REPORTER row
-      let previousTotalRow = startDataRow - 2; // Track the previous TOTAL REPORTE row
-  
-      filteredPayments.forEach(payment => {
-        const row = worksheet.addRow([
-          payment.nom_resident,
-          payment.rib,
-          payment.nom_banque,
-          parseFloat(payment.montant) // Ensure montant is parsed as float
-        ]);
-  
-        // Apply styles to cells in the current row
-        row.eachCell((cell, colNumber) => {
-          switch (colNumber) {
-            case 1: // nom_resident
-              cell.font = { name: 'Times New Roman', size: 16, bold: true, italic: true };
-              cell.alignment = { vertical: 'middle', horizontal: 'left', wrapText: true };
-              break;
-            case 2: // rib
-              cell.font = { name: 'Times New Roman', size: 16, bold: true };
-              cell.alignment = { vertical: 'middle', horizontal: 'center', wrapText: true };
-              break;
-            case 3: // nom_banque
-              cell.font = { name: 'Times New Roman', size: 14, bold: true, italic: true };
-              cell.alignment = { vertical: 'middle', horizontal: 'left', wrapText: true };
-              break;
-            case 4: // montant
-              cell.font = { name: 'Times New Roman', size: 14, bold: true };
-              cell.alignment = { vertical: 'middle', horizontal: 'center', wrapText: true };
-              cell.numFmt = '#,##0.00'; // Format as number with two decimal places
-              break;
-            default:
-              break;
-        }
-        // Set height of the row to standard row height
-        worksheet.getRow(currentRow).height = rowHeightPoints;
-        // Add border to each cell
-        cell.border = {
-          top: { style: 'thin' },
-          left: { style: 'thin' },
-          bottom: { style: 'thin' },
-          right: { style: 'thin' }
-        };
+      await invoke("generate_ov", {
+        annee: selectedYear,
+        mois: months.indexOf(selectedMonth) + 1,
+        idBanque: null,
+        chemin,
       });
-        // Add row height to current page height
-        currentPageHeight += rowHeightPoints;
-        currentRow++;
+      setSnackbarMessage(`Ordre de virement enregistré dans ${chemin}`);
+      setSnackbarOpen(true);
+    } catch (error) {
+      setSnackbarMessage(`Échec de la génération de l'ordre de virement : ${error}`);
+      setSnackbarOpen(true);
+    }
+  };
   
-        // Check if inserting another row would exceed the maximum page height
-      if (currentPageHeight + rowHeightPoints> maxPageHeight) {
-        // Update endDataRow to the last data entry before "TOTAL A REPORTER"
-        endDataRow = currentRow - 1;
-
-        // Insert TOTAL A REPORTER row
-        insertTotalRow(worksheet, currentRow, 'TOTAL A REPORTER', startDataRow, endDataRow);
-
-        // Move to the next row for TOTAL REPORTE
-        currentRow++;
-
-        startDataRow = currentRow;
-        insertTotalRow(worksheet, currentRow, 'TOTAL REPORTE', startDataRow, previousTotalRow);
-
-        // Move to the next row after TOTAL REPORTE
-        currentRow++;
-
-        // Reset current page height to header height for new page
-        currentPageHeight = 100; // Assuming header height is 385 points
-
-        // Update previousTotalARow and previousTotalRow for the next page
-        previousTotalARow = currentRow - 3; // Subtracting 3 because we added 3 rows (1 TOTAL A REPORTER, 1 TOTAL REPORTE, 1 blank row)
-        previousTotalRow = currentRow - 2;
+  const handleOvPerBankClick = async () => {
+    try {
+      const dossier = await open({ directory: true, title: "Dossier des ordres de virement" });
+      if (!dossier) {
+        return;
       }
-            });
-  
-      // Ensure the worksheet has at least 45 lines including header
-      const minimumRows = 45;
-      const totalRows = worksheet.rowCount;
-      const rowsToAdd = minimumRows - totalRows; // Calculate rows to add
-  
-      // Add rows if necessary to reach minimum rows
-      if (rowsToAdd > 0) {
-        for (let i = 0; i < rowsToAdd; i++) {
-          worksheet.addRow([]);
-        }
+      const bilan = await invoke("generate_ov_par_banque", {
+        annee: selectedYear,
+        mois: months.indexOf(selectedMonth) + 1,
+        dossier,
+      });
+      setSnackbarMessage(
+        `${bilan.ordres.length} ordre(s) de virement générés dans ${dossier}, total ${bilan.total} MAD`
+      );
+      setSnackbarOpen(true);
+    } catch (error) {
+      setSnackbarMessage(`Échec de la génération des ordres de virement : ${error}`);
+      setSnackbarOpen(true);
+    }
+  };
+
+  const handlePayslipsClick = async (combine) => {
+    try {
+      const dossier = await open({ directory: true, title: "Dossier des bulletins de paie" });
+      if (!dossier) {
+        return;
       }
-      // After the loop for adding static data rows
+      const fichiers = await invoke("generate_payslips", {
+        annee: selectedYear,
+        mois: months.indexOf(selectedMonth) + 1,
+        dossier,
+        combine,
+      });
+      setSnackbarMessage(`${fichiers.length} fichier(s) de bulletins générés dans ${dossier}`);
+      setSnackbarOpen(true);
+    } catch (error) {
+      setSnackbarMessage(`Échec de la génération des bulletins : ${error}`);
+      setSnackbarOpen(true);
+    }
+  };
 
-      // Insert "Total Général" row
-      insertTotalRow(worksheet, currentRow, 'TOTAL GENERAL', startDataRow, currentRow - 1);
-      let totalGeneralValue = 0;
-      for (let i = startDataRow; i < currentRow; i++) {
-        const cellValue = worksheet.getCell(`D${i}`).value;
-        if (typeof cellValue === 'number') {
-          totalGeneralValue += cellValue;
+  const handleTransferFilesClick = async (xml) => {
+    try {
+      const mois = months.indexOf(selectedMonth) + 1;
+      let bilan;
+      if (xml) {
+        const chemin = await save({
+          defaultPath: `PAIE${selectedYear}${String(mois).padStart(2, "0")}.xml`,
+          filters: [{ name: "ISO 20022", extensions: ["xml"] }],
+        });
+        if (!chemin) {
+          return;
+        }
+        bilan = await invoke("export_pain001", { annee: selectedYear, mois, chemin });
+      } else {
+        const dossier = await open({ directory: true, title: "Dossier des fichiers de virement" });
+        if (!dossier) {
+          return;
         }
+        bilan = await invoke("export_bank_files", { annee: selectedYear, mois, dossier });
       }
-  
-      // Convert the manually calculated total general value to French words
-      const letterVersion = n2words(totalGeneralValue, { lang: 'fr' });
-
-      // Assuming B18:D18 are already merged, set the value in B18
-      const totalCell = worksheet.getCell(`A18`);
-      totalCell.value = totalGeneralValue;
-      const mergedCell = worksheet.getCell(`B18`);
-      mergedCell.value = letterVersion;
-  
-
+      setSnackbarMessage(
+        `${bilan.nombre_virements} virement(s), total ${bilan.total} MAD, ${bilan.banques.length} banque(s)`
+      );
+      setSnackbarOpen(true);
+    } catch (error) {
+      setSnackbarMessage(`Échec de l'export des virements : ${error}`);
+      setSnackbarOpen(true);
+    }
+  };
 
-      // Increment currentRow to move to the next row for the footer
-      currentRow++;
-  
-      // First row of the footer: Merge cells A, B, C, and D and add text
-      worksheet.mergeCells(`A${currentRow}:D${currentRow}`);
-      const firstFooterRow = worksheet.getCell(`A${currentRow}`);
-      firstFooterRow.value = `Le règlement des indemnités de fonction et allocations familiales des résidents au titre du mois de ${selectedMonth} ${selectedYear}`;
-      firstFooterRow.font = { name: 'Times New Roman', bold: true, size: 14 };
-      firstFooterRow.alignment = { vertical: 'middle', horizontal: 'center', wrapText: true };
-  
-      // Set height of the first footer row to 39 pixels
-      worksheet.getRow(currentRow).height = 39;
-  
-      // Second row of the footer: Individual cells for "L'ORDONNATEUR" and merged cells for "LE FONDE DE POUVOIRS"
-      currentRow++; // Move to the next row
-      const secondFooterRowA = worksheet.getCell(`A${currentRow}`);
-      secondFooterRowA.value = "L'ORDONNATEUR";
-      secondFooterRowA.font = { name: 'Arial', bold: true, size: 16 };
-      secondFooterRowA.alignment = { vertical: 'middle', horizontal: 'center', wrapText: true };
-  
-      // Merge cells C and D in the second footer row
-      worksheet.mergeCells(`C${currentRow}:D${currentRow}`);
-      const secondFooterRowCD = worksheet.getCell(`C${currentRow}`);
-      secondFooterRowCD.value = "LE FONDE DE POUVOIRS";
-      secondFooterRowCD.font = { name: 'Arial', bold: true, size: 16 };
-      secondFooterRowCD.alignment = { vertical: 'middle', horizontal: 'center', wrapText: true };
-  
-      // Save the modified workbook
-      const fileName = `OV ${selectedMonth} ${selectedYear}.xlsx`;
-      const buffer = await workbook.xlsx.writeBuffer();
-      const blob = new Blob([buffer], { type: 'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet' });
-      saveAs(blob, fileName);
+  // Rejected payments of the month are reissued with the residents' current RIB,
+  // corrected beforehand from the residents page
+  const handleReissueClick = async () => {
+    try {
+      const rejetes = filteredPayments.filter((payment) => payment.statut_virement === "rejete");
+      if (rejetes.length === 0) {
+        setSnackbarMessage("Aucun virement rejeté pour ce mois");
+        setSnackbarOpen(true);
+        return;
+      }
+      const chemin = await save({
+        defaultPath: `OV réémission ${selectedMonth} ${selectedYear}.xlsx`,
+        filters: [{ name: "Excel", extensions: ["xlsx"] }],
+      });
+      if (!chemin) {
+        return;
+      }
+      const ordre = await invoke("generate_ov_reemission", {
+        origine: "paiement",
+        reemissions: rejetes.map((payment) => ({ id: payment.id_paiement })),
+        chemin,
+      });
+      setReloadTrigger((trigger) => !trigger);
+      setSnackbarMessage(
+        `OV n° ${ordre.numero}/${ordre.exercice} : ${ordre.nombre_virements} virement(s) réémis, total ${ordre.total} MAD`
+      );
+      setSnackbarOpen(true);
     } catch (error) {
-      console.error('Error generating Excel file from template:', error);
-      throw error;
+      setSnackbarMessage(`Échec de la réémission des virements : ${error}`);
+      setSnackbarOpen(true);
     }
   };
-  
-  // Function to insert custom styling row for TOTAL A REPORTER and TOTAL REPORTE
-  function insertTotalRow(worksheet, rowNumber, text, startDataRow, endDataRow) {
-    // Merge cells A, B, C, and D for the total row
-    worksheet.mergeCells(`A${rowNumber}:C${rowNumber}`);
-    const totalRowA = worksheet.getCell(`A${rowNumber}`);
-    totalRowA.value = text;
-    totalRowA.font = { name: 'Times New Roman', bold: true, size: 12};
-    totalRowA.alignment = { vertical: 'middle', horizontal: 'center', wrapText: true };
-  
-    // Set background fill color for the total row
-    totalRowA.fill = {
-      type: 'pattern',
-      pattern: 'solid',
-      fgColor: { argb: 'FFBFBFBF' }, 
-    };
-  
-    // Cell D for the total row with reference to previous total cell
-    const totalRowD = worksheet.getCell(`D${rowNumber}`);
-    if (text === 'TOTAL A REPORTER'|| text === 'TOTAL GENERAL') {
-      // Sum from D[startDataRow] to D[endDataRow]
-      totalRowD.value = { formula: `SUM(D${startDataRow}:D${endDataRow})` };
-    } else if (text === 'TOTAL REPORTE') {
-      // Reference the cell above it
-      totalRowD.value = { formula: `D${rowNumber - 1}` };
+
+  // The XML is only written once every blocking anomaly is fixed; the workbook
+  // lists them on a second sheet
+  const handleDeclarationClick = async (format) => {
+    try {
+      const chemin = await save({
+        defaultPath: `Etat 9421 ${selectedYear}.${format}`,
+        filters: [format === "xml" ? { name: "XML", extensions: ["xml"] } : { name: "Excel", extensions: ["xlsx"] }],
+      });
+      if (!chemin) {
+        return;
+      }
+      const rapport = await invoke("export_tax_declaration", { annee: selectedYear, format, chemin });
+      const bloquantes = rapport.anomalies.filter((anomalie) => anomalie.bloquante);
+      if (!rapport.fichier) {
+        setSnackbarMessage(
+          `Déclaration ${selectedYear} non générée, ${bloquantes.length} anomalie(s) bloquante(s) : ` +
+            bloquantes.map((anomalie) => `${anomalie.nom_prenom} (${anomalie.message})`).join(", ")
+        );
+      } else {
+        setSnackbarMessage(
+          `Déclaration ${selectedYear} : ${rapport.nombre_salaries} salarié(s), IR ${rapport.ir} MAD, ${rapport.anomalies.length} anomalie(s)`
+        );
+      }
+      setSnackbarOpen(true);
+    } catch (error) {
+      setSnackbarMessage(`Échec de la déclaration des salaires : ${error}`);
+      setSnackbarOpen(true);
     }
-    totalRowD.font = { name: 'Arial', bold: true ,size: 14};
-    totalRowD.alignment = { vertical: 'middle', horizontal: 'center', wrapText: true };
-    totalRowD.numFmt = '#,##0.00'; // Format as number with two decimal places
-  
-    // Set border for the total row
-    totalRowA.border = {
-      top: { style: 'thin' },
-      left: { style: 'thin' },
-      bottom: { style: 'thin' },
-      right: { style: 'thin' }
-    };
+  };
 
-    totalRowD.border = {
-      top: { style: 'thin' },
-      left: { style: 'thin' },
-      bottom: { style: 'thin' },
-      right: { style: 'thin' }
-    };
+  const handleBankReturnClick = async () => {
+    try {
+      const path = await open({
+        title: "Fichier retour de la banque",
+        filters: [{ name: "Retour banque", extensions: ["csv", "xlsx", "txt", "dat"] }],
+      });
+      if (!path) {
+        return;
+      }
+      const rapport = await invoke("import_bank_return", { path, dryRun: false });
+      setReloadTrigger((trigger) => !trigger);
+      setSnackbarMessage(
+        `${rapport.payes} payé(s), ${rapport.rejetes} rejeté(s), ${rapport.non_rapprochees.length} ligne(s) non rapprochée(s), ${rapport.erreurs.length} erreur(s)`
+      );
+      setSnackbarOpen(true);
+    } catch (error) {
+      setSnackbarMessage(`Échec de l'import du retour banque : ${error}`);
+      setSnackbarOpen(true);
+    }
+  };
 
-    // Set height of the total row to standard row height in points
-    worksheet.getRow(rowNumber).height = 33.75; // Adjust height as needed
-}
 
   
   
@@ -362,6 +323,15 @@ const Payments = () => {
         return `${formattedValue} DH`;
       },
     },
+    {
+      field: "statut_virement",
+      headerName: "Virement",
+      flex: 1,
+      renderCell: (params) =>
+        ({ en_attente: "En attente", paye: "Payé", reemis: "Réémis", rejete: `Rejeté${params.row.motif_rejet ? ` : ${params.row.motif_rejet}` : ""}` })[
+          params.value
+        ] ?? params.value,
+    },
   ];
   
 
@@ -373,6 +343,21 @@ const Payments = () => {
 
   
 
+
+  const colonnesExport = [
+    { field: "nom_resident", label: "Résident" },
+    { field: "date_paiement", label: "Date de paiement" },
+    { field: "jours_travail", label: "Jours travaillés" },
+    { field: "salaire_brut", label: "Salaire brut" },
+    { field: "total_retenues", label: "Total des retenues" },
+    { field: "allocations_familiales", label: "Allocations familiales" },
+    { field: "montant", label: "Montant total" },
+    { field: "rib", label: "RIB" },
+    { field: "nom_banque", label: "Banque" },
+    { field: "statut_virement", label: "Virement" },
+    { field: "motif_rejet", label: "Motif du rejet" },
+  ];
+
   return (
     <Box m="20px">
       <Header title="PAIEMENTS" />
@@ -395,6 +380,16 @@ const Payments = () => {
             <SearchIcon />
           </IconButton>
         </Box>
+        <ExportButton
+          liste="paiements"
+          filtre={{
+            recherche: searchInput,
+            annee: selectedYear ? parseInt(selectedYear, 10) : null,
+            mois: selectedMonth ? months.indexOf(selectedMonth) + 1 : null,
+          }}
+          colonnes={colonnesExport}
+          nomFichier="Paiements"
+        />
         <FormControl sx={{ ml: 2, minWidth: 120 }}>
           <InputLabel>Mois</InputLabel>
           <Select
@@ -431,6 +426,93 @@ const Payments = () => {
       >
         Générer Ordre de Virement
       </Button>
+      <Button
+        variant="contained"
+        color="secondary"
+        size="medium"
+        onClick={handleOvPerBankClick}
+        sx={{ ml: 2 }}
+        disabled={!hasPaymentsForMonth}
+      >
+        OV par banque
+      </Button>
+      <Button
+        variant="contained"
+        color="secondary"
+        size="medium"
+        onClick={() => handlePayslipsClick(false)}
+        sx={{ ml: 2 }}
+        disabled={!hasPaymentsForMonth}
+      >
+        Bulletins de paie
+      </Button>
+      <Button
+        variant="contained"
+        color="secondary"
+        size="medium"
+        onClick={() => handlePayslipsClick(true)}
+        sx={{ ml: 2 }}
+        disabled={!hasPaymentsForMonth}
+      >
+        Bulletins (fichier unique)
+      </Button>
+      <Button
+        variant="contained"
+        color="secondary"
+        size="medium"
+        onClick={() => handleTransferFilesClick(false)}
+        sx={{ ml: 2 }}
+        disabled={!hasPaymentsForMonth}
+      >
+        Fichiers de virement
+      </Button>
+      <Button
+        variant="contained"
+        color="secondary"
+        size="medium"
+        onClick={() => handleTransferFilesClick(true)}
+        sx={{ ml: 2 }}
+        disabled={!hasPaymentsForMonth}
+      >
+        Virement XML
+      </Button>
+      <Button
+        variant="contained"
+        color="secondary"
+        size="medium"
+        onClick={handleBankReturnClick}
+        sx={{ ml: 2 }}
+      >
+        Retour banque
+      </Button>
+      <Button
+        variant="contained"
+        color="secondary"
+        size="medium"
+        onClick={handleReissueClick}
+        sx={{ ml: 2 }}
+        disabled={!hasPaymentsForMonth}
+      >
+        Réémettre les rejets
+      </Button>
+      <Button
+        variant="contained"
+        color="secondary"
+        size="medium"
+        onClick={() => handleDeclarationClick("xlsx")}
+        sx={{ ml: 2 }}
+      >
+        État 9421 (Excel)
+      </Button>
+      <Button
+        variant="contained"
+        color="secondary"
+        size="medium"
+        onClick={() => handleDeclarationClick("xml")}
+        sx={{ ml: 2 }}
+      >
+        État 9421 (XML)
+      </Button>
 
     </Box>
 
@@ -14,12 +14,11 @@ import {
 } from "@mui/material";
 import { DataGrid } from "@mui/x-data-grid";
 import { invoke } from "@tauri-apps/api/tauri";
+import { save } from "@tauri-apps/api/dialog";
 import { tokens } from "../../theme";
 import Header from "../../components/Header";
+import ExportButton from "../../components/ExportButton";
 import SearchIcon from "@mui/icons-material/Search";
-import { saveAs } from 'file-saver';
-import ExcelJS from 'exceljs';
-import n2words from 'n2words';
 
 const Rappels = () => {
   const theme = useTheme();
@@ -44,121 +43,27 @@ const Rappels = () => {
 
   const handleGenerateClick = async () => {
     try {
-      if (filteredRappels.length === 0) {
-        throw new Error('Aucun rappel filtré à exporter.');
-      }
-  
-      const headerResponse = await fetch('/templates/headerOV-RAP.xlsx');
-      if (!headerResponse.ok) {
-        throw new Error('Failed to fetch template file');
-      }
-      const headerArrayBuffer = await headerResponse.arrayBuffer();
-  
-      const workbook = new ExcelJS.Workbook();
-      await workbook.xlsx.load(headerArrayBuffer);
-  
-      // Ensure worksheet exists and is loaded correctly
-      const worksheet = workbook.getWorksheet('OV-RAP (14)'); // Replace 'Sheet1' with actual sheet name/index
-      if (!worksheet) {
-        throw new Error('Worksheet not found in the workbook.');
-      }
-  
-      let currentRow = 19; // Start adding data from row 19
-      let totalNetAPayer = 0;
-  
-      // Insert data rows
-      for (const item of filteredRappels) {
-        if (item.exercice === "Total") {
-          const row = worksheet.getRow(currentRow);
-          row.height = 60.75;
-          row.getCell(2).value = { richText: [{ text: item.nom_resident, font: { size: 26, italic: true, name: 'Times New Roman' } }] };
-          row.getCell(3).value = { richText: [{ text: item.rib, font: { size: 26, italic: true, name: 'Times New Roman' } }] };
-          row.getCell(4).value = { richText: [{ text: item.nom_banque, font: { size: 26, italic: true, name: 'Times New Roman' } }] };
-          row.getCell(5).value = parseFloat(item.net_a_payer).toFixed(2); // Set as numeric value directly
-          row.getCell(5).numFmt = '#,##0.00'; // Numeric format
-          row.font = { size: 26, italic: true, name: 'Times New Roman' }; // Apply font style
-          applyBordersToRow(row); // Apply borders to the entire row
-          row.alignment = { vertical: 'middle', horizontal: 'left', wrapText: true }; // Wrap text and align
-  
-          // Accumulate total net_a_payer
-          totalNetAPayer += parseFloat(item.net_a_payer);
-          currentRow++;
-        }
-      }
-  
-      // Insert total row after data rows
-      const totalRow = worksheet.getRow(currentRow);
-      totalRow.height = 60.75; // Set row height
-      totalRow.getCell(2).value = { richText: [{ text: 'TOTAL', font: { size: 26, italic: true, name: 'Times New Roman' } }] };
-      totalRow.getCell(3).value = null; // Merging cells, so leave other cells null
-      totalRow.getCell(4).value = null;
-      totalRow.getCell(5).value = parseFloat(totalNetAPayer.toFixed(2)); // Set as numeric value directly
-      totalRow.getCell(5).numFmt = '#,##0.00'; // Numeric format
-      totalRow.font = { size: 26, italic: true, name: 'Times New Roman' }; // Apply font style
-      applyBordersToRow(totalRow); // Apply borders to the entire row
-      worksheet.mergeCells(currentRow, 2, currentRow, 4); // Merge cells B, C, D for 'TOTAL'
-  
-      const totalInWords = n2words(totalNetAPayer, { lang: 'fr' });
-
-      // Insert the total value and its French word version in B17 and C17 respectively
-      const summaryRow = worksheet.getRow(17);
-      summaryRow.getCell(2).value = parseFloat(totalNetAPayer.toFixed(2));
-      summaryRow.getCell(3).value = totalInWords; // French word version
-      summaryRow.font = { size: 23, name: 'Times New Roman' }; 
-  
-  
-      // Insert additional rows
-      currentRow++; // Move to the next row after 'TOTAL'
-  
-      // Merge cells B to E and insert text in the subsequent row
-      const additionalRow1 = worksheet.getRow(currentRow);
-      additionalRow1.getCell(2).value = "RAPPEL DE L'INDEMNITE DE FONCTION DES MEDECINS RESIDENTS DU  AU ";
-      worksheet.mergeCells(currentRow, 2, currentRow, 5); // Merge cells B to E
-      additionalRow1.alignment = { vertical: 'middle', horizontal: 'center', wrapText: true }; // Center align and wrap text
-      additionalRow1.font = { size: 26, italic: true, name: 'Times New Roman' }; // Apply font style
-      additionalRow1.height = 60.75;
-      currentRow++;
-  
-      // Insert 'L'ORDONNATEUR' and 'LE FONDE DE POUVOIRS' in the next row
-      const additionalRow2 = worksheet.getRow(currentRow);
-      additionalRow2.getCell(2).value = "L'ORDONNATEUR";
-      additionalRow2.getCell(4).value = "LE FONDE DE POUVOIRS";
-      additionalRow2.alignment = { vertical: 'middle', horizontal: 'center', wrapText: true }; // Center align and wrap text
-      additionalRow2.getCell(2).font = { size: 26, bold: true, name: 'Times New Roman' }; // Apply font style (size 26)
-      additionalRow2.getCell(4).font = { size: 20, bold: true, name: 'Times New Roman' }; // Apply font style (size 20)
-      additionalRow2.height = 70;
-      currentRow++;
-  
-      // Clear borders after the additional rows
-      for (let i = currentRow; i <= worksheet.rowCount; i++) {
-        const clearRow = worksheet.getRow(i);
-        clearRow.eachCell((cell) => {
-          cell.border = {};
-        });
+      const chemin = await save({
+        defaultPath: `OV-RAP ${selectedMonth} ${selectedYear}.xlsx`,
+        filters: [{ name: "Excel", extensions: ["xlsx"] }],
+      });
+      if (!chemin) {
+        return;
       }
-  
-      const fileName = `OV-RAP ${selectedMonth} ${selectedYear}.xlsx`;
-  
-      // Save the modified workbook with the generated file name
-      const buffer = await workbook.xlsx.writeBuffer();
-      const blob = new Blob([buffer], { type: 'application/vnd.openxmlformats-officedocument.spreadsheetml.sheet' });
-      saveAs(blob, fileName);
+      await invoke("generate_ov_rappel", {
+        annee: parseInt(selectedYear, 10),
+        mois: monthLabels.indexOf(selectedMonth) + 1,
+        idBanque: null,
+        chemin,
+      });
+      setSnackbarMessage(`Ordre de virement enregistré dans ${chemin}`);
+      setSnackbarOpen(true);
     } catch (error) {
-      console.error('Error generating Excel file from template:', error);
-      setSnackbarMessage(`Erreur lors de la génération du fichier Excel: ${error.message}`);
+      setSnackbarMessage(`Erreur lors de la génération du fichier Excel: ${error}`);
       setSnackbarOpen(true);
     }
   };
   
-  const applyBordersToRow = (row) => {
-    // Apply borders to all cells in the row
-    row.eachCell((cell) => {
-      cell.border = {
-        top: { style: 'thin' },
-        left: { style: 'thin' },
-        bottom: { style: 'thin' },
-        right: { style: 'thin' },
-      };
     });
   };
   
@@ -362,6 +267,19 @@ const Rappels = () => {
     return totalHeight < maxHeight ? totalHeight : maxHeight;
   };
 
+
+  const colonnesExport = [
+    { field: "nom_resident", label: "Résident" },
+    { field: "exercice", label: "Exercice" },
+    { field: "duree_rappel", label: "Durée du rappel" },
+    { field: "montant", label: "Montant" },
+    { field: "retenue_recouvrement", label: "Retenue de recouvrement" },
+    { field: "date_generation", label: "Date de génération" },
+    { field: "rib", label: "RIB" },
+    { field: "nom_banque", label: "Banque" },
+    { field: "statut_virement", label: "Virement" },
+  ];
+
   return (
     <Box m="20px">
       <Header title="RAPPELS" />
@@ -384,6 +302,16 @@ const Rappels = () => {
             <SearchIcon />
           </IconButton>
         </Box>
+        <ExportButton
+          liste="rappels"
+          filtre={{
+            recherche: searchInput,
+            annee: selectedYear ? parseInt(selectedYear, 10) : null,
+            mois: selectedMonth ? monthLabels.indexOf(selectedMonth) + 1 : null,
+          }}
+          colonnes={colonnesExport}
+          nomFichier="Rappels"
+        />
         <FormControl sx={{ ml: 2, minWidth: 120 }}>
           <InputLabel>Mois</InputLabel>
           <Select
@@ -4,8 +4,10 @@ import {
 } from "@mui/material";
 import { DataGrid } from "@mui/x-data-grid";
 import { invoke } from "@tauri-apps/api/tauri";
+import { save } from "@tauri-apps/api/dialog";
 import { tokens } from "../../theme";
 import Header from "../../components/Header";
+import ExportButton from "../../components/ExportButton";
 import SearchIcon from "@mui/icons-material/Search";
 
 const Residents = () => {
@@ -27,6 +29,8 @@ const Residents = () => {
     rib: "",
     nombre_enfants: 0,
     id_banque: "",
+    cin: "",
+    adresse: "",
   });
   const [snackbarOpen, setSnackbarOpen] = useState(false);
   const [snackbarMessage, setSnackbarMessage] = useState("");
@@ -34,6 +38,11 @@ const Residents = () => {
   const [formMode, setFormMode] = useState("add");
   const [confirmationOpen, setConfirmationOpen] = useState(false);
   const [residentToDelete, setResidentToDelete] = useState(null);
+  const [attestationResident, setAttestationResident] = useState(null);
+  const [attestationYear, setAttestationYear] = useState(new Date().getFullYear() - 1);
+  // "salaire" for the salary attestation, otherwise the id of a work certificate template
+  const [attestationType, setAttestationType] = useState("salaire");
+  const [certificateTemplates, setCertificateTemplates] = useState([]);
 
   const handleAddClick = () => {
     setFormMode("add");
@@ -51,6 +60,8 @@ const Residents = () => {
         rib: resident.rib,
         nombre_enfants: resident.nombre_enfants,
         id_banque: resident.id_banque,
+        cin: resident.cin || "",
+        adresse: resident.adresse || "",
       });
       setFormMode("edit");
       setOpen(true);
@@ -86,6 +97,46 @@ const Residents = () => {
     setConfirmationOpen(false);
   };
 
+  const handleAttestationClick = (id_resident) => {
+    setAttestationResident(residents.find((resident) => resident.id_resident === id_resident) || null);
+  };
+
+  const handleAttestationGenerate = async () => {
+    const salaire = attestationType === "salaire";
+    try {
+      const chemin = await save({
+        defaultPath: salaire
+          ? `Attestation de salaire ${attestationYear} - ${attestationResident.nom_prenom}.pdf`
+          : `Attestation de travail - ${attestationResident.nom_prenom}.pdf`,
+        filters: [{ name: "PDF", extensions: ["pdf"] }],
+      });
+      if (!chemin) {
+        return;
+      }
+      if (salaire) {
+        await invoke("generate_salary_attestation", {
+          idResident: attestationResident.id_resident,
+          annee: Number(attestationYear),
+          chemin,
+        });
+        setSnackbarMessage(`Attestation enregistrée dans ${chemin}`);
+      } else {
+        const emise = await invoke("generate_work_certificate", {
+          idResident: attestationResident.id_resident,
+          idModele: attestationType,
+          chemin,
+        });
+        setSnackbarMessage(`Attestation de travail N° ${emise.numero}/${emise.exercice} enregistrée dans ${chemin}`);
+      }
+      setAttestationResident(null);
+      setSnackbarMessageType("success");
+    } catch (error) {
+      setSnackbarMessage(`Échec de la génération de l'attestation : ${error}`);
+      setSnackbarMessageType("error");
+    }
+    setSnackbarOpen(true);
+  };
+
   const handleCancelDelete = () => {
     setResidentToDelete(null);
     setConfirmationOpen(false);
@@ -100,6 +151,8 @@ const Residents = () => {
       rib: "",
       nombre_enfants: 0,
       id_banque: "",
+      cin: "",
+      adresse: "",
     });
   };
 
@@ -138,7 +191,10 @@ const Residents = () => {
         await invoke("add_resident", { resident });
         const getResponse = await invoke("get_resident_id", { nomPrenom: newResident.nom_prenom }); 
         console.log(getResponse);
-        await invoke("generate_rappel", { residentId: getResponse });
+        await invoke("generate_rappel", {
+          residentId: getResponse,
+          exercice: new Date().getFullYear(),
+        });
         setSnackbarMessageType("success");
         setSnackbarMessage("Resident ajouté avec succès!");
       } else if (formMode === "edit") {
@@ -181,6 +237,14 @@ const Residents = () => {
         setLoading(false); // Set loading to false even if there's an error
       }
     };
+    const fetchCertificateTemplates = async () => {
+      try {
+        const data = await invoke("get_modeles_attestation");
+        setCertificateTemplates(data);
+      } catch (error) {
+        console.error("Failed to fetch certificate templates", error);
+      }
+    };
     const fetchBanks = async () => {
       try {
         const data = await invoke("get_banques");
@@ -191,6 +255,7 @@ const Residents = () => {
     };
   
     fetchBanks();
+    fetchCertificateTemplates();
     fetchResidents();
     fetchSpecialties();
 
@@ -223,7 +288,7 @@ const Residents = () => {
     {
       field: "actions",
       headerName: "Actions",
-      width: 200,
+      width: 310,
       renderCell: (params) => (
         <Box>
           <Button
@@ -240,14 +305,36 @@ const Residents = () => {
             sx={{ bgcolor: '#f44336', color: '#fff', '&:hover': { bgcolor: '#d32f2f' } }}
             size="small"
             onClick={() => handleDeleteClick(params.row.id_resident)}
+            style={{ marginRight: 8 }}
           >
             Supprimer
           </Button>
+          <Button
+            variant="contained"
+            color="secondary"
+            size="small"
+            onClick={() => handleAttestationClick(params.row.id_resident)}
+          >
+            Attestation
+          </Button>
         </Box>
       )
     }
   ];
 
+
+  const colonnesExport = [
+    { field: "nom_prenom", label: "Nom et prénom" },
+    { field: "date_debut", label: "Date de début" },
+    { field: "date_fin", label: "Date de fin" },
+    { field: "nom_specialite", label: "Spécialité" },
+    { field: "rib", label: "RIB" },
+    { field: "nom_banque", label: "Banque" },
+    { field: "nombre_enfants", label: "Nombre d'enfants" },
+    { field: "cin", label: "CIN" },
+    { field: "adresse", label: "Adresse" },
+  ];
+
   return (
     <Box m="20px">
       <Header title="RÉSIDENTS" />
@@ -270,6 +357,12 @@ const Residents = () => {
             <SearchIcon />
           </IconButton>
         </Box>
+        <ExportButton
+          liste="residents"
+          filtre={{ recherche: searchInput }}
+          colonnes={colonnesExport}
+          nomFichier="Résidents"
+        />
         <Button
           variant="contained"
           color="secondary"
@@ -448,6 +541,29 @@ const Residents = () => {
             onChange={handleInputChange}
             autoComplete="off"
           />
+          <TextField
+            margin="dense"
+            id="cin"
+            name="cin"
+            label="CIN"
+            type="text"
+            fullWidth
+            value={newResident.cin || ""}
+            onChange={handleInputChange}
+            autoComplete="off"
+          />
+          <TextField
+            margin="dense"
+            id="adresse"
+            name="adresse"
+            label="Adresse"
+            type="text"
+            fullWidth
+            multiline
+            value={newResident.adresse || ""}
+            onChange={handleInputChange}
+            autoComplete="street-address"
+          />
         </DialogContent>
         <DialogActions>
           <Button onClick={handleClose} color="secondary">
@@ -476,6 +592,47 @@ const Residents = () => {
           </Button>
         </DialogActions>
       </Dialog>
+      <Dialog open={attestationResident !== null} onClose={() => setAttestationResident(null)}>
+        <DialogTitle>Attestation</DialogTitle>
+        <DialogContent>
+          <Box mb={2}>
+            <strong>{attestationResident ? attestationResident.nom_prenom : ""}</strong>
+          </Box>
+          <TextField
+            select
+            label="Document"
+            value={attestationType}
+            onChange={(e) => setAttestationType(e.target.value)}
+            fullWidth
+            margin="dense"
+          >
+            <MenuItem value="salaire">Attestation de salaire</MenuItem>
+            {certificateTemplates.map((modele) => (
+              <MenuItem key={modele.id_modele} value={modele.id_modele}>
+                Attestation de travail : {modele.nom}
+              </MenuItem>
+            ))}
+          </TextField>
+          {attestationType === "salaire" && (
+            <TextField
+              label="Année"
+              type="number"
+              value={attestationYear}
+              onChange={(e) => setAttestationYear(e.target.value)}
+              fullWidth
+              margin="dense"
+            />
+          )}
+        </DialogContent>
+        <DialogActions>
+          <Button onClick={() => setAttestationResident(null)} color="primary">
+            Annuler
+          </Button>
+          <Button onClick={handleAttestationGenerate} color="secondary" variant="contained">
+            Générer
+          </Button>
+        </DialogActions>
+      </Dialog>
     </Box>
 
       <Snackbar
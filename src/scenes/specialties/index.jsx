@@ -5,6 +5,7 @@ import { DataGrid } from "@mui/x-data-grid";
 import { invoke } from "@tauri-apps/api/tauri";
 import { tokens } from "../../theme";
 import Header from "../../components/Header";
+import ExportButton from "../../components/ExportButton";
 import SearchIcon from "@mui/icons-material/Search";
 
 const Specialties = () => {
@@ -51,7 +52,9 @@ const Specialties = () => {
     } catch (error) {
       console.error("Failed to delete specialty", error);
       setSnackbarMessageType("error");
-      setSnackbarMessage("Échec de la suppression de la spécialité");
+      setSnackbarMessage(
+        error.message || "Échec de la suppression de la spécialité"
+      );
       setSnackbarOpen(true);
     } finally {
       setConfirmationOpen(false);
@@ -187,6 +190,12 @@ const Specialties = () => {
     return totalHeight < maxHeight ? totalHeight : maxHeight;
   };
 
+
+  const colonnesExport = [
+    { field: "nom", label: "Spécialité" },
+    { field: "nombre_annees", label: "Durée (années)" },
+  ];
+
   return (
     <Box m="20px">
       <Header title="SPÉCIALITÉS" />
@@ -214,6 +223,12 @@ const Specialties = () => {
             <SearchIcon />
           </IconButton>
         </Box>
+        <ExportButton
+          liste="specialites"
+          filtre={{ recherche: searchInput }}
+          colonnes={colonnesExport}
+          nomFichier="Spécialités"
+        />
         <Button
           variant="contained"
           color="secondary"